@@ -103,6 +103,14 @@ impl App {
 
             let work_status = self.get_work_status();
 
+            if let Some(notification) = self
+                .current_state
+                .as_mut()
+                .and_then(|state| state.take_notification())
+            {
+                self.active_notification = Some(notification);
+            }
+
             // Expire notification if its duration has elapsed
             if let Some(ref notif) = self.active_notification
                 && notif.is_expired()
@@ -1,41 +1,186 @@
+use std::collections::BTreeMap;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, atomic::AtomicBool};
 
 use clap::{Error, Parser, Subcommand};
+use color_eyre::eyre::eyre;
+use signal_hook::consts::{SIGINT, SIGTERM};
 use tracing::{error, info};
 
+use crate::core::defs;
+use crate::core::feed::feedentry::EntrySummary;
+use crate::core::feed::feedentry::FeedEntry;
+use crate::core::feed::fetcher;
+use crate::core::library::daemon::{Daemon, UpdateLock};
 use crate::core::library::data::config::Config;
-use crate::core::library::data::opml;
-use crate::core::library::feeditem::FeedItem;
-use crate::core::library::feedlibrary::FeedLibrary;
+use crate::core::library::data::{newsboat, opml};
+use crate::core::library::feeditem::{
+    Auth, FeedItem, HttpOptions, MAX_UPDATE_INTERVAL_MINS, Secret,
+};
+use crate::core::library::feedlibrary::{FeedLibrary, ImportResult, ImportStatus};
 use crate::logging;
 
+mod entries;
+mod output;
+
+use entries::{EntryArgs, EntryChange};
+use output::{
+    DeleteRow, DirsRow, FeedResult, FeedRow, Outcome, Output, OutputFormat, TaggedFeedRow,
+};
+
 #[derive(Parser)]
 #[command(name = "bulletty")]
 #[command(version, about = "Your TUI feed reader", long_about = None)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
+
+    /// Output format of the commands
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Human)]
+    pub format: OutputFormat,
+}
+
+/// Request options for feeds that need credentials. Secret values can be given as `env:NAME`
+/// or `cmd:COMMAND` so only the reference is stored in the library.
+#[derive(clap::Args, Default)]
+pub struct HttpArgs {
+    /// User agent to send instead of bulletty's own
+    #[arg(long)]
+    user_agent: Option<String>,
+    /// Extra request header, as "Name: value". Can be repeated
+    #[arg(long = "header", value_name = "HEADER")]
+    headers: Vec<String>,
+    /// Cookie header value
+    #[arg(long)]
+    cookie: Option<String>,
+    /// User name for HTTP basic authentication
+    #[arg(long)]
+    username: Option<String>,
+    /// Password for HTTP basic authentication
+    #[arg(long, requires = "username")]
+    password: Option<String>,
+    /// Bearer token
+    #[arg(long, conflicts_with = "username")]
+    token: Option<String>,
+}
+
+impl HttpArgs {
+    fn to_options(&self) -> color_eyre::Result<Option<HttpOptions>> {
+        let mut headers = BTreeMap::new();
+        for header in self.headers.iter() {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| eyre!("Invalid header {:?}, expected \"Name: value\"", header))?;
+            headers.insert(name.trim().to_string(), Secret(value.trim().to_string()));
+        }
+
+        let auth = match (&self.username, &self.token) {
+            (Some(username), _) => Some(Auth::Basic {
+                username: username.to_string(),
+                password: self.password.clone().map(Secret),
+            }),
+            (None, Some(token)) => Some(Auth::Bearer {
+                token: Secret(token.to_string()),
+            }),
+            (None, None) => None,
+        };
+
+        let options = HttpOptions {
+            user_agent: self.user_agent.clone(),
+            cookie: self.cookie.clone().map(Secret),
+            headers,
+            auth,
+        };
+
+        Ok((options != HttpOptions::default()).then_some(options))
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// List all feeds and categories
-    List,
+    List {
+        /// Only list the entries tagged with this category
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// List entries, newest first
+    Entries {
+        #[command(flatten)]
+        filter: EntryArgs,
+    },
+    /// Print an entry as Markdown
+    Show {
+        /// The entry file path, relative to the library, or the entry URL
+        entry: String,
+        /// Render the Markdown with colors instead
+        #[arg(long)]
+        render: bool,
+    },
+    /// Mark entries as read
+    MarkRead {
+        /// Entry file paths, relative to the library, or entry URLs
+        #[arg(required = true)]
+        entries: Vec<String>,
+    },
+    /// Mark entries as unread
+    MarkUnread {
+        /// Entry file paths, relative to the library, or entry URLs
+        #[arg(required = true)]
+        entries: Vec<String>,
+    },
+    /// Add entries to Read Later
+    Later {
+        /// Entry file paths, relative to the library, or entry URLs
+        #[arg(required = true)]
+        entries: Vec<String>,
+        /// Remove them from Read Later instead
+        #[arg(long)]
+        remove: bool,
+    },
     /// Add new feed
     Add {
-        /// The ATOM/RSS feed URL
+        /// The ATOM/RSS feed URL, a local file path, or `exec:<command>` to read a command's output
         url: String,
         #[arg()]
         /// The category to add under, if none is passed, it will be added to General
         category: Option<String>,
+        #[command(flatten)]
+        http: HttpArgs,
     },
     /// Update all feeds
     Update,
+    /// Keep updating feeds on a schedule until stopped
+    Daemon {
+        /// Minutes between updates of feeds without their own `update_interval_mins`
+        #[arg(
+            long,
+            default_value_t = 60,
+            value_parser = clap::value_parser!(u64).range(1..=MAX_UPDATE_INTERVAL_MINS)
+        )]
+        interval: u64,
+    },
     /// Delete a feed
     Delete {
         /// The feed identifier (can be url, title or slug)
         ident: String,
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+        /// Only match feeds whose url, title or slug is exactly the identifier
+        #[arg(long)]
+        exact: bool,
+        /// Only match feeds in this category
+        #[arg(long)]
+        category: Option<String>,
+        /// Delete every matching feed instead of asking which one
+        #[arg(long)]
+        all_matches: bool,
+        /// Print what would be deleted, without deleting anything
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Show important directories
     Dirs {
@@ -44,16 +189,81 @@ pub enum Commands {
     },
     /// Import a list of feed sources through OPML
     Import {
-        /// The filepath of the OPML file
-        opml_file: String,
+        /// The filepath of the OPML file, or of newsboat's urls file. For newsboat, its usual
+        /// location is used when none is given
+        file: Option<PathBuf>,
+        /// The reader the file comes from
+        #[arg(long, value_enum, default_value_t = ImportSource::Opml)]
+        from: ImportSource,
+        /// newsboat's cache.db, to bring over which articles were read or flagged
+        #[arg(long)]
+        cache: Option<PathBuf>,
+        /// Print which feeds would be added, without fetching or adding them
+        #[arg(long)]
+        dry_run: bool,
+        /// Also import feeds read from local files or commands (`exec:`). Only use it with files
+        /// you trust, as those commands run on every update
+        #[arg(long)]
+        allow_local: bool,
     },
     /// Export all your sources to an OPML file
     Export {
         /// The filepath of the OPML file
         opml_file: String,
+        /// Also write each feed's bulletty settings, as attributes other readers ignore
+        #[arg(long)]
+        with_settings: bool,
+    },
+    /// Export entries to a static HTML site or an EPUB book
+    ExportEntries {
+        /// The directory of the site, or the EPUB file
+        output: PathBuf,
+        /// What to export to
+        #[arg(long = "to", value_enum, default_value_t = ExportTarget::Html)]
+        target: ExportTarget,
+        /// Title of the site or book
+        #[arg(long, default_value = "bulletty")]
+        title: String,
+        #[command(flatten)]
+        filter: EntryArgs,
+    },
+    /// Write a digest of recent entries, by category and feed
+    Digest {
+        /// Write the digest to this file instead of the standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Format of the digest
+        #[arg(long = "to", value_enum, default_value_t = DigestFormat::Markdown)]
+        target: DigestFormat,
+        /// Title of the digest
+        #[arg(long, default_value = "bulletty digest")]
+        title: String,
+        /// Mark the entries in the digest as read
+        #[arg(long)]
+        mark_read: bool,
+        #[command(flatten)]
+        filter: EntryArgs,
     },
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum DigestFormat {
+    Markdown,
+    Html,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum ExportTarget {
+    Html,
+    Epub,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, PartialEq)]
+pub enum ImportSource {
+    Opml,
+    Newsboat,
+}
+
 #[derive(Subcommand)]
 pub enum DirsCommands {
     /// Show or update the library path
@@ -65,23 +275,106 @@ pub enum DirsCommands {
     Logs,
 }
 
-pub fn run_main_cli(cli: Cli) -> color_eyre::Result<()> {
+pub fn run_main_cli(cli: Cli) -> color_eyre::Result<ExitCode> {
     info!("Initializing CLI");
 
-    match &cli.command {
-        Some(Commands::List) => command_list(&cli),
-        Some(Commands::Add { url, category }) => command_add(&cli, url, category),
+    let outcome = match &cli.command {
+        Some(Commands::List { tag }) => command_list(&cli, tag),
+        Some(Commands::Entries { filter }) => entries::command_entries(&cli, filter),
+        Some(Commands::Show { entry, render }) => entries::command_show(&cli, entry, *render),
+        Some(Commands::MarkRead { entries }) => {
+            entries::command_change_entries(&cli, entries, EntryChange::Read)
+        }
+        Some(Commands::MarkUnread { entries }) => {
+            entries::command_change_entries(&cli, entries, EntryChange::Unread)
+        }
+        Some(Commands::Later { entries, remove }) => {
+            let change = if *remove {
+                EntryChange::NotReadLater
+            } else {
+                EntryChange::ReadLater
+            };
+            entries::command_change_entries(&cli, entries, change)
+        }
+        Some(Commands::Add {
+            url,
+            category,
+            http,
+        }) => command_add(&cli, url, category, http),
         Some(Commands::Update) => command_update(&cli),
-        Some(Commands::Delete { ident }) => command_delete(&cli, ident),
+        Some(Commands::Daemon { interval }) => command_daemon(&cli, *interval),
+        Some(Commands::Delete {
+            ident,
+            yes,
+            exact,
+            category,
+            all_matches,
+            dry_run,
+        }) => command_delete(
+            &cli,
+            ident,
+            &DeleteArgs {
+                yes: *yes,
+                exact: *exact,
+                category: category.clone(),
+                all_matches: *all_matches,
+                dry_run: *dry_run,
+            },
+        ),
         Some(Commands::Dirs { subcmd }) => command_dirs(&cli, subcmd),
-        Some(Commands::Import { opml_file }) => command_import(&cli, opml_file),
-        Some(Commands::Export { opml_file }) => command_export(&cli, opml_file),
-        None => Ok(()),
-    }
+        Some(Commands::Import {
+            file,
+            from,
+            cache,
+            dry_run,
+            allow_local,
+        }) => command_import(&cli, file, *from, cache, *dry_run, *allow_local),
+        Some(Commands::Export {
+            opml_file,
+            with_settings,
+        }) => command_export(&cli, opml_file, *with_settings),
+        Some(Commands::ExportEntries {
+            output,
+            target,
+            title,
+            filter,
+        }) => entries::command_export_entries(&cli, output, *target, title, filter),
+        Some(Commands::Digest {
+            output,
+            target,
+            title,
+            mark_read,
+            filter,
+        }) => entries::command_digest(&cli, output, *target, title, *mark_read, filter),
+        None => Ok(Outcome::Success),
+    }?;
+
+    Ok(outcome.into())
 }
 
-fn command_list(_cli: &Cli) -> color_eyre::Result<()> {
+fn command_list(cli: &Cli, tag: &Option<String>) -> color_eyre::Result<Outcome> {
     let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    if let Some(tag) = tag {
+        return command_list_tagged(&library, &out, tag);
+    }
+
+    if !out.is_human() {
+        let mut rows = vec![];
+        for category in library.feedcategories.iter() {
+            for feed in category.feeds.iter() {
+                let unread = library
+                    .data
+                    .get_unread_feed(&category.title, &feed.slug)
+                    .unwrap_or(0);
+                rows.push(FeedRow::new(feed, unread));
+            }
+        }
+
+        out.rows(&rows)?;
+        return Ok(Outcome::Success);
+    }
 
     println!("Feeds Registered\n\n");
     for category in library.feedcategories.iter() {
@@ -92,39 +385,229 @@ fn command_list(_cli: &Cli) -> color_eyre::Result<()> {
         println!();
     }
 
-    Ok(())
+    Ok(Outcome::Success)
 }
 
-fn command_add(_cli: &Cli, url: &str, category: &Option<String>) -> color_eyre::Result<()> {
+fn command_list_tagged(
+    library: &FeedLibrary,
+    out: &Output,
+    tag: &str,
+) -> color_eyre::Result<Outcome> {
+    let mut rows = vec![];
+
+    if out.is_human() {
+        println!("Entries tagged '{tag}'\n\n");
+    }
+
+    for category in library.feedcategories.iter() {
+        for feed in category.feeds.iter() {
+            let entries: Vec<FeedEntry> = library
+                .get_feed_entries(category, feed)?
+                .into_iter()
+                .filter(|e| e.has_category(tag))
+                .collect();
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            if !out.is_human() {
+                let unread = library
+                    .data
+                    .get_unread_feed(&category.title, &feed.slug)
+                    .unwrap_or(0);
+                rows.push(TaggedFeedRow {
+                    feed: FeedRow::new(feed, unread),
+                    tag: tag.to_string(),
+                    tagged: entries.len(),
+                });
+                continue;
+            }
+
+            println!("{}/{}", category.title, feed.title);
+            for entry in entries.iter() {
+                println!(
+                    "\t-> {} {}",
+                    entry.date.with_timezone(&chrono::Local).format("%Y-%m-%d"),
+                    entry.title
+                );
+            }
+            println!();
+        }
+    }
+
+    out.rows(&rows)?;
+
+    Ok(Outcome::Success)
+}
+
+fn command_add(
+    cli: &Cli,
+    url: &str,
+    category: &Option<String>,
+    http: &HttpArgs,
+) -> color_eyre::Result<Outcome> {
     let mut library = FeedLibrary::new();
-    match library.add_feed_from_url(url, category) {
+    let out = Output::new(cli.format);
+    let http = http.to_options()?;
+
+    let mut candidates = match library.discover_feeds(url, http.as_ref()) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            error!("{err}");
+            eprintln!("{err}");
+            return Ok(Outcome::Failed);
+        }
+    };
+
+    // Scripts get the first feed found instead of a prompt
+    let candidate = if candidates.len() == 1 || !out.is_human() {
+        candidates.swap_remove(0)
+    } else {
+        println!(
+            "There were {} feeds found at that address:",
+            candidates.len()
+        );
+        for (i, candidate) in candidates.iter().enumerate() {
+            println!(
+                "\t-> {}) {} ({}) {}",
+                i + 1,
+                candidate.feed.title,
+                candidate.format,
+                candidate.feed.feed_url
+            );
+        }
+        print!("Which one would you like to add? ");
+        io::stdout().flush()?;
+
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice)?;
+
+        let normalized_input = choice.trim();
+
+        match normalized_input.parse::<usize>() {
+            Ok(ind) if ind >= 1 && ind <= candidates.len() => candidates.swap_remove(ind - 1),
+            _ => {
+                info!("Invalid input received: {normalized_input}");
+                eprintln!("Invalid input received: {normalized_input}");
+                return Ok(Outcome::Failed);
+            }
+        }
+    };
+
+    // The options were given for the address typed, so a feed found on another host doesn't
+    // keep them
+    let http = match http {
+        Some(_) if !fetcher::same_origin(url, &candidate.feed.feed_url) => {
+            eprintln!(
+                "The feed is on another host than {url}, so its request options weren't kept"
+            );
+            None
+        }
+        http => http,
+    };
+
+    match library.add_feed_candidate(candidate, category, http) {
         Ok(feed) => {
             info!("Feed added: {}", feed.title);
-            println!("Feed added: {}", feed.title);
+            if out.is_human() {
+                println!("Feed added: {}", feed.title);
+            }
+            out.record(&FeedResult::new(&feed, "added"))?;
+            Ok(Outcome::Success)
         }
         Err(err) => {
             error!("{err}");
-            println!("{err}");
+            eprintln!("{err}");
+            Ok(Outcome::Failed)
         }
     }
-
-    Ok(())
 }
 
-fn command_update(_cli: &Cli) -> color_eyre::Result<()> {
+fn command_update(cli: &Cli) -> color_eyre::Result<Outcome> {
     let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    let Some(_lock) = UpdateLock::acquire(&library.data.path)? else {
+        info!("Another update is running");
+        eprintln!("Another update is running");
+        return Ok(Outcome::Failed);
+    };
+
+    let mut new_entries = vec![];
+    let mut results = vec![];
 
     for category in library.feedcategories.iter() {
         for feed in category.feeds.iter() {
             info!("Updating {}", feed.title);
-            println!("Updating {}", feed.title);
-            library
-                .data
-                .update_feed_entries(&category.title, feed, None)?;
+            if out.is_human() {
+                println!("Updating {}", feed.title);
+            }
+
+            match library.data.update_feed_entries(
+                library.fetcher.as_ref(),
+                &category.title,
+                feed,
+                None,
+            ) {
+                Ok(entries) => {
+                    results.push(FeedResult {
+                        new_entries: entries.len(),
+                        ..FeedResult::new(feed, "updated")
+                    });
+                    new_entries.extend(
+                        entries
+                            .iter()
+                            .map(|entry| EntrySummary::new(&library.data.path, feed, entry)),
+                    );
+                }
+                Err(e) => {
+                    error!("Something happened when updating {}: {:?}", feed.title, e);
+                    if out.is_human() {
+                        eprintln!("Couldn't update {}: {}", feed.title, e);
+                    }
+                    results.push(FeedResult {
+                        error: Some(e.to_string()),
+                        ..FeedResult::new(feed, "failed")
+                    });
+                }
+            }
         }
     }
 
-    Ok(())
+    library.settings.hooks.run(&new_entries);
+    out.rows(&results)?;
+
+    if results.iter().any(|r| r.error.is_some()) {
+        Ok(Outcome::Partial)
+    } else {
+        Ok(Outcome::Success)
+    }
+}
+
+fn command_daemon(_cli: &Cli, interval: u64) -> color_eyre::Result<Outcome> {
+    let library = FeedLibrary::new();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(&stop))?;
+    }
+
+    println!(
+        "Updating feeds every {interval} minutes, status in {}",
+        library.data.path.join(defs::DATA_DAEMON_STATUS).display()
+    );
+
+    let mut daemon = Daemon::new(
+        &library.data.path,
+        library.fetcher.clone(),
+        library.settings.hooks.clone(),
+        chrono::Duration::minutes(interval as i64),
+    );
+
+    daemon.run(&stop)?;
+
+    Ok(Outcome::Success)
 }
 
 fn confirm_delete(title: &str) -> Result<bool, Error> {
@@ -138,27 +621,50 @@ fn confirm_delete(title: &str) -> Result<bool, Error> {
     Ok(matches!(normalized_input.as_str(), "y" | "yes"))
 }
 
-fn command_delete(_cli: &Cli, ident: &str) -> color_eyre::Result<()> {
+struct DeleteArgs {
+    yes: bool,
+    exact: bool,
+    category: Option<String>,
+    all_matches: bool,
+    dry_run: bool,
+}
+
+fn command_delete(cli: &Cli, ident: &str, args: &DeleteArgs) -> color_eyre::Result<Outcome> {
     let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
 
-    let matches: Vec<&FeedItem> = library.get_matching_feeds(ident);
+    let matches: Vec<&FeedItem> = if args.exact {
+        library.get_exact_feeds(ident)
+    } else {
+        library.get_matching_feeds(ident)
+    }
+    .into_iter()
+    .filter(|feed| {
+        args.category
+            .as_ref()
+            .is_none_or(|category| feed.category.to_lowercase() == category.to_lowercase())
+    })
+    .collect();
     let matches_len = matches.len();
 
-    match matches_len {
+    // Prompts would be mixed with the records, so scripts have to say what they want
+    let interactive = out.is_human() && !args.yes;
+
+    let chosen: Vec<&FeedItem> = match matches_len {
         0 => {
             info!("No matching feeds exist");
-            println!("No matching feeds exist");
+            eprintln!("No matching feeds exist");
+            return Ok(Outcome::NotFound);
         }
-        1 => {
-            let matched = matches[0];
-            if confirm_delete(&matched.title)? {
-                library.delete_feed(&matched.slug, &matched.category)?;
-                info!("Feed deleted: {}", &matched.title);
-                println!("Feed deleted: {}", &matched.title);
-            } else {
-                info!("Feed was not deleted: {}", &matched.title);
-                println!("Feed was not deleted: {}", &matched.title);
+        1 => matches,
+        _ if args.all_matches || args.dry_run => matches,
+        _ if !interactive => {
+            eprintln!("There were {matches_len} feeds found with that identifier:");
+            for feed in matches.iter() {
+                eprintln!("\t-> {}/{}", &feed.category, &feed.title);
             }
+            eprintln!("Use --exact, --category or --all-matches to pick which ones to delete");
+            return Ok(Outcome::Failed);
         }
         _ => {
             println!("There were {} feeds found with that identifier:", {
@@ -177,138 +683,373 @@ fn command_delete(_cli: &Cli, ident: &str) -> color_eyre::Result<()> {
             let normalized_input = choice.trim();
 
             match normalized_input.parse::<usize>() {
-                Ok(ind) => {
-                    if ind >= 1 && ind <= matches_len {
-                        let title =
-                            format!("{}/{}", &matches[ind - 1].category, &matches[ind - 1].title);
-
-                        if confirm_delete(&title)? {
-                            library
-                                .delete_feed(&matches[ind - 1].slug, &matches[ind - 1].category)?;
-                            info!("Feed deleted: {}", &matches[ind - 1].title);
-                            println!("Feed deleted: {}", &matches[ind - 1].title);
-                        } else {
-                            info!("Feed was not deleted: {}", &title);
-                            println!("Feed was not deleted: {}", &title);
-                        }
-                    } else {
-                        info!("Invalid input received: {ind}");
-                        println!("Invalid input received: {ind}");
-                    }
-                }
-                Err(_) => {
+                Ok(ind) if ind >= 1 && ind <= matches_len => vec![matches[ind - 1]],
+                _ => {
                     info!("Invalid input received: {normalized_input}");
-                    println!("Invalid input received: {normalized_input}");
+                    eprintln!("Invalid input received: {normalized_input}");
+                    return Ok(Outcome::Failed);
                 }
             }
         }
+    };
+
+    let mut rows = vec![];
+    for feed in chosen.iter() {
+        rows.push(DeleteRow {
+            category: feed.category.clone(),
+            title: feed.title.clone(),
+            slug: feed.slug.clone(),
+            feed_url: feed.feed_url.clone(),
+            status: "would delete",
+            entries: library
+                .data
+                .count_feed_entries(&feed.category, &feed.slug)?,
+            read_later: library.data.feed_read_later(&feed.category, &feed.slug)?,
+        });
+    }
+
+    if args.dry_run || (interactive && chosen.len() > 1) {
+        if out.is_human() {
+            print_delete_rows(&rows, args.dry_run);
+        }
+
+        if args.dry_run {
+            out.rows(&rows)?;
+            return Ok(Outcome::Success);
+        }
     }
 
-    Ok(())
+    if !args.yes && !out.is_human() {
+        eprintln!(
+            "Pass --yes to delete without confirmation, or --dry-run to see what would be deleted"
+        );
+        return Ok(Outcome::Failed);
+    }
+
+    let title = match chosen.as_slice() {
+        [feed] if matches_len > 1 => format!("{}/{}", &feed.category, &feed.title),
+        [feed] => feed.title.clone(),
+        _ => format!("these {} feeds", chosen.len()),
+    };
+
+    let confirmed = args.yes || confirm_delete(&title)?;
+
+    for (feed, row) in chosen.iter().zip(rows.iter_mut()) {
+        if confirmed {
+            library.delete_feed(&feed.slug, &feed.category)?;
+            info!("Feed deleted: {}", &feed.title);
+            if out.is_human() {
+                println!("Feed deleted: {}", &feed.title);
+            }
+            row.status = "deleted";
+        } else {
+            row.status = "kept";
+        }
+    }
+
+    if !confirmed {
+        info!("Feed was not deleted: {}", &title);
+        if out.is_human() {
+            println!("Feed was not deleted: {}", &title);
+        }
+    }
+
+    out.rows(&rows)?;
+
+    Ok(Outcome::Success)
 }
 
-fn command_dirs(_cli: &Cli, subcmd: &Option<DirsCommands>) -> color_eyre::Result<()> {
+fn print_delete_rows(rows: &[DeleteRow], dry_run: bool) {
+    if dry_run {
+        println!("These feeds would be deleted:");
+    } else {
+        println!("These feeds will be deleted:");
+    }
+
+    for row in rows {
+        println!(
+            "\t-> {}/{}: {} entries, {} in Read Later",
+            row.category,
+            row.title,
+            row.entries,
+            row.read_later.len()
+        );
+        for later in row.read_later.iter() {
+            println!("\t\t{later}");
+        }
+    }
+}
+
+fn command_dirs(cli: &Cli, subcmd: &Option<DirsCommands>) -> color_eyre::Result<Outcome> {
+    let out = Output::new(cli.format);
+
     match subcmd {
-        Some(DirsCommands::Library { path }) => command_dirs_library(path),
-        Some(DirsCommands::Logs) => command_dirs_logs(),
+        Some(DirsCommands::Library { path }) => command_dirs_library(&out, path),
+        Some(DirsCommands::Logs) => command_dirs_logs(&out),
         None => {
             let config = Config::new();
             let library_path = config.datapath;
+            let logs_path = logging::logging_dir();
+
+            if !out.is_human() {
+                out.record(&DirsRow {
+                    library: Some(library_path.to_string_lossy().to_string()),
+                    logs: logs_path.map(|p| p.to_string_lossy().to_string()),
+                })?;
+                return Ok(Outcome::Success);
+            }
 
             println!("bulletty directories");
             println!("\t-> Library: {}", library_path.to_string_lossy());
 
-            if let Some(logs_path) = logging::logging_dir() {
+            if let Some(logs_path) = logs_path {
                 println!("\t-> Logs:    {}", logs_path.to_string_lossy());
             }
 
-            Ok(())
+            Ok(Outcome::Success)
         }
     }
 }
 
-fn command_dirs_library(path: &Option<PathBuf>) -> color_eyre::Result<()> {
+fn command_dirs_library(out: &Output, path: &Option<PathBuf>) -> color_eyre::Result<Outcome> {
     let mut config = Config::new();
 
     match path {
         Some(new_path) => {
             if !new_path.exists() {
-                println!(
+                eprintln!(
                     "Error: path '{}' does not exist",
                     new_path.to_string_lossy()
                 );
-                std::process::exit(1);
+                return Ok(Outcome::Failed);
             }
 
             if !new_path.is_dir() {
-                println!(
+                eprintln!(
                     "Error: path '{}' is not a directory",
                     new_path.to_string_lossy()
                 );
-                std::process::exit(1);
+                return Ok(Outcome::Failed);
             }
 
-            let absolute_path = new_path.canonicalize().unwrap_or_else(|e| {
-                println!(
-                    "Error: failed to resolve path '{}': {}",
-                    new_path.to_string_lossy(),
-                    e
-                );
-                std::process::exit(1);
-            });
+            let absolute_path = match new_path.canonicalize() {
+                Ok(path) => path,
+                Err(e) => {
+                    eprintln!(
+                        "Error: failed to resolve path '{}': {}",
+                        new_path.to_string_lossy(),
+                        e
+                    );
+                    return Ok(Outcome::Failed);
+                }
+            };
 
             config.datapath = absolute_path.clone();
             config.save();
-            println!(
-                "Library path updated to: {}",
-                absolute_path.to_string_lossy()
-            );
+
+            if out.is_human() {
+                println!(
+                    "Library path updated to: {}",
+                    absolute_path.to_string_lossy()
+                );
+            }
         }
         None => {
-            println!("{}", config.datapath.to_string_lossy());
+            if out.is_human() {
+                println!("{}", config.datapath.to_string_lossy());
+            }
         }
     }
 
-    Ok(())
+    out.record(&DirsRow {
+        library: Some(config.datapath.to_string_lossy().to_string()),
+        logs: None,
+    })?;
+
+    Ok(Outcome::Success)
 }
 
-fn command_dirs_logs() -> color_eyre::Result<()> {
+fn command_dirs_logs(out: &Output) -> color_eyre::Result<Outcome> {
     match logging::logging_dir() {
-        Some(logs_path) => println!("{}", logs_path.to_string_lossy()),
+        Some(logs_path) => {
+            if out.is_human() {
+                println!("{}", logs_path.to_string_lossy());
+            }
+
+            out.record(&DirsRow {
+                library: None,
+                logs: Some(logs_path.to_string_lossy().to_string()),
+            })?;
+
+            Ok(Outcome::Success)
+        }
         None => {
-            println!("Error: logs directory not available");
-            std::process::exit(1);
+            eprintln!("Error: logs directory not available");
+            Ok(Outcome::Failed)
         }
     }
-
-    Ok(())
 }
 
-fn command_import(_cli: &Cli, opml_file: &str) -> color_eyre::Result<()> {
-    println!("Importing feeds");
+fn command_import(
+    cli: &Cli,
+    file: &Option<PathBuf>,
+    from: ImportSource,
+    cache: &Option<PathBuf>,
+    dry_run: bool,
+    allow_local: bool,
+) -> color_eyre::Result<Outcome> {
+    let out = Output::new(cli.format);
     let mut library = FeedLibrary::new();
-    let opml_feeds = opml::get_opml_feeds(opml_file)?;
 
-    for feed in opml_feeds {
-        match library.add_feed_from_url(&feed.url, &feed.category) {
-            Ok(feed) => {
-                info!("Feed added: {}", feed.title);
-                println!("Feed added: {}", feed.title);
+    if cache.is_some() && from != ImportSource::Newsboat {
+        eprintln!("--cache can only be used with --from newsboat");
+        return Ok(Outcome::Failed);
+    }
+
+    let file = match (file, from) {
+        (Some(file), _) => file.clone(),
+        (None, ImportSource::Newsboat) => match newsboat::default_urls_path() {
+            Some(path) => path,
+            None => {
+                eprintln!("Couldn't find newsboat's urls file, pass its path instead");
+                return Ok(Outcome::Failed);
             }
-            Err(err) => {
-                error!("{err}");
-                println!("{err}");
+        },
+        (None, ImportSource::Opml) => {
+            eprintln!("Pass the OPML file to import");
+            return Ok(Outcome::Failed);
+        }
+    };
+
+    let feeds = match from {
+        ImportSource::Opml => opml::get_opml_feeds(&file.to_string_lossy())?,
+        ImportSource::Newsboat => newsboat::get_urls_feeds(&file)?,
+    };
+
+    if out.is_human() {
+        println!("Importing {} feeds", feeds.len());
+    }
+
+    let imported = library.import_feeds(&feeds, dry_run, allow_local);
+
+    let results: Vec<FeedResult> = imported
+        .iter()
+        .map(|result| FeedResult {
+            error: result.error.clone(),
+            ..FeedResult::new(&result.feed, result.status.name())
+        })
+        .collect();
+
+    if out.is_human() {
+        print_import_summary(&imported, dry_run);
+    }
+    out.rows(&results)?;
+
+    if let Some(cache) = cache {
+        let cached = newsboat::read_cache(cache)?;
+
+        if dry_run {
+            if out.is_human() {
+                println!("{} articles found in the cache", cached.len());
+            }
+        } else {
+            library.feedcategories = library.data.generate_categories_tree()?;
+            let states = library.import_entry_states(&cached)?;
+
+            info!("Imported entry states: {:?}", states);
+            if out.is_human() {
+                println!(
+                    "{} entries marked read, {} starred, {} starred entries restored from the cache",
+                    states.read, states.starred, states.restored
+                );
             }
         }
     }
 
-    Ok(())
+    let incomplete = imported
+        .iter()
+        .any(|r| matches!(r.status, ImportStatus::Failed | ImportStatus::Pending));
+
+    if incomplete {
+        Ok(Outcome::Partial)
+    } else {
+        Ok(Outcome::Success)
+    }
 }
 
-fn command_export(_cli: &Cli, opml_file: &str) -> color_eyre::Result<()> {
+fn print_import_summary(imported: &[ImportResult], dry_run: bool) {
+    let rows: Vec<[String; 4]> = imported
+        .iter()
+        .map(|r| {
+            [
+                r.status.name().to_string(),
+                r.feed.category.clone(),
+                r.feed.title.clone(),
+                r.feed.feed_url.clone(),
+            ]
+        })
+        .collect();
+
+    let header = ["Status", "Category", "Title", "URL"];
+    let mut widths = header.map(|h| h.chars().count());
+    for row in rows.iter() {
+        for (width, field) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(field.chars().count());
+        }
+    }
+
+    let line = |fields: [&str; 4]| {
+        let padded: Vec<String> = fields
+            .iter()
+            .zip(widths.iter())
+            .map(|(field, width)| format!("{field:<width$}"))
+            .collect();
+        println!("{}", padded.join("  ").trim_end());
+    };
+
+    println!();
+    line(header);
+    for row in rows.iter() {
+        line([&row[0], &row[1], &row[2], &row[3]]);
+    }
+
+    for result in imported.iter() {
+        if let Some(error) = &result.error {
+            println!("\n{}: {}", result.feed.feed_url, error);
+        }
+    }
+
+    let count = |status: ImportStatus| imported.iter().filter(|r| r.status == status).count();
+    if dry_run {
+        println!(
+            "\n{} new, {} already in the library",
+            count(ImportStatus::New),
+            count(ImportStatus::Exists)
+        );
+    } else {
+        println!(
+            "\n{} added, {} pending, {} already in the library, {} failed",
+            count(ImportStatus::Added),
+            count(ImportStatus::Pending),
+            count(ImportStatus::Exists),
+            count(ImportStatus::Failed)
+        );
+    }
+}
+
+fn command_export(cli: &Cli, opml_file: &str, with_settings: bool) -> color_eyre::Result<Outcome> {
     let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    opml::save_opml(&library.feedcategories, opml_file, with_settings)?;
 
-    opml::save_opml(&library.feedcategories, opml_file)?;
+    let results: Vec<FeedResult> = library
+        .feedcategories
+        .iter()
+        .flat_map(|c| c.feeds.iter())
+        .map(|feed| FeedResult::new(feed, "exported"))
+        .collect();
+    out.rows(&results)?;
 
-    Ok(())
+    Ok(Outcome::Success)
 }
@@ -0,0 +1,446 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use tracing::{error, info};
+
+use super::output::{EntryRow, Outcome, Output};
+use super::{Cli, DigestFormat, ExportTarget};
+use crate::core::feed::feedentry::{EntrySummary, FeedEntry};
+use crate::core::library::digest;
+use crate::core::library::export::{self, ExportEntry};
+use crate::core::library::feedlibrary::{EntryFilter, FeedLibrary};
+use crate::ui::tools::tuimarkdown;
+
+/// Options to pick which entries to use
+#[derive(clap::Args, Default)]
+pub struct EntryArgs {
+    /// Only entries of this feed, by slug or title
+    #[arg(long)]
+    feed: Option<String>,
+    /// Only entries of feeds in this category
+    #[arg(long)]
+    category: Option<String>,
+    /// Only entries not read yet
+    #[arg(long)]
+    unread: bool,
+    /// Only starred entries
+    #[arg(long)]
+    starred: bool,
+    /// Only entries in Read Later
+    #[arg(long)]
+    read_later: bool,
+    /// Only entries published after this date, or in this last period, e.g. 2025-01-31, 24h or 7d
+    #[arg(long, value_parser = parse_since)]
+    since: Option<DateTime<Utc>>,
+    /// Only entries published before this date, or before this last period
+    #[arg(long, value_parser = parse_since)]
+    until: Option<DateTime<Utc>>,
+    /// Only entries tagged with this category by their feed
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+impl EntryArgs {
+    pub fn to_filter(&self) -> EntryFilter {
+        EntryFilter {
+            feed: self.feed.clone(),
+            category: self.category.clone(),
+            unread: self.unread,
+            starred: self.starred,
+            read_later: self.read_later,
+            since: self.since,
+            until: self.until,
+            stored_since: None,
+            tag: self.tag.clone(),
+        }
+    }
+}
+
+/// What `mark-read`, `mark-unread` and `later` do to each entry
+#[derive(Clone, Copy)]
+pub enum EntryChange {
+    Read,
+    Unread,
+    ReadLater,
+    NotReadLater,
+}
+
+/// Parses a date, a date and time, or a period back from now given in minutes, hours, days or
+/// weeks
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_since_from(value, Utc::now())
+}
+
+fn parse_since_from(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Ok(date.with_timezone(&Utc));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(0);
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid date or period {value:?}"))?;
+
+    let period = match unit {
+        "m" | "min" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        "w" => Duration::try_weeks(amount),
+        _ => {
+            return Err(format!(
+                "Invalid period unit {unit:?}, expected m, h, d or w"
+            ));
+        }
+    };
+
+    period
+        .and_then(|period| now.checked_sub_signed(period))
+        .ok_or_else(|| format!("Period {value:?} is too long"))
+}
+
+pub fn summaries(library: &FeedLibrary, entries: &[FeedEntry]) -> Vec<EntrySummary> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            library
+                .get_entry_feed(entry)
+                .map(|feed| EntrySummary::new(&library.data.path, feed, entry))
+        })
+        .collect()
+}
+
+pub fn command_entries(cli: &Cli, args: &EntryArgs) -> color_eyre::Result<Outcome> {
+    let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    let entries = library.get_entries(&args.to_filter())?;
+    let rows = summaries(&library, &entries);
+
+    if !out.is_human() {
+        out.rows(&rows)?;
+        return Ok(Outcome::Success);
+    }
+
+    for row in rows.iter() {
+        println!(
+            "{} {} {}/{}: {}",
+            row.date.with_timezone(&chrono::Local).format("%Y-%m-%d"),
+            if row.seen { " " } else { "*" },
+            row.category,
+            row.feed,
+            row.title
+        );
+        println!("\t{}", row.path);
+    }
+
+    Ok(Outcome::Success)
+}
+
+pub fn command_show(cli: &Cli, ident: &str, render: bool) -> color_eyre::Result<Outcome> {
+    let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    let Some(entry) = find_entry(&library, ident)? else {
+        return Ok(Outcome::NotFound);
+    };
+
+    if !out.is_human() {
+        if let Some(summary) = summaries(&library, std::slice::from_ref(&entry)).pop() {
+            out.record(&EntryRow {
+                summary,
+                text: entry.text.clone(),
+            })?;
+        }
+        return Ok(Outcome::Success);
+    }
+
+    let feed = library
+        .get_entry_feed(&entry)
+        .map(|feed| feed.title.as_str())
+        .unwrap_or_default();
+
+    let markdown = format!(
+        "# {}\n\n{} · {}\n<{}>\n\n{}\n",
+        entry.title,
+        feed,
+        entry
+            .date
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M"),
+        entry.url,
+        entry.text
+    );
+
+    if render {
+        let theme = library.settings.get_theme().cloned();
+        print!(
+            "{}",
+            tuimarkdown::to_ansi(&tuimarkdown::from_str(&markdown, theme))
+        );
+    } else {
+        print!("{markdown}");
+    }
+
+    Ok(Outcome::Success)
+}
+
+/// The single entry identified by `ident`. When there's none, or several share the URL, it's
+/// reported and `None` is returned.
+fn find_entry(library: &FeedLibrary, ident: &str) -> color_eyre::Result<Option<FeedEntry>> {
+    let mut entries = library.find_entries(ident)?;
+    if entries.len() > 1 {
+        eprintln!("There were {} entries found with that URL:", entries.len());
+        for row in summaries(library, &entries) {
+            eprintln!("\t-> {}/{}: {}", row.category, row.feed, row.path);
+        }
+        eprintln!("Use the path of the entry to pick one of them");
+        return Ok(None);
+    }
+
+    if entries.is_empty() {
+        info!("No matching entry: {ident}");
+        eprintln!("No matching entry: {ident}");
+    }
+
+    Ok(entries.pop())
+}
+
+pub fn command_change_entries(
+    cli: &Cli,
+    idents: &[String],
+    change: EntryChange,
+) -> color_eyre::Result<Outcome> {
+    let mut library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    let mut changed = vec![];
+    let mut missing = 0;
+    let mut failed = 0;
+
+    for ident in idents {
+        let Some(mut entry) = find_entry(&library, ident)? else {
+            missing += 1;
+            continue;
+        };
+
+        let result = match change {
+            EntryChange::Read => library.data.set_entry_seen(&entry).map(|_| {
+                entry.seen = true;
+                "Marked as read"
+            }),
+            EntryChange::Unread => library.data.set_entry_unseen(&entry).map(|_| {
+                entry.seen = false;
+                "Marked as unread"
+            }),
+            EntryChange::ReadLater => library
+                .add_to_read_later(&entry)
+                .map(|_| "Added to Read Later"),
+            EntryChange::NotReadLater => library
+                .remove_from_read_later(&entry.filepath.to_string_lossy())
+                .map(|_| "Removed from Read Later"),
+        };
+
+        match result {
+            Ok(message) => {
+                if out.is_human() {
+                    println!("{message}: {}", entry.title);
+                }
+                changed.push(entry);
+            }
+            Err(e) => {
+                error!("Couldn't change {}: {e}", entry.filepath.display());
+                eprintln!("Couldn't change {}: {e}", entry.title);
+                failed += 1;
+            }
+        }
+    }
+
+    out.rows(&summaries(&library, &changed))?;
+
+    Ok(match (missing, failed) {
+        (0, 0) => Outcome::Success,
+        _ if !changed.is_empty() => Outcome::Partial,
+        (_, 0) => Outcome::NotFound,
+        _ => Outcome::Failed,
+    })
+}
+
+/// Period of a digest when no `--since` is given
+const DIGEST_PERIOD: &str = "24h";
+
+fn export_entries(
+    library: &FeedLibrary,
+    filter: &EntryFilter,
+) -> color_eyre::Result<Vec<ExportEntry>> {
+    Ok(library
+        .get_entries(filter)?
+        .into_iter()
+        .filter_map(|entry| {
+            let feed = library.get_entry_feed(&entry)?;
+            Some(ExportEntry {
+                summary: EntrySummary::new(&library.data.path, feed, &entry),
+                entry,
+            })
+        })
+        .collect())
+}
+
+pub fn command_export_entries(
+    cli: &Cli,
+    output: &Path,
+    target: ExportTarget,
+    title: &str,
+    args: &EntryArgs,
+) -> color_eyre::Result<Outcome> {
+    let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    let entries = export_entries(&library, &args.to_filter())?;
+
+    if entries.is_empty() {
+        info!("No entries to export");
+        eprintln!("No entries to export");
+        return Ok(Outcome::NotFound);
+    }
+
+    let result = match target {
+        ExportTarget::Html => export::write_site(output, title, &entries, library.fetcher.as_ref()),
+        ExportTarget::Epub => export::write_epub(output, title, &entries, library.fetcher.as_ref()),
+    };
+    if let Err(e) = result {
+        error!("Couldn't export entries to {}: {e}", output.display());
+        eprintln!("Couldn't export entries to {}: {e}", output.display());
+        return Ok(Outcome::Failed);
+    }
+
+    if out.is_human() {
+        println!("Exported {} entries to {}", entries.len(), output.display());
+    } else {
+        let rows: Vec<EntrySummary> = entries.into_iter().map(|e| e.summary).collect();
+        out.rows(&rows)?;
+    }
+
+    Ok(Outcome::Success)
+}
+
+pub fn command_digest(
+    cli: &Cli,
+    output: &Option<PathBuf>,
+    target: DigestFormat,
+    title: &str,
+    mark_read: bool,
+    args: &EntryArgs,
+) -> color_eyre::Result<Outcome> {
+    let library = FeedLibrary::new();
+    let out = Output::new(cli.format);
+
+    // New entries are those that arrived since then, as feeds may date them in the past or not
+    // at all
+    let mut filter = args.to_filter();
+    let since = match filter.since.take() {
+        Some(since) => since,
+        None => parse_since(DIGEST_PERIOD).map_err(|e| color_eyre::eyre::eyre!(e))?,
+    };
+    filter.stored_since = Some(since);
+
+    let mut entries = export_entries(&library, &filter)?;
+    if entries.is_empty() {
+        info!("No entries for the digest");
+        eprintln!("No entries for the digest");
+        return Ok(Outcome::NotFound);
+    }
+
+    let mut document = digest::digest_markdown(title, since, &entries);
+    if target == DigestFormat::Html {
+        document = digest::digest_html(title, &document);
+    }
+
+    match output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, &document) {
+                error!("Couldn't write the digest to {}: {e}", path.display());
+                eprintln!("Couldn't write the digest to {}: {e}", path.display());
+                return Ok(Outcome::Failed);
+            }
+        }
+        None => print!("{document}"),
+    }
+
+    let mut failed = 0;
+    if mark_read {
+        for export in entries.iter_mut() {
+            match library.data.set_entry_seen(&export.entry) {
+                Ok(()) => export.summary.seen = true,
+                Err(e) => {
+                    error!(
+                        "Couldn't mark {} read: {e}",
+                        export.entry.filepath.display()
+                    );
+                    eprintln!("Couldn't mark {} read: {e}", export.entry.title);
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    if let Some(path) = output {
+        if out.is_human() {
+            println!(
+                "Wrote a digest of {} entries to {}",
+                entries.len(),
+                path.display()
+            );
+        } else {
+            let rows: Vec<EntrySummary> = entries.into_iter().map(|e| e.summary).collect();
+            out.rows(&rows)?;
+        }
+    }
+
+    // The digest is written either way, but some entries will be in the next one too
+    Ok(match failed {
+        0 => Outcome::Success,
+        _ => Outcome::Partial,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2025-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_since_from("24h", now).unwrap(),
+            now - Duration::hours(24)
+        );
+        assert_eq!(
+            parse_since_from("7d", now).unwrap(),
+            now - Duration::days(7)
+        );
+        assert_eq!(
+            parse_since_from("2025-03-01", now).unwrap().to_rfc3339(),
+            "2025-03-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_since_from("2025-03-01T08:30:00+01:00", now)
+                .unwrap()
+                .to_rfc3339(),
+            "2025-03-01T07:30:00+00:00"
+        );
+        assert!(parse_since_from("3y", now).is_err());
+        assert!(parse_since_from("yesterday", now).is_err());
+        assert!(parse_since_from("9999999999999h", now).is_err());
+        assert!(parse_since_from("9223372036854775807w", now).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+use std::process::ExitCode;
+
+use serde::Serialize;
+
+use crate::core::{feed::feedentry::EntrySummary, library::feeditem::FeedItem};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Text meant to be read
+    #[default]
+    Human,
+    /// A JSON array of records, or a single object for commands about one thing
+    Json,
+    /// Tab separated values, with a header line
+    Tsv,
+}
+
+/// How a command ended. Errors that stop a command exit with 1, and wrong arguments with 2.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    Success,
+    /// The command couldn't do what it was asked, and said why
+    Failed,
+    /// Some of the feeds or entries failed, the others were processed
+    Partial,
+    /// Nothing matched what was asked for
+    NotFound,
+}
+
+impl From<Outcome> for ExitCode {
+    fn from(outcome: Outcome) -> Self {
+        ExitCode::from(match outcome {
+            Outcome::Success => 0,
+            Outcome::Failed => 1,
+            Outcome::Partial => 3,
+            Outcome::NotFound => 4,
+        })
+    }
+}
+
+/// A record printed by the CLI. Field names are stable, so scripts can rely on them.
+pub trait Row: Serialize {
+    const HEADER: &'static [&'static str];
+
+    /// Values in the order of `HEADER`
+    fn fields(&self) -> Vec<String>;
+}
+
+/// Prints records in the format picked with `--format`. Human output is left to each command.
+pub struct Output {
+    pub format: OutputFormat,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+
+    pub fn is_human(&self) -> bool {
+        self.format == OutputFormat::Human
+    }
+
+    pub fn rows<T: Row>(&self, rows: &[T]) -> color_eyre::Result<()> {
+        match self.format {
+            OutputFormat::Human => {}
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(rows)?),
+            OutputFormat::Tsv => print!("{}", tsv(rows)),
+        }
+
+        Ok(())
+    }
+
+    pub fn record<T: Row>(&self, row: &T) -> color_eyre::Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(row)?);
+                Ok(())
+            }
+            _ => self.rows(std::slice::from_ref(row)),
+        }
+    }
+}
+
+fn tsv<T: Row>(rows: &[T]) -> String {
+    let mut out = T::HEADER.join("\t");
+    out.push('\n');
+
+    for row in rows {
+        let fields: Vec<String> = row.fields().iter().map(|f| escape(f)).collect();
+        out.push_str(&fields.join("\t"));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Keeps each record on one line
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// A feed, as printed by `list`
+#[derive(Serialize)]
+pub struct FeedRow {
+    pub category: String,
+    pub title: String,
+    pub slug: String,
+    pub url: String,
+    pub feed_url: String,
+    pub unread: u16,
+}
+
+impl FeedRow {
+    pub fn new(feed: &FeedItem, unread: u16) -> Self {
+        Self {
+            category: feed.category.clone(),
+            title: feed.title.clone(),
+            slug: feed.slug.clone(),
+            url: feed.url.clone(),
+            feed_url: feed.feed_url.clone(),
+            unread,
+        }
+    }
+}
+
+impl Row for FeedRow {
+    const HEADER: &'static [&'static str] =
+        &["category", "title", "slug", "url", "feed_url", "unread"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.category.clone(),
+            self.title.clone(),
+            self.slug.clone(),
+            self.url.clone(),
+            self.feed_url.clone(),
+            self.unread.to_string(),
+        ]
+    }
+}
+
+/// A feed with entries carrying a tag, as printed by `list --tag`. It has the columns of
+/// `FeedRow`, followed by the tag and how many of the feed's entries carry it.
+#[derive(Serialize)]
+pub struct TaggedFeedRow {
+    #[serde(flatten)]
+    pub feed: FeedRow,
+    pub tag: String,
+    pub tagged: usize,
+}
+
+impl Row for TaggedFeedRow {
+    const HEADER: &'static [&'static str] = &[
+        "category", "title", "slug", "url", "feed_url", "unread", "tag", "tagged",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = self.feed.fields();
+        fields.push(self.tag.clone());
+        fields.push(self.tagged.to_string());
+        fields
+    }
+}
+
+impl Row for EntrySummary {
+    const HEADER: &'static [&'static str] = &[
+        "category",
+        "feed",
+        "feed_slug",
+        "title",
+        "url",
+        "author",
+        "date",
+        "tags",
+        "seen",
+        "starred",
+        "path",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.category.clone(),
+            self.feed.clone(),
+            self.feed_slug.clone(),
+            self.title.clone(),
+            self.url.clone(),
+            self.author.clone(),
+            self.date.to_rfc3339(),
+            self.tags.join(","),
+            self.seen.to_string(),
+            self.starred.to_string(),
+            self.path.clone(),
+        ]
+    }
+}
+
+/// An entry along with its text, as printed by `show`
+#[derive(Serialize)]
+pub struct EntryRow {
+    #[serde(flatten)]
+    pub summary: EntrySummary,
+    pub text: String,
+}
+
+impl Row for EntryRow {
+    const HEADER: &'static [&'static str] = &[
+        "category",
+        "feed",
+        "feed_slug",
+        "title",
+        "url",
+        "author",
+        "date",
+        "tags",
+        "seen",
+        "starred",
+        "path",
+        "text",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        let mut fields = self.summary.fields();
+        fields.push(self.text.clone());
+        fields
+    }
+}
+
+/// What happened to a feed in `add`, `update`, `delete` or `import`
+#[derive(Serialize, Default)]
+pub struct FeedResult {
+    pub category: String,
+    pub title: String,
+    pub slug: String,
+    pub feed_url: String,
+    pub status: &'static str,
+    pub new_entries: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FeedResult {
+    pub fn new(feed: &FeedItem, status: &'static str) -> Self {
+        Self {
+            category: feed.category.clone(),
+            title: feed.title.clone(),
+            slug: feed.slug.clone(),
+            feed_url: feed.feed_url.clone(),
+            status,
+            ..Default::default()
+        }
+    }
+}
+
+impl Row for FeedResult {
+    const HEADER: &'static [&'static str] = &[
+        "category",
+        "title",
+        "slug",
+        "feed_url",
+        "status",
+        "new_entries",
+        "error",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.category.clone(),
+            self.title.clone(),
+            self.slug.clone(),
+            self.feed_url.clone(),
+            self.status.to_string(),
+            self.new_entries.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// A feed picked by `delete`, with what deleting it loses
+#[derive(Serialize)]
+pub struct DeleteRow {
+    pub category: String,
+    pub title: String,
+    pub slug: String,
+    pub feed_url: String,
+    pub status: &'static str,
+    pub entries: usize,
+    /// Read Later references to its entries, relative to the categories directory
+    pub read_later: Vec<String>,
+}
+
+impl Row for DeleteRow {
+    const HEADER: &'static [&'static str] = &[
+        "category",
+        "title",
+        "slug",
+        "feed_url",
+        "status",
+        "entries",
+        "read_later",
+    ];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.category.clone(),
+            self.title.clone(),
+            self.slug.clone(),
+            self.feed_url.clone(),
+            self.status.to_string(),
+            self.entries.to_string(),
+            self.read_later.join(","),
+        ]
+    }
+}
+
+/// Where bulletty keeps its files, as printed by `dirs`
+#[derive(Serialize)]
+pub struct DirsRow {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub library: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<String>,
+}
+
+impl Row for DirsRow {
+    const HEADER: &'static [&'static str] = &["library", "logs"];
+
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.library.clone().unwrap_or_default(),
+            self.logs.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_output() {
+        let feed = FeedItem {
+            title: "Tabs\tand\nlines".to_string(),
+            slug: "tabs".to_string(),
+            category: "General".to_string(),
+            ..Default::default()
+        };
+
+        let out = tsv(&[FeedRow::new(&feed, 3)]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "category\ttitle\tslug\turl\tfeed_url\tunread");
+        assert_eq!(lines[1], "General\tTabs\\tand\\nlines\ttabs\t\t\t3");
+        assert_eq!(lines.len(), 2);
+
+        // Every field has a header, in the same order as the JSON output
+        let json = serde_json::to_value(FeedRow::new(&feed, 3)).unwrap();
+        let keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        assert_eq!(keys.len(), FeedRow::HEADER.len());
+    }
+
+    #[test]
+    fn test_tagged_feed_row_keeps_feed_columns() {
+        let feed = FeedItem {
+            title: "Blog".to_string(),
+            slug: "blog".to_string(),
+            category: "General".to_string(),
+            ..Default::default()
+        };
+        let row = TaggedFeedRow {
+            feed: FeedRow::new(&feed, 3),
+            tag: "rust".to_string(),
+            tagged: 2,
+        };
+
+        // Scripts reading plain `list` keep working, the tag comes after its columns
+        assert!(TaggedFeedRow::HEADER.starts_with(FeedRow::HEADER));
+        let out = tsv(std::slice::from_ref(&row));
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[1], "General\tBlog\tblog\t\t\t3\trust\t2");
+
+        let json = serde_json::to_value(&row).unwrap();
+        let json = json.as_object().unwrap();
+        assert_eq!(json.len(), TaggedFeedRow::HEADER.len());
+        assert!(
+            TaggedFeedRow::HEADER
+                .iter()
+                .all(|key| json.contains_key(*key))
+        );
+    }
+}
@@ -6,3 +6,7 @@ pub const DATA_CATEGORY_DEFAULT: &str = "General";
 pub const DATA_FEED: &str = ".feed.toml";
 pub const LOG_DIR: &str = "bulletty_logs";
 pub const DATA_READ_LATER: &str = ".later.toml";
+pub const DATA_RULES: &str = ".rules.toml";
+pub const DATA_UPDATE_LOCK: &str = ".update.lock";
+pub const DATA_DAEMON_STATUS: &str = ".daemon.toml";
+pub const DATA_ASSETS_DIR: &str = "assets";
@@ -0,0 +1,135 @@
+use std::sync::LazyLock;
+
+use encoding_rs::{Encoding, UTF_8};
+use regex::bytes::Regex;
+
+/// How many bytes at the start of a document are searched for an encoding declaration
+const SNIFF_LEN: usize = 1024;
+
+static XML_ENCODING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^\s*<\?xml[^>]*?\sencoding\s*=\s*["']([A-Za-z0-9._:-]+)["']"#).unwrap()
+});
+
+static META_CHARSET: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([A-Za-z0-9._:-]+)"#).unwrap());
+
+/// Decodes a downloaded document to UTF-8. The encoding is taken from, in order: a byte order
+/// mark, the `charset` of the HTTP `Content-Type` header, the `<?xml encoding?>` declaration or an
+/// HTML `<meta charset>`. UTF-8 is assumed when there's none of them.
+pub fn decode(body: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(charset_from_content_type)
+        .or_else(|| declared_encoding(body))
+        .unwrap_or(UTF_8);
+
+    // `decode` gives precedence to the BOM and replaces invalid sequences
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| label_to_encoding(value.trim().trim_matches('"').as_bytes()))
+}
+
+fn declared_encoding(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(SNIFF_LEN)];
+
+    XML_ENCODING
+        .captures(head)
+        .or_else(|| META_CHARSET.captures(head))
+        .and_then(|caps| label_to_encoding(&caps[1]))
+}
+
+fn label_to_encoding(label: &[u8]) -> Option<&'static Encoding> {
+    // A document whose declaration can be read as ASCII can't really be UTF-16, which is what
+    // some servers claim anyway. The BOM is the only reliable signal for it.
+    Encoding::for_label(label).map(|e| e.output_encoding())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{SHIFT_JIS, UTF_16LE, WINDOWS_1251, WINDOWS_1252};
+
+    fn feed(encoding: &str, title: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="{encoding}"?>
+<rss version="2.0"><channel><title>{title}</title></channel></rss>"#
+        )
+    }
+
+    fn encode(text: &str, encoding: &'static Encoding) -> Vec<u8> {
+        let (bytes, _, unmappable) = encoding.encode(text);
+        assert!(!unmappable);
+        bytes.into_owned()
+    }
+
+    #[test]
+    fn decodes_latin1_from_xml_declaration() {
+        let text = feed("ISO-8859-1", "Café à la crème");
+        let bytes = encode(&text, WINDOWS_1252);
+
+        assert_eq!(decode(&bytes, None), text);
+    }
+
+    #[test]
+    fn decodes_windows_1251_from_xml_declaration() {
+        let text = feed("windows-1251", "Новости дня");
+        let bytes = encode(&text, WINDOWS_1251);
+
+        assert_eq!(decode(&bytes, Some("application/rss+xml")), text);
+    }
+
+    #[test]
+    fn decodes_shift_jis_from_xml_declaration() {
+        let text = feed("Shift_JIS", "今日のニュース");
+        let bytes = encode(&text, SHIFT_JIS);
+
+        assert_eq!(decode(&bytes, None), text);
+    }
+
+    #[test]
+    fn http_charset_takes_precedence() {
+        // The declaration is wrong, as it often is when servers transcode feeds
+        let text = feed("UTF-8", "Новости");
+        let bytes = encode(&text, WINDOWS_1251);
+
+        assert_eq!(
+            decode(&bytes, Some("text/xml; charset=\"windows-1251\"")),
+            text
+        );
+    }
+
+    #[test]
+    fn byte_order_mark_takes_precedence() {
+        let text = feed("ISO-8859-1", "Grüße");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes()));
+
+        assert_eq!(decode(&bytes, Some("text/xml; charset=iso-8859-1")), text);
+        assert_eq!(UTF_16LE.decode_without_bom_handling(&bytes[2..]).0, text);
+    }
+
+    #[test]
+    fn decodes_html_meta_charset() {
+        let text = "<html><head><meta charset=\"windows-1251\"></head><body>Привет</body></html>";
+        let bytes = encode(text, WINDOWS_1251);
+
+        assert_eq!(decode(&bytes, Some("text/html")), text);
+    }
+
+    #[test]
+    fn defaults_to_utf8() {
+        let text = "<rss><channel><title>Ünïcödé ✓</title></channel></rss>";
+        assert_eq!(decode(text.as_bytes(), None), text);
+        assert_eq!(
+            decode(text.as_bytes(), Some("text/xml; charset=bogus")),
+            text
+        );
+    }
+}
@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::core::library::feeditem::FeedItem;
+
 #[derive(Default, Debug, Serialize, Deserialize, Clone)]
 pub struct FeedEntry {
     pub title: String,
@@ -12,9 +14,119 @@ pub struct FeedEntry {
     pub author: String,
     pub text: String,
 
+    /// When the entry was fetched and stored in the library
     pub lastupdated: DateTime<Utc>,
     pub seen: bool,
 
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub starred: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+
+    /// Categories or tags given by the feed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enclosures: Vec<Enclosure>,
+
     #[serde(skip_serializing, skip_deserializing)]
     pub filepath: PathBuf,
 }
+
+impl FeedEntry {
+    /// Whether the entry has the category `name`, ignoring case
+    pub fn has_category(&self, name: &str) -> bool {
+        self.categories
+            .iter()
+            .any(|c| c.to_lowercase() == name.to_lowercase())
+    }
+}
+
+/// An entry's metadata along with its feed, as given to hooks and printed by the CLI
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct EntrySummary {
+    pub feed: String,
+    pub feed_slug: String,
+    pub category: String,
+    pub title: String,
+    pub url: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    pub tags: Vec<String>,
+    pub seen: bool,
+    pub starred: bool,
+    /// Entry file, relative to the library
+    pub path: String,
+}
+
+impl EntrySummary {
+    pub fn new(datapath: &Path, feed: &FeedItem, entry: &FeedEntry) -> Self {
+        Self {
+            feed: feed.title.clone(),
+            feed_slug: feed.slug.clone(),
+            category: feed.category.clone(),
+            title: entry.title.clone(),
+            url: entry.url.clone(),
+            author: entry.author.clone(),
+            date: entry.date,
+            tags: entry.categories.clone(),
+            seen: entry.seen,
+            starred: entry.starred,
+            path: entry
+                .filepath
+                .strip_prefix(datapath)
+                .unwrap_or(&entry.filepath)
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+}
+
+/// A media file attached to an entry, like a podcast episode or a video
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Enclosure {
+    pub url: String,
+    #[serde(default)]
+    pub mime_type: String,
+    /// Size in bytes, as advertised by the feed. Zero when unknown
+    #[serde(default)]
+    pub length: u64,
+    /// Duration in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    /// Path of the downloaded file, relative to the entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local: Option<String>,
+}
+
+impl Enclosure {
+    /// Short human readable summary, e.g. "audio/mpeg | 1:17:14 | 42.1 MB"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![];
+
+        if !self.mime_type.is_empty() {
+            parts.push(self.mime_type.clone());
+        }
+
+        if let Some(duration) = self.duration {
+            let (hours, minutes, seconds) = (duration / 3600, duration / 60 % 60, duration % 60);
+            if hours > 0 {
+                parts.push(format!("{hours}:{minutes:02}:{seconds:02}"));
+            } else {
+                parts.push(format!("{minutes}:{seconds:02}"));
+            }
+        }
+
+        if self.length > 0 {
+            parts.push(format!("{:.1} MB", self.length as f64 / (1024.0 * 1024.0)));
+        }
+
+        if self.local.is_some() {
+            parts.push("downloaded".to_string());
+        }
+
+        parts.join(" | ")
+    }
+}
@@ -0,0 +1,220 @@
+use color_eyre::eyre::{bail, eyre};
+use roxmltree::{Document, Node, ParsingOptions};
+
+pub const ATOM_NS: &str = "http://www.w3.org/2005/Atom";
+pub const ATOM03_NS: &str = "http://purl.org/atom/ns#";
+pub const RDF_NS: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+pub const RSS10_NS: &str = "http://purl.org/rss/1.0/";
+pub const RSS090_NS: &str = "http://my.netscape.com/rdf/simple/0.9/";
+pub const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+pub const CONTENT_NS: &str = "http://purl.org/rss/1.0/modules/content/";
+
+/// The flavor of a feed document, along with the namespace its core elements live in: none for
+/// RSS 0.9x/2.0, the RSS 1.0 or 0.90 one for RDF feeds, and the Atom 1.0 or 0.3 one for Atom.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format<'a> {
+    Rss(Option<&'a str>),
+    Atom(Option<&'a str>),
+}
+
+impl<'a> Format<'a> {
+    pub fn ns(&self) -> Option<&'a str> {
+        match self {
+            Format::Rss(ns) | Format::Atom(ns) => *ns,
+        }
+    }
+
+    /// Name of the format as shown to users
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Rss(Some(RSS10_NS)) => "RSS 1.0",
+            Format::Rss(Some(RSS090_NS)) => "RSS 0.90",
+            Format::Rss(_) => "RSS",
+            Format::Atom(Some(ATOM03_NS)) => "Atom 0.3",
+            Format::Atom(_) => "Atom",
+        }
+    }
+
+    /// Value of the `type` attribute of OPML outlines
+    pub fn opml_type(&self) -> &'static str {
+        match self {
+            Format::Rss(_) => "rss",
+            Format::Atom(_) => "atom",
+        }
+    }
+}
+
+/// A feed document split into the element holding the feed metadata and its entries
+pub struct FeedDoc<'a, 'input> {
+    pub format: Format<'a>,
+    pub channel: Node<'a, 'input>,
+    pub entries: Vec<Node<'a, 'input>>,
+}
+
+impl<'a, 'input> FeedDoc<'a, 'input> {
+    pub fn new(doc: &'a Document<'input>) -> color_eyre::Result<Self> {
+        let root = doc.root_element();
+        let tag = root.tag_name();
+
+        match (tag.name(), tag.namespace()) {
+            ("feed", ns @ (None | Some(ATOM_NS) | Some(ATOM03_NS))) => Ok(Self {
+                format: Format::Atom(ns),
+                channel: root,
+                entries: root.children().filter(|c| is(c, ns, "entry")).collect(),
+            }),
+            ("rss", None) | ("RDF", Some(RDF_NS)) => {
+                let channel = root
+                    .children()
+                    .find(|c| c.is_element() && c.tag_name().name() == "channel")
+                    .ok_or_else(|| eyre!("Feed has no <channel> element"))?;
+
+                let ns = channel.tag_name().namespace();
+                if tag.name() == "RDF" && !matches!(ns, Some(RSS10_NS) | Some(RSS090_NS)) {
+                    bail!("Unsupported RDF feed namespace: {:?}", ns);
+                }
+
+                // RSS 2.0 items live inside the channel, RSS 1.0 ones are its siblings
+                Ok(Self {
+                    format: Format::Rss(ns),
+                    channel,
+                    entries: root.descendants().filter(|c| is(c, ns, "item")).collect(),
+                })
+            }
+            (name, _) => bail!("Unsupported feed format: <{}>", name),
+        }
+    }
+}
+
+/// Parses an XML document. Old RSS 0.91 feeds ship with a DOCTYPE, so DTDs are allowed; external
+/// ones are never loaded.
+pub fn parse_document(text: &str) -> Result<Document<'_>, roxmltree::Error> {
+    Document::parse_with_options(
+        text,
+        ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        },
+    )
+}
+
+/// Whether `node` is the element `name` in the namespace `ns`
+pub fn is(node: &Node, ns: Option<&str>, name: &str) -> bool {
+    node.is_element() && node.tag_name().name() == name && node.tag_name().namespace() == ns
+}
+
+pub fn child<'a, 'input>(
+    node: &Node<'a, 'input>,
+    ns: Option<&str>,
+    name: &str,
+) -> Option<Node<'a, 'input>> {
+    node.children().find(|c| is(c, ns, name))
+}
+
+/// Trimmed text of the first non-empty child element among `names`, tried in order
+pub fn child_text<'a>(node: &Node<'a, '_>, names: &[(Option<&str>, &str)]) -> Option<&'a str> {
+    names.iter().find_map(|(ns, name)| {
+        node.children()
+            .filter(|c| is(c, *ns, name))
+            .find_map(|c| c.text().map(str::trim).filter(|t| !t.is_empty()))
+    })
+}
+
+/// The `link` element pointing to the HTML version of `node`: `rel="alternate"`, or no `rel`
+/// at all, preferring HTML over other types.
+pub fn alternate_link<'a, 'input>(
+    node: &Node<'a, 'input>,
+    ns: Option<&str>,
+) -> Option<Node<'a, 'input>> {
+    let alternates: Vec<Node> = node
+        .children()
+        .filter(|c| is(c, ns, "link") && c.attribute("href").is_some())
+        .filter(|c| c.attribute("rel").is_none_or(|rel| rel == "alternate"))
+        .collect();
+
+    alternates
+        .iter()
+        .find(|l| {
+            l.attribute("type")
+                .is_none_or(|t| t == "text/html" || t == "application/xhtml+xml")
+        })
+        .or(alternates.first())
+        .copied()
+}
+
+/// Contents of a text construct. Atom allows them to hold inline XHTML markup, which is returned
+/// as it appears in the document. Atom 0.3 does it by default, without saying so.
+pub fn markup_text<'a>(node: &Node<'a, '_>) -> Option<&'a str> {
+    let inline_markup = node.attribute("type") == Some("xhtml")
+        || node.attribute("mode") == Some("xml")
+        || node.children().any(|c| c.is_element());
+
+    if inline_markup && let (Some(first), Some(last)) = (node.first_child(), node.last_child()) {
+        let text = &node.document().input_text()[first.range().start..last.range().end];
+        return Some(text.trim());
+    }
+
+    node.text()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats() {
+        let cases = [
+            (r#"<rss version="2.0"><channel/></rss>"#, Format::Rss(None)),
+            (
+                r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="http://purl.org/rss/1.0/"><channel/></rdf:RDF>"#,
+                Format::Rss(Some(RSS10_NS)),
+            ),
+            (
+                r#"<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#" xmlns="http://my.netscape.com/rdf/simple/0.9/"><channel/></rdf:RDF>"#,
+                Format::Rss(Some(RSS090_NS)),
+            ),
+            (
+                r#"<feed xmlns="http://www.w3.org/2005/Atom"/>"#,
+                Format::Atom(Some(ATOM_NS)),
+            ),
+            (
+                r#"<feed version="0.3" xmlns="http://purl.org/atom/ns#"/>"#,
+                Format::Atom(Some(ATOM03_NS)),
+            ),
+        ];
+
+        for (xml, format) in cases {
+            let doc = Document::parse(xml).unwrap();
+            assert_eq!(FeedDoc::new(&doc).unwrap().format, format, "{xml}");
+        }
+
+        let doc = Document::parse("<html><body/></html>").unwrap();
+        assert!(FeedDoc::new(&doc).is_err());
+    }
+
+    #[test]
+    fn picks_html_alternate_link() {
+        let xml = r#"<entry xmlns="http://www.w3.org/2005/Atom">
+  <link rel="self" href="https://example.org/self"/>
+  <link rel="alternate" type="application/json" href="https://example.org/json"/>
+  <link rel="replies" href="https://example.org/comments"/>
+  <link rel="alternate" type="text/html" href="https://example.org/html"/>
+</entry>"#;
+        let doc = Document::parse(xml).unwrap();
+
+        let link = alternate_link(&doc.root_element(), Some(ATOM_NS)).unwrap();
+        assert_eq!(link.attribute("href"), Some("https://example.org/html"));
+    }
+
+    #[test]
+    fn returns_inline_xhtml_markup() {
+        let xml = r#"<content xmlns="http://www.w3.org/2005/Atom" type="xhtml">
+  <div xmlns="http://www.w3.org/1999/xhtml"><p>Hello <b>world</b></p></div>
+</content>"#;
+        let doc = Document::parse(xml).unwrap();
+
+        assert_eq!(
+            markup_text(&doc.root_element()),
+            Some(r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Hello <b>world</b></p></div>"#)
+        );
+    }
+}
@@ -4,239 +4,322 @@ use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use color_eyre::eyre::{bail, eyre};
 use html2md_bulletty::parse_html;
 use regex::Regex;
-use reqwest::blocking::Client;
 use roxmltree::Node;
 use slug::slugify;
-use tracing::error;
+use tracing::{debug, error};
 use url::Url;
 
 use crate::core::{
-    feed::{feedentry::FeedEntry, feedutils, html},
-    library::feeditem::FeedItem,
+    feed::{
+        feedentry::{Enclosure, FeedEntry},
+        feedformat::{
+            ATOM_NS, ATOM03_NS, CONTENT_NS, DC_NS, FeedDoc, Format, RDF_NS, alternate_link, child,
+            child_text, is, markup_text, parse_document,
+        },
+        feedutils,
+        fetcher::{self, Document, Fetcher},
+        html, readability,
+        source::FeedSource,
+    },
+    library::feeditem::{FeedItem, HttpOptions},
 };
 
-pub fn get_feed_with_data(url: &str) -> color_eyre::Result<(FeedItem, String)> {
-    let client = Client::builder()
-        .user_agent(format!("bulletty/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
+const MEDIA_NS: &str = "http://search.yahoo.com/mrss/";
+const ITUNES_NS: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+const XML_NS: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// Paths where sites often publish their feed without advertising it
+const WELL_KNOWN_FEED_PATHS: [&str; 4] = ["/feed", "/rss.xml", "/atom.xml", "/index.xml"];
+
+/// A feed found at or from a URL, along with the document it was read from
+pub struct FeedCandidate {
+    pub feed: FeedItem,
+    /// Name of the feed format, e.g. "RSS" or "Atom"
+    pub format: &'static str,
+    pub text: String,
+}
+
+impl FeedCandidate {
+    fn new(text: String, feed_url: &str) -> color_eyre::Result<Self> {
+        let format = FeedDoc::new(&parse_document(&text)?)?.format.name();
+
+        Ok(Self {
+            feed: parse(&text, feed_url)?,
+            format,
+            text,
+        })
+    }
+}
+
+/// Gets the first feed found at `url`. See `discover_feeds`.
+pub fn get_feed_with_data(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<(FeedItem, String)> {
+    let candidate = discover_feeds(fetcher, url, http)?.swap_remove(0);
+    Ok((candidate.feed, candidate.text))
+}
+
+/// Finds the feeds at `url`. When it points to a web page instead of a feed, those advertised
+/// by the page are returned, in the order they appear, followed by any found at the usual feed
+/// paths of the site. The result is never empty.
+pub fn discover_feeds(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<Vec<FeedCandidate>> {
+    let source = FeedSource::new(url);
+    if let Some(body) = source.read_local() {
+        return Ok(vec![FeedCandidate::new(body?, &source.url())?]);
+    }
+
+    let doc = fetcher::get_document(fetcher, url, http)?;
+
+    if !html::is_html(&doc.text) {
+        // Feeds that moved permanently are stored with their new address
+        let feed_url = if doc.moved { doc.url.as_str() } else { url };
+        return Ok(vec![FeedCandidate::new(doc.text, feed_url)?]);
+    }
+
+    let page_url = Url::from_str(&doc.url)?;
+    let link_parser = match html::LinkParser::new(&doc.text, &page_url) {
+        Ok(p) => p,
+        Err(html::ParseError::TooLarge) => {
+            bail!("HTML page at \"{}\" is too large to parse", page_url);
+        }
+    };
 
-    let response = client.get(url).send()?;
+    let well_known = WELL_KNOWN_FEED_PATHS
+        .iter()
+        .filter_map(|path| page_url.join(path).ok().map(String::from));
 
-    if !response.status().is_success() {
-        return Err(eyre!(
-            "Request to \"{}\" returned status code {:?}",
-            url,
-            response.status()
-        ));
+    let mut urls: Vec<String> = vec![];
+    for link in link_parser.into_iter().chain(well_known) {
+        if !urls.contains(&link) {
+            urls.push(link);
+        }
     }
 
-    let body = response.text()?;
+    let mut candidates: Vec<FeedCandidate> = vec![];
+    let mut found_at: Vec<String> = vec![];
 
-    // If the response is HTML try to follow metadata feed links
-    if html::is_html(&body) {
-        let url = Url::from_str(url)?; // Fails with same error as the reqwest send() above
-        let link_parser = match html::LinkParser::new(&body, &url) {
-            Ok(p) => p,
-            Err(html::ParseError::TooLarge) => {
-                bail!("HTML page at \"{}\" is too large to parse", url);
+    for link in urls {
+        // Pages can advertise feeds on any host, which mustn't get the request options
+        let options = http.filter(|_| fetcher::same_origin(url, &link));
+        let doc = match fetcher::get_document(fetcher, &link, options) {
+            Ok(doc) if !html::is_html(&doc.text) => doc,
+            Ok(_) => continue,
+            Err(e) => {
+                debug!("No feed at \"{}\": {}", link, e);
+                continue;
             }
         };
 
-        return link_parser
-            .into_iter()
-            .take(3)
-            .find_map(|feed_url| get_feed_with_data(&feed_url).ok())
-            .ok_or_else(|| eyre!("No embedded RSS/Atom feed links found at \"{url}\""));
+        // Several of the paths can lead to the same feed
+        if found_at.contains(&doc.url) {
+            continue;
+        }
+
+        let feed_url = if doc.moved { doc.url.clone() } else { link };
+        match FeedCandidate::new(doc.text, &feed_url) {
+            Ok(candidate) => {
+                found_at.push(doc.url);
+                candidates.push(candidate);
+            }
+            Err(e) => debug!("No feed at \"{}\": {}", feed_url, e),
+        }
     }
 
-    Ok((parse(&body, url)?, body))
-}
+    if candidates.is_empty() {
+        bail!("No RSS/Atom feeds found at \"{}\"", page_url);
+    }
 
-pub fn get_feed(url: &str) -> color_eyre::Result<FeedItem> {
-    let (feeditem, _) = get_feed_with_data(url)?;
-    Ok(feeditem)
+    Ok(candidates)
 }
 
 fn parse(doc: &str, feed_url: &str) -> color_eyre::Result<FeedItem> {
     let mut feed = FeedItem::default();
 
-    let doc = roxmltree::Document::parse(doc)?;
-    let feed_tag = doc.root();
+    let doc = parse_document(doc)?;
+    let feeddoc = FeedDoc::new(&doc)?;
+    let channel = feeddoc.channel;
+    let ns = feeddoc.format.ns();
 
-    feed.title = feed_tag
-        .descendants()
-        .find(|t| t.tag_name().name() == "title")
-        .and_then(|t| t.text())
+    feed.title = child_text(&channel, &[(ns, "title"), (Some(DC_NS), "title")])
         .map(|s| feedutils::normalize_and_truncate(s, 256))
         .unwrap_or_default();
 
-    feed.description = feed_tag
-        .descendants()
-        .find(|t| t.tag_name().name() == "description" || t.tag_name().name() == "subtitle")
-        .and_then(|t| t.text())
-        .unwrap_or(&feed.title)
-        .to_string();
-
-    feed.url = feed_tag
-        .descendants()
-        .find(|t| t.tag_name().name() == "link")
-        .and_then(|t| {
-            if t.text().is_none() {
-                t.attribute("href")
-            } else {
-                t.text()
-            }
+    feed.description = child_text(
+        &channel,
+        &[
+            (ns, "description"),
+            (ns, "subtitle"),
+            (ns, "tagline"),
+            (Some(DC_NS), "description"),
+        ],
+    )
+    .unwrap_or(&feed.title)
+    .to_string();
+
+    let link = match feeddoc.format {
+        Format::Rss(_) => channel
+            .children()
+            .find(|t| is(t, ns, "link") && t.text().is_some_and(|l| !l.trim().is_empty()))
+            .and_then(|t| Some((t, t.text()?.trim())))
+            .or_else(|| {
+                alternate_link(&channel, Some(ATOM_NS))
+                    .and_then(|t| Some((t, t.attribute("href")?)))
+            }),
+        Format::Atom(_) => {
+            alternate_link(&channel, ns).and_then(|t| Some((t, t.attribute("href")?)))
+        }
+    };
+
+    feed.url = link
+        .map(|(link_tag, link)| {
+            // Relative site links are resolved against the feed's own URL
+            let base = Url::parse(feed_url).ok();
+            let base = xml_base(&link_tag, base.as_ref()).or(base);
+            feedutils::resolve_url(link, base.as_ref())
+                .map(String::from)
+                .unwrap_or_else(|| link.to_string())
         })
-        .unwrap_or(feed_url)
-        .to_string();
+        .unwrap_or_else(|| feed_url.to_string());
 
     feed.feed_url = feed_url.to_string();
 
-    if let Some(author_tag) = feed_tag
-        .descendants()
-        .find(|t| t.tag_name().name() == "author")
-    {
-        if let Some(nametag) = author_tag
-            .descendants()
-            .find(|t| t.tag_name().name() == "name")
-            .and_then(|t| t.text())
-        {
-            feed.author = String::from(nametag);
-        } else if let Some(text) = author_tag.text() {
-            feed.author = String::from(text);
-        } else {
-            feed.author = feed.title.to_string();
-        }
-    } else {
-        feed.author = feed.title.to_string();
-    }
+    feed.author = get_author(&channel, feeddoc.format).unwrap_or_else(|| feed.title.to_string());
 
     feed.slug = slugify(&feed.title);
+    feed.format = Some(feeddoc.format.opml_type().to_string());
 
     Ok(feed)
 }
 
-pub fn get_feed_entries(feed: &FeedItem) -> color_eyre::Result<Vec<FeedEntry>> {
-    let client = Client::builder()
-        .user_agent(format!("bulletty/{}", env!("CARGO_PKG_VERSION")))
-        .build()?;
+/// The format of a feed document, as written to OPML exports
+pub fn get_feed_format(doc: &str) -> Option<&'static str> {
+    let doc = parse_document(doc).ok()?;
+    Some(FeedDoc::new(&doc).ok()?.format.opml_type())
+}
 
-    let response = client.get(&feed.feed_url).send()?;
+pub fn get_feed_entries(
+    fetcher: &dyn Fetcher,
+    feed: &FeedItem,
+) -> color_eyre::Result<Vec<FeedEntry>> {
+    let doc = fetch_feed(fetcher, feed)?;
+    get_feed_entries_doc(&doc.text, &feed.author, &feed.url)
+}
 
-    if !response.status().is_success() {
-        return Err(eyre!(
-            "Request to \"{}\" returned status code {:?}",
-            feed.feed_url,
-            response.status()
-        ));
+/// Downloads the document of `feed`, or reads it when it's a local source
+pub fn fetch_feed(fetcher: &dyn Fetcher, feed: &FeedItem) -> color_eyre::Result<Document> {
+    if let Some(body) = FeedSource::new(&feed.feed_url).read_local() {
+        return Ok(Document {
+            text: body?,
+            url: feed.feed_url.to_string(),
+            moved: false,
+        });
     }
 
-    let body = response.text()?;
-    get_feed_entries_doc(&body, &feed.author)
+    fetcher::get_document(fetcher, &feed.feed_url, feed.http.as_ref())
+}
+
+/// Downloads the page at `url` and returns its main content converted to Markdown, for feeds
+/// that only ship a summary.
+pub fn get_article_content(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<String> {
+    let doc = fetcher::get_document(fetcher, url, http)?;
+
+    let content = match readability::extract_main_content(&doc.text) {
+        Ok(Some(content)) => content,
+        Ok(None) => bail!("No article content found at \"{}\"", url),
+        Err(html::ParseError::TooLarge) => {
+            bail!("HTML page at \"{}\" is too large to parse", url);
+        }
+    };
+
+    // Relative links are relative to where the page ended up, after redirects
+    let content = match Url::parse(&doc.url) {
+        Ok(base) => feedutils::absolutize_links(&content, &base),
+        Err(_) => content,
+    };
+
+    Ok(parse_html(&content))
 }
 
+/// Parses the entries of a feed document. Relative URLs are resolved against `xml:base`, the
+/// entry's link or `site_url`, in that order.
 pub fn get_feed_entries_doc(
     doctxt: &str,
     defaultauthor: &str,
+    site_url: &str,
 ) -> color_eyre::Result<Vec<FeedEntry>> {
-    let doc = roxmltree::Document::parse(doctxt)?;
-
-    let feed_tag = doc.root();
+    let doc = parse_document(doctxt)?;
+    let feeddoc = FeedDoc::new(&doc)?;
+    let format = feeddoc.format;
+    let ns = format.ns();
+    let site_url = Url::parse(site_url).ok();
 
     let mut feedentries = Vec::<FeedEntry>::new();
 
-    for entry in feed_tag
-        .descendants()
-        .filter(|t| t.tag_name().name() == "item" || t.tag_name().name() == "entry")
-    {
-        let (desc, content) = get_description_content(&entry);
-
+    for entry in feeddoc.entries {
         // date extraction
-        let datestr = entry
-            .descendants()
-            .find(|t| {
-                t.tag_name().name() == "published"
-                    || t.tag_name().name() == "updated"
-                    || t.tag_name().name() == "date"
-                    || t.tag_name().name() == "pubDate"
-            })
-            .and_then(|t| t.text())
-            .unwrap_or("1990-09-19")
-            .to_string();
-
-        // author extraction
-        let entryauthor: String = if let Some(author_tag) = entry
-            .descendants()
-            .find(|t| t.tag_name().name() == "author" || t.tag_name().name() == "creator")
-        {
-            if let Some(nametag) = author_tag
-                .descendants()
-                .find(|t| t.tag_name().name() == "name")
-                .and_then(|t| t.text())
-            {
-                String::from(nametag)
-            } else if let Some(text) = author_tag.text() {
-                String::from(text)
-            } else {
-                defaultauthor.to_string()
-            }
-        } else {
-            defaultauthor.to_string()
+        let date_names: &[(Option<&str>, &str)] = match format {
+            Format::Rss(_) => &[
+                (ns, "pubDate"),
+                (Some(DC_NS), "date"),
+                (Some(ATOM_NS), "published"),
+                (Some(ATOM_NS), "updated"),
+            ],
+            Format::Atom(_) => &[
+                (ns, "published"),
+                (ns, "issued"),
+                (ns, "updated"),
+                (ns, "modified"),
+                (ns, "created"),
+                (Some(DC_NS), "date"),
+            ],
         };
 
-        // url extraction
-        let entryurl = entry
-            .descendants()
-            .find(|t| {
-                if t.tag_name().name() == "id"
-                    && let Some(text) = t.text()
-                    && let Ok(url) = Url::parse(text)
-                    && (url.scheme() == "http" || url.scheme() == "https")
-                {
-                    return true;
-                }
+        let datestr = child_text(&entry, date_names).unwrap_or("1990-09-19");
 
-                if t.tag_name().name() == "enclosure"
-                    && let Some(text) = t.attribute("url")
-                    && let Ok(url) = Url::parse(text)
-                    && (url.scheme() == "http" || url.scheme() == "https")
-                {
-                    return true;
-                }
+        // author extraction
+        let entryauthor = get_author(&entry, format).unwrap_or_else(|| defaultauthor.to_string());
 
-                t.tag_name().name() == "link"
-            })
-            .and_then(|t| {
-                if t.text().is_none() {
-                    if t.attribute("url").is_some() {
-                        return t.attribute("url");
-                    }
-
-                    t.attribute("href")
-                } else {
-                    t.text()
-                }
+        // url extraction
+        let entryurl = get_entry_link(&entry, format)
+            .map(|(url_tag, url)| {
+                let base = xml_base(&url_tag, site_url.as_ref()).or_else(|| site_url.clone());
+                feedutils::resolve_url(url, base.as_ref())
+                    .map(String::from)
+                    .unwrap_or_else(|| url.to_string())
             })
-            .unwrap_or("NOURL")
-            .to_string();
+            .unwrap_or_else(|| "NOURL".to_string());
+
+        let entry_base = Url::parse(&entryurl).ok().or_else(|| site_url.clone());
+        let (desc, content) = get_description_content(&entry, format, entry_base.as_ref());
 
         // feed creation
         let fe = FeedEntry {
-            title: entry
-                .descendants()
-                .find(|t| t.tag_name().name() == "title")
-                .and_then(|t| t.text())
+            title: child_text(&entry, &[(ns, "title"), (Some(DC_NS), "title")])
                 .map(|s| feedutils::normalize_and_truncate(s, 256))
                 .unwrap_or_default(),
             author: entryauthor,
             url: entryurl.clone(),
             text: content,
-            date: parse_date(&datestr)
+            date: parse_date(datestr)
                 .map_err(|err| error!("{:?} from {entryurl}", err))
                 .unwrap_or_default(),
             description: desc,
             lastupdated: Utc::now(),
             seen: false,
+            starred: false,
+            thumbnail: get_thumbnail(&entry, entry_base.as_ref()),
+            categories: get_categories(&entry, format),
+            enclosures: get_enclosures(&entry, entry_base.as_ref()),
             filepath: PathBuf::default(),
         };
 
@@ -246,6 +329,230 @@ pub fn get_feed_entries_doc(
     Ok(feedentries)
 }
 
+/// Name of the author of a feed or entry, from Atom's `<author><name>`, RSS's `<author>`,
+/// `<dc:creator>` or `<itunes:author>`
+fn get_author(node: &Node, format: Format) -> Option<String> {
+    let ns = format.ns();
+
+    if let Some(author) = child(node, ns, "author").or_else(|| child(node, Some(ATOM_NS), "author"))
+        && let Some(name) = child_text(&author, &[(author.tag_name().namespace(), "name")])
+    {
+        return Some(name.to_string());
+    }
+
+    child_text(
+        node,
+        &[
+            (ns, "author"),
+            (Some(DC_NS), "creator"),
+            (Some(ITUNES_NS), "author"),
+        ],
+    )
+    .map(String::from)
+}
+
+/// Categories of an entry, from RSS's `<category>` and `<dc:subject>` or Atom's
+/// `<category term label>`
+fn get_categories(entry: &Node, format: Format) -> Vec<String> {
+    let ns = format.ns();
+    let mut categories = Vec::<String>::new();
+
+    for t in entry.children().filter(|t| t.is_element()) {
+        let category = match format {
+            Format::Atom(_) if is(&t, ns, "category") => {
+                t.attribute("label").or_else(|| t.attribute("term"))
+            }
+            Format::Rss(_) if is(&t, ns, "category") || is(&t, Some(DC_NS), "subject") => t.text(),
+            _ => None,
+        };
+
+        if let Some(category) = category.map(|c| feedutils::normalize_and_truncate(c, 64))
+            && !category.is_empty()
+            && !categories.iter().any(|c| c.eq_ignore_ascii_case(&category))
+        {
+            categories.push(category);
+        }
+    }
+
+    categories
+}
+
+/// Link to the web page of an entry, along with the element it comes from. Atom entries use their
+/// alternate link, RSS ones their `<link>`. Otherwise, a permalink `<guid>` or `<id>`, RDF's
+/// `rdf:about` or the first enclosure are used.
+fn get_entry_link<'a, 'input>(
+    entry: &Node<'a, 'input>,
+    format: Format,
+) -> Option<(Node<'a, 'input>, &'a str)> {
+    let ns = format.ns();
+    let href = |t: Node<'a, 'input>| Some((t, t.attribute("href")?));
+
+    let link = match format {
+        Format::Atom(_) => alternate_link(entry, ns).and_then(href),
+        Format::Rss(_) => entry
+            .children()
+            .find(|t| is(t, ns, "link") && t.text().is_some_and(|l| !l.trim().is_empty()))
+            .and_then(|t| Some((t, t.text()?.trim())))
+            .or_else(|| alternate_link(entry, Some(ATOM_NS)).and_then(href)),
+    };
+
+    let is_web_url = |text: &str| {
+        Url::parse(text).is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https")
+    };
+
+    link.or_else(|| {
+        entry
+            .children()
+            .filter(|t| is(t, ns, "id") || is(t, ns, "guid"))
+            .filter(|t| t.attribute("isPermaLink") != Some("false"))
+            .find_map(|t| Some((t, t.text()?.trim())).filter(|(_, text)| is_web_url(text)))
+    })
+    .or_else(|| {
+        entry
+            .attribute((RDF_NS, "about"))
+            .filter(|about| is_web_url(about))
+            .map(|about| (*entry, about))
+    })
+    .or_else(|| {
+        entry.descendants().find_map(|t| {
+            if is(&t, None, "enclosure") {
+                Some((t, t.attribute("url")?))
+            } else if is(&t, ns, "link") && t.attribute("rel") == Some("enclosure") {
+                Some((t, t.attribute("href")?))
+            } else {
+                None
+            }
+        })
+    })
+}
+
+/// Returns the base URL set by the `xml:base` attributes of `node` and its ancestors, resolved
+/// against `base`, or `None` when there aren't any.
+fn xml_base(node: &Node, base: Option<&Url>) -> Option<Url> {
+    let mut bases: Vec<&str> = node
+        .ancestors()
+        .filter_map(|n| n.attribute((XML_NS, "base")))
+        .collect();
+    bases.reverse();
+
+    bases.into_iter().fold(None, |current, xml_base| {
+        feedutils::resolve_url(xml_base, current.as_ref().or(base)).or(current)
+    })
+}
+
+/// Resolves `url`, from an attribute of `node`, the same way as links in the description
+fn resolve_node_url(node: &Node, url: &str, base: Option<&Url>) -> String {
+    let node_base = xml_base(node, base);
+    feedutils::resolve_url(url, node_base.as_ref().or(base))
+        .map(String::from)
+        .unwrap_or_else(|| url.to_string())
+}
+
+fn get_enclosures(entry: &Node, base: Option<&Url>) -> Vec<Enclosure> {
+    let mut enclosures = Vec::<Enclosure>::new();
+
+    for t in entry.descendants().filter(|t| t.is_element()) {
+        let name = t.tag_name().name();
+        let namespace = t.tag_name().namespace();
+
+        let enclosure = if name == "enclosure" && namespace.is_none() {
+            // RSS <enclosure url length type>
+            t.attribute("url").map(|url| Enclosure {
+                url: url.to_string(),
+                mime_type: t.attribute("type").unwrap_or_default().to_string(),
+                length: t
+                    .attribute("length")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(0),
+                ..Default::default()
+            })
+        } else if name == "link"
+            && matches!(namespace, None | Some(ATOM_NS) | Some(ATOM03_NS))
+            && t.attribute("rel") == Some("enclosure")
+        {
+            // Atom <link rel="enclosure" href length type>
+            t.attribute("href").map(|url| Enclosure {
+                url: url.to_string(),
+                mime_type: t.attribute("type").unwrap_or_default().to_string(),
+                length: t
+                    .attribute("length")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(0),
+                ..Default::default()
+            })
+        } else if name == "content"
+            && namespace == Some(MEDIA_NS)
+            && t.attribute("medium") != Some("image")
+            && !t.attribute("type").is_some_and(|t| t.starts_with("image/"))
+        {
+            // Media RSS <media:content url fileSize type duration>
+            t.attribute("url").map(|url| Enclosure {
+                url: url.to_string(),
+                mime_type: t.attribute("type").unwrap_or_default().to_string(),
+                length: t
+                    .attribute("fileSize")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(0),
+                duration: t.attribute("duration").and_then(parse_duration),
+                ..Default::default()
+            })
+        } else {
+            None
+        };
+
+        if let Some(mut enclosure) = enclosure {
+            enclosure.url = resolve_node_url(&t, &enclosure.url, base);
+            if !enclosures.iter().any(|e| e.url == enclosure.url) {
+                enclosures.push(enclosure);
+            }
+        }
+    }
+
+    // iTunes only gives the episode duration, which belongs to its single enclosure
+    if let Some(duration) = entry
+        .children()
+        .find(|t| t.tag_name().name() == "duration" && t.tag_name().namespace() == Some(ITUNES_NS))
+        .and_then(|t| t.text())
+        .and_then(parse_duration)
+        && let Some(first) = enclosures.first_mut()
+        && first.duration.is_none()
+    {
+        first.duration = Some(duration);
+    }
+
+    enclosures
+}
+
+fn get_thumbnail(entry: &Node, base: Option<&Url>) -> Option<String> {
+    let node = entry.descendants().find(|t| {
+        (t.tag_name().name() == "thumbnail" && t.tag_name().namespace() == Some(MEDIA_NS))
+            || (t.tag_name().name() == "image" && t.tag_name().namespace() == Some(ITUNES_NS))
+    })?;
+
+    node.attribute("url")
+        .or_else(|| node.attribute("href"))
+        .map(|url| resolve_node_url(&node, url, base))
+}
+
+/// Parses durations in seconds ("4634"), "MM:SS" or "HH:MM:SS"
+fn parse_duration(duration: &str) -> Option<u64> {
+    let parts: Vec<&str> = duration.trim().split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for part in parts.iter() {
+        let value = part.trim().parse::<f64>().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+
+    seconds.is_finite().then(|| seconds.round() as u64)
+}
+
 fn parse_date(date_str: &str) -> color_eyre::Result<DateTime<Utc>> {
     let mut errors = Vec::new();
 
@@ -331,16 +638,43 @@ fn parse_date(date_str: &str) -> color_eyre::Result<DateTime<Utc>> {
     ))
 }
 
-fn get_description_content(entry: &Node) -> (String, String) {
-    let content = entry
-        .descendants()
-        .find(|t| t.tag_name().name() == "content" || t.tag_name().name() == "encoded")
-        .and_then(|t| t.text());
+/// Returns the description and the content of an entry. Relative links in them are resolved
+/// against their `xml:base`, or `base` when there's none.
+fn get_description_content(entry: &Node, format: Format, base: Option<&Url>) -> (String, String) {
+    let ns = format.ns();
+
+    let absolute_text = |node: Node| {
+        let text = markup_text(&node)?;
+        match xml_base(&node, base).as_ref().or(base) {
+            Some(base) => Some(feedutils::absolutize_links(text, base)),
+            None => Some(text.to_string()),
+        }
+    };
+
+    let find = |names: &[(Option<&str>, &str)]| {
+        names.iter().find_map(|(ns, name)| {
+            entry
+                .children()
+                .filter(|t| is(t, *ns, name))
+                .find_map(absolute_text)
+        })
+    };
+
+    let (content, description) = match format {
+        Format::Rss(_) => (
+            find(&[(Some(CONTENT_NS), "encoded")]),
+            find(&[(ns, "description"), (Some(DC_NS), "description")]),
+        ),
+        Format::Atom(_) => (find(&[(ns, "content")]), find(&[(ns, "summary")])),
+    };
 
-    let description = entry
-        .descendants()
-        .find(|t| t.tag_name().name() == "description" || t.tag_name().name() == "summary")
-        .and_then(|t| t.text());
+    // Media RSS descriptions, like YouTube's, are usually nested in a <media:group>
+    let description = description.or_else(|| {
+        entry
+            .descendants()
+            .filter(|t| is(t, Some(MEDIA_NS), "description"))
+            .find_map(absolute_text)
+    });
 
     let content_text = match content.as_ref() {
         Some(text) => parse_html(text),
@@ -351,7 +685,7 @@ fn get_description_content(entry: &Node) -> (String, String) {
     };
 
     let description_text = match description {
-        Some(text) => parse_html(text)
+        Some(text) => parse_html(&text)
             .replace("\n", "")
             .chars()
             .take(280)
@@ -392,6 +726,7 @@ mod tests {
     use chrono::TimeZone;
 
     use super::*;
+    use crate::core::feed::fetcher::fixture::FixtureFetcher;
 
     #[test]
     fn test_strip_markdown_tags() {
@@ -549,7 +884,7 @@ mod tests {
    </channel>
  </rss>"#;
 
-        let entries = get_feed_entries_doc(xml, "Carol").expect("failed to parse RSS entries");
+        let entries = get_feed_entries_doc(xml, "Carol", "").expect("failed to parse RSS entries");
         assert_eq!(entries.len(), 2);
 
         // Item A: prefers content:encoded for text, description for description, channel-level author
@@ -614,7 +949,7 @@ mod tests {
    </entry>
  </feed>"#;
 
-        let entries = get_feed_entries_doc(xml, "Bob").expect("failed to parse Atom entries");
+        let entries = get_feed_entries_doc(xml, "Bob", "").expect("failed to parse Atom entries");
         assert_eq!(entries.len(), 3);
 
         // Entry 1: uses summary for description, content for text, published for date, id for URL, feed-level author
@@ -684,7 +1019,7 @@ mod tests {
   </entry>
 </feed>"#;
 
-        let entries = get_feed_entries_doc(xml, "Feed Author")
+        let entries = get_feed_entries_doc(xml, "Feed Author", "")
             .expect("failed to parse Atom entries with entry-level authors");
         assert_eq!(entries.len(), 2);
 
@@ -725,7 +1060,7 @@ mod tests {
   </channel>
 </rss>"#;
 
-        let entries = get_feed_entries_doc(xml, "Channel Author")
+        let entries = get_feed_entries_doc(xml, "Channel Author", "")
             .expect("failed to parse RSS entries with entry-level authors");
         assert_eq!(entries.len(), 2);
 
@@ -779,7 +1114,7 @@ mod tests {
  </entry> 
 </feed>"#;
 
-        let entries = get_feed_entries_doc(xml, "Channel Author")
+        let entries = get_feed_entries_doc(xml, "Channel Author", "")
             .expect("failed to parse feed youtube style");
         assert_eq!(entries.len(), 1);
 
@@ -789,6 +1124,15 @@ mod tests {
         assert_eq!(entry.url, "https://www.youtube.com/watch?v=VIDEOID");
         assert_eq!(entry.author, "Some Youtube Author");
         assert_eq!(entry.description, "This is a description!");
+        assert_eq!(
+            entry.thumbnail.as_deref(),
+            Some("https://i2.ytimg.com/vi/VIDEOID/hqdefault.jpg")
+        );
+        assert_eq!(entry.enclosures.len(), 1);
+        assert_eq!(
+            entry.enclosures[0].url,
+            "https://www.youtube.com/v/VIDEOID?version=3"
+        );
     }
 
     #[test]
@@ -817,7 +1161,7 @@ mod tests {
   </channel>
 </rss>"#;
 
-        let entries = get_feed_entries_doc(xml, "Channel Author")
+        let entries = get_feed_entries_doc(xml, "Channel Author", "")
             .expect("failed to parse feed podcasty style");
         assert_eq!(entries.len(), 1);
 
@@ -827,5 +1171,493 @@ mod tests {
         assert_eq!(entry.url, "https://podcast_link.com/audio");
         assert_eq!(entry.author, "Podcast Author");
         assert_eq!(entry.description, "Podcast Entry Description");
+        assert_eq!(
+            entry.thumbnail.as_deref(),
+            Some("https://podcast_link.com/thumbnail")
+        );
+        assert_eq!(
+            entry.enclosures,
+            vec![Enclosure {
+                url: "https://podcast_link.com/audio".to_string(),
+                mime_type: "audio/mpeg".to_string(),
+                length: 0,
+                duration: Some(4634),
+                local: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn get_feed_entries_media_enclosures() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+  <title>Media Atom</title>
+  <entry>
+    <title>Episode</title>
+    <id>https://example.org/episode</id>
+    <link rel="enclosure" href="https://example.org/episode.mp3" type="audio/mpeg" length="1048576"/>
+    <media:content url="https://example.org/cover.jpg" medium="image"/>
+    <media:content url="https://example.org/episode.mp4" type="video/mp4" fileSize="2048" duration="125"/>
+    <media:content url="https://example.org/episode.mp3" type="audio/mpeg"/>
+  </entry>
+</feed>"#;
+
+        let entries = get_feed_entries_doc(xml, "Author", "").expect("failed to parse media feed");
+        assert_eq!(entries.len(), 1);
+
+        let entry = &entries[0];
+        assert_eq!(entry.thumbnail, None);
+        assert_eq!(entry.enclosures.len(), 2);
+        assert_eq!(entry.enclosures[0].url, "https://example.org/episode.mp3");
+        assert_eq!(entry.enclosures[0].length, 1048576);
+        assert_eq!(entry.enclosures[1].url, "https://example.org/episode.mp4");
+        assert_eq!(entry.enclosures[1].mime_type, "video/mp4");
+        assert_eq!(entry.enclosures[1].length, 2048);
+        assert_eq!(entry.enclosures[1].duration, Some(125));
+        assert_eq!(entry.enclosures[1].summary(), "video/mp4 | 2:05 | 0.0 MB");
+    }
+
+    #[test]
+    fn get_feed_entries_relative_enclosures() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:media="http://search.yahoo.com/mrss/">
+  <channel>
+    <title>Podcast</title>
+    <item>
+      <title>Episode</title>
+      <link>https://example.org/episodes/1</link>
+      <enclosure url="1.mp3" type="audio/mpeg"/>
+      <media:thumbnail url="/covers/1.jpg"/>
+    </item>
+    <item xml:base="https://cdn.example.com/audio/">
+      <title>Elsewhere</title>
+      <link>https://example.org/episodes/2</link>
+      <enclosure url="2.mp3" type="audio/mpeg"/>
+    </item>
+  </channel>
+</rss>"#;
+
+        let entries =
+            get_feed_entries_doc(xml, "Author", "https://example.org/").expect("failed to parse");
+
+        assert_eq!(
+            entries[0].enclosures[0].url,
+            "https://example.org/episodes/1.mp3"
+        );
+        assert_eq!(
+            entries[0].thumbnail.as_deref(),
+            Some("https://example.org/covers/1.jpg")
+        );
+        assert_eq!(
+            entries[1].enclosures[0].url,
+            "https://cdn.example.com/audio/2.mp3"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_formats() {
+        assert_eq!(parse_duration("4634"), Some(4634));
+        assert_eq!(parse_duration("1:17:14"), Some(4634));
+        assert_eq!(parse_duration("17:14"), Some(1034));
+        assert_eq!(parse_duration(" 90.6 "), Some(91));
+        assert_eq!(parse_duration("1:2:3:4"), None);
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration("inf"), None);
+        assert_eq!(parse_duration("NaN"), None);
+        assert_eq!(parse_duration("-90"), None);
+        assert_eq!(parse_duration("1:-30"), None);
+        assert_eq!(parse_duration("1e400"), None);
+    }
+
+    #[test]
+    fn get_feed_entries_resolves_xml_base() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:base="https://example.com/blog/">
+  <title>Relative</title>
+  <entry>
+    <title>First</title>
+    <link href="posts/first.html"/>
+    <updated>2024-01-01T00:00:00Z</updated>
+    <content type="html" xml:base="/static/">&lt;p&gt;&lt;img src="a.png" alt="A"&gt; &lt;a href="../about"&gt;About&lt;/a&gt;&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+        let entries = get_feed_entries_doc(xml, "Author", "https://example.com/")
+            .expect("failed to parse Atom entries");
+
+        let entry = &entries[0];
+        assert_eq!(entry.url, "https://example.com/blog/posts/first.html");
+        assert!(entry.text.contains("https://example.com/static/a.png"));
+        assert!(entry.text.contains("(https://example.com/about)"));
+    }
+
+    #[test]
+    fn get_feed_entries_resolves_against_entry_and_site_links() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+  <channel>
+    <title>Relative</title>
+    <link>https://example.com/</link>
+    <item>
+      <title>Absolute link</title>
+      <link>https://example.com/2024/01/post/</link>
+      <description>&lt;img src="cover.jpg"&gt;</description>
+    </item>
+    <item>
+      <title>Relative link</title>
+      <link>/2024/02/post/</link>
+      <description>&lt;a href="/tags/rust"&gt;rust&lt;/a&gt;</description>
+    </item>
+  </channel>
+</rss>"#;
+
+        let entries = get_feed_entries_doc(xml, "Author", "https://example.com/")
+            .expect("failed to parse RSS entries");
+
+        assert!(
+            entries[0]
+                .text
+                .contains("https://example.com/2024/01/post/cover.jpg")
+        );
+        assert_eq!(entries[1].url, "https://example.com/2024/02/post/");
+        assert!(entries[1].text.contains("(https://example.com/tags/rust)"));
+    }
+
+    #[test]
+    fn parse_resolves_relative_site_link() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Relative</title>
+  <link href="/"/>
+</feed>"#;
+
+        let feed = parse(xml, "https://example.com/feeds/atom.xml").unwrap();
+        assert_eq!(feed.url, "https://example.com/");
+    }
+
+    #[test]
+    fn get_feed_entries_from_legacy_encoding() {
+        let xml = r#"<?xml version="1.0" encoding="windows-1251"?>
+<rss version="2.0">
+  <channel>
+    <title>Новости</title>
+    <item>
+      <title>Привет, мир</title>
+      <link>https://example.ru/1</link>
+      <description>Первая запись</description>
+    </item>
+  </channel>
+</rss>"#;
+        let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode(xml);
+
+        let doc = crate::core::feed::charset::decode(&bytes, Some("application/xml"));
+        let entries = get_feed_entries_doc(&doc, "Author", "").unwrap();
+
+        assert_eq!(entries[0].title, "Привет, мир");
+        assert_eq!(entries[0].description, "Первая запись");
+    }
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../../../tests/fixtures/feeds/", $name))
+        };
+    }
+
+    #[test]
+    fn get_feed_follows_html_feed_links() {
+        let page = r#"<html><head>
+<link rel="alternate" type="application/rss+xml" href="/feed.xml">
+</head><body></body></html>"#;
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.org/", page)
+            .file("https://example.org/feed.xml", "rss20_wordpress.xml");
+
+        let (feed, _) = get_feed_with_data(&fetcher, "https://example.org/", None).unwrap();
+        assert_eq!(feed.feed_url, "https://example.org/feed.xml");
+
+        let entries = get_feed_entries(&fetcher, &feed).unwrap();
+        assert!(!entries.is_empty());
+
+        assert!(get_feed_with_data(&fetcher, "https://example.org/missing", None).is_err());
+    }
+
+    #[test]
+    fn discover_feeds_probes_well_known_paths() {
+        let page = r#"<!doctype html><html><head>
+<link rel="alternate" type="application/rss+xml" href="/posts.xml">
+<link rel="alternate" type="application/atom+xml" href="/comments.atom">
+</head><body></body></html>"#;
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.org/blog/", page)
+            .file("https://example.org/posts.xml", "rss20_wordpress.xml")
+            .file("https://example.org/comments.atom", "atom10_xhtml.xml")
+            .redirect("https://example.org/feed", 302, "/posts.xml")
+            .body("https://example.org/rss.xml", page)
+            .file("https://example.org/index.xml", "rss10.rdf");
+
+        let candidates = discover_feeds(&fetcher, "https://example.org/blog/", None).unwrap();
+        let found: Vec<(&str, &str)> = candidates
+            .iter()
+            .map(|c| (c.feed.feed_url.as_str(), c.format))
+            .collect();
+
+        // /feed leads to a feed already found, /rss.xml is a page and /atom.xml doesn't exist
+        assert_eq!(
+            found,
+            vec![
+                ("https://example.org/posts.xml", "RSS"),
+                ("https://example.org/comments.atom", "Atom"),
+                ("https://example.org/index.xml", "RSS 1.0"),
+            ]
+        );
+
+        // Sites that don't advertise their feed
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.com/", "<html><body></body></html>")
+            .file("https://example.com/atom.xml", "atom10_xhtml.xml");
+
+        let (feed, _) = get_feed_with_data(&fetcher, "https://example.com/", None).unwrap();
+        assert_eq!(feed.feed_url, "https://example.com/atom.xml");
+
+        let fetcher = FixtureFetcher::default();
+        fetcher.body("https://example.net/", "<html><body></body></html>");
+        let err = discover_feeds(&fetcher, "https://example.net/", None)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(err.to_string().contains("No RSS/Atom feeds"), "{err}");
+    }
+
+    #[test]
+    fn article_content_resolves_relative_links() {
+        let text = "A paragraph long enough to count as the content of the article, and more.";
+        let page = format!(
+            r#"<html><body><article>
+<p>{text} <a href="../about">About</a></p>
+<p>{text} <img src="images/photo.png" alt="Photo"></p>
+</article></body></html>"#
+        );
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .redirect(
+                "https://example.org/post",
+                301,
+                "https://example.org/blog/2025/post",
+            )
+            .body("https://example.org/blog/2025/post", &page);
+
+        let content = get_article_content(&fetcher, "https://example.org/post", None).unwrap();
+
+        assert!(content.contains("(https://example.org/blog/about)"));
+        assert!(content.contains("(https://example.org/blog/2025/images/photo.png)"));
+    }
+
+    #[test]
+    fn discover_feeds_keeps_options_on_the_page_host() {
+        let page = r#"<html><head>
+<link rel="alternate" type="application/rss+xml" href="https://elsewhere.example.com/feed.xml">
+<link rel="alternate" type="application/rss+xml" href="/private.xml">
+</head><body></body></html>"#;
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.org/", page)
+            .file(
+                "https://elsewhere.example.com/feed.xml",
+                "rss20_wordpress.xml",
+            )
+            .file("https://example.org/private.xml", "atom10_xhtml.xml");
+
+        let http = HttpOptions {
+            user_agent: Some("private".to_string()),
+            ..Default::default()
+        };
+        discover_feeds(&fetcher, "https://example.org/", Some(&http)).unwrap();
+
+        let sent: Vec<(String, bool)> = fetcher
+            .requests()
+            .into_iter()
+            .zip(fetcher.requests_with_options())
+            .collect();
+        assert!(sent.contains(&("https://example.org/".to_string(), true)));
+        assert!(sent.contains(&("https://example.org/private.xml".to_string(), true)));
+        assert!(sent.contains(&("https://example.org/feed".to_string(), true)));
+        assert!(sent.contains(&("https://elsewhere.example.com/feed.xml".to_string(), false)));
+    }
+
+    #[test]
+    fn get_feed_from_local_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("changelog.xml");
+        std::fs::write(&path, fixture!("rss20_wordpress.xml")).unwrap();
+
+        let (feed, _) =
+            get_feed_with_data(&FixtureFetcher::default(), path.to_str().unwrap(), None).unwrap();
+        assert!(feed.feed_url.starts_with("file://"), "{}", feed.feed_url);
+
+        // Updates read the file again through the stored URL
+        let entries = get_feed_entries(&FixtureFetcher::default(), &feed).unwrap();
+        assert!(!entries.is_empty());
+    }
+
+    #[test]
+    fn fixture_rss090() {
+        let xml = fixture!("rss090.rdf");
+
+        let feed = parse(xml, "http://www.mozilla.org/news.rdf").unwrap();
+        assert_eq!(feed.title, "Mozilla Dot Org");
+        assert_eq!(feed.description, "the Mozilla Organization web site");
+        assert_eq!(feed.url, "http://www.mozilla.org/");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "New Status Updates");
+        assert_eq!(entries[0].url, "http://www.mozilla.org/status/");
+        assert_eq!(entries[1].url, "http://www.mozilla.org/bugs/");
+    }
+
+    #[test]
+    fn fixture_rss091() {
+        let xml = fixture!("rss091.xml");
+
+        let feed = parse(xml, "http://writetheweb.com/rss.xml").unwrap();
+        assert_eq!(feed.title, "WriteTheWeb");
+        assert_eq!(feed.url, "http://writetheweb.com/");
+        assert_eq!(feed.author, "WriteTheWeb");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "Giving the world a pluggable Gnutella");
+        assert_eq!(entries[0].url, "http://writetheweb.com/read.php?item=24");
+        assert!(entries[1].text.starts_with("After a period of dormancy"));
+    }
+
+    #[test]
+    fn fixture_rss10() {
+        let xml = fixture!("rss10.rdf");
+
+        let feed = parse(xml, "https://news.example.org/index.rdf").unwrap();
+        assert_eq!(feed.title, "Example News");
+        assert_eq!(feed.url, "https://news.example.org/");
+        assert_eq!(feed.author, "editors@example.org");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let first = &entries[0];
+        assert_eq!(first.title, "Kernel 9.0 Released");
+        assert_eq!(first.author, "msmash");
+        assert_eq!(first.categories, vec!["linux"]);
+        assert_eq!(first.date, parse_date("2024-05-12T14:00:00+00:00").unwrap());
+        assert!(
+            first
+                .text
+                .contains("(https://news.example.org/tags/scheduler)")
+        );
+        assert_eq!(
+            first.description,
+            "The new kernel is out, with a rewritten scheduler."
+        );
+
+        // No <link>, the rdf:about attribute is the item's URL
+        assert_eq!(entries[1].url, "https://news.example.org/story/2");
+        assert_eq!(entries[1].author, "BeauHD");
+    }
+
+    #[test]
+    fn fixture_rss20_wordpress() {
+        let xml = fixture!("rss20_wordpress.xml");
+
+        let feed = parse(xml, "https://blog.example.com/feed/").unwrap();
+        assert_eq!(feed.title, "A WordPress Blog");
+        assert_eq!(feed.url, "https://blog.example.com/");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        // Neither <atom:link rel="replies"> nor <media:title> get in the way
+        let first = &entries[0];
+        assert_eq!(first.title, "Hello world!");
+        assert_eq!(
+            first.url,
+            "https://blog.example.com/2024/06/04/hello-world/"
+        );
+        assert_eq!(first.author, "Jane Doe");
+        assert_eq!(first.categories, vec!["Uncategorized"]);
+        assert!(
+            first
+                .text
+                .contains("https://blog.example.com/wp-content/uploads/2024/06/first.jpg")
+        );
+        assert!(first.enclosures.is_empty());
+
+        let second = &entries[1];
+        assert_eq!(second.url, "https://blog.example.com/?p=2");
+        assert_eq!(second.author, "John Roe");
+    }
+
+    #[test]
+    fn fixture_atom03() {
+        let xml = fixture!("atom03.xml");
+
+        let feed = parse(xml, "http://oldblog.blogspot.com/atom.xml").unwrap();
+        assert_eq!(feed.title, "An Old Blogger Blog");
+        assert_eq!(feed.description, "Posting since 2004");
+        assert_eq!(feed.url, "http://oldblog.blogspot.com/");
+        assert_eq!(feed.author, "Old Blogger");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let first = &entries[0];
+        assert_eq!(first.title, "Summer");
+        assert_eq!(first.url, "http://oldblog.blogspot.com/2004/07/summer.html");
+        assert_eq!(first.author, "Guest Writer");
+        assert_eq!(first.date, parse_date("2004-07-15T12:50:00-07:00").unwrap());
+        assert!(first.text.contains("**hot**"));
+        assert!(
+            first
+                .text
+                .contains("(http://oldblog.blogspot.com/2004/06/spring.html)")
+        );
+
+        let second = &entries[1];
+        assert_eq!(second.author, "Old Blogger");
+        assert_eq!(second.text, "Flowers & rain.");
+    }
+
+    #[test]
+    fn fixture_atom10_xhtml() {
+        let xml = fixture!("atom10_xhtml.xml");
+
+        let feed = parse(xml, "https://www.example.net/feed.atom").unwrap();
+        assert_eq!(feed.title, "dive into mark");
+        assert_eq!(feed.url, "https://www.example.net/");
+
+        let entries = get_feed_entries_doc(xml, &feed.author, &feed.url).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let first = &entries[0];
+        assert_eq!(first.url, "https://www.example.net/2005/04/02/atom");
+        assert_eq!(first.author, "Mark Pilgrim");
+        assert_eq!(first.date, parse_date("2003-12-13T08:29:29-04:00").unwrap());
+        assert!(first.text.contains("The Atom draft is finished."));
+        assert!(
+            first
+                .text
+                .contains("(https://www.example.net/2005/04/02/diff.html)")
+        );
+        assert_eq!(first.description, "Update: the spec is out.");
+        assert_eq!(first.enclosures.len(), 1);
+        assert_eq!(first.enclosures[0].length, 1337);
+        assert_eq!(first.categories, vec!["Atom", "xml"]);
+
+        let second = &entries[1];
+        assert_eq!(
+            second.url,
+            "https://www.example.net/2005/03/01/ids-can-be-links"
+        );
+        assert_eq!(second.text, "Plain text content");
     }
 }
@@ -1,3 +1,22 @@
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Output, Stdio},
+    sync::LazyLock,
+    thread,
+};
+
+use color_eyre::eyre::{bail, eyre};
+use regex::{Captures, Regex};
+use url::Url;
+
+static HTML_URL_ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)(\s(?:href|src|poster)\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap()
+});
+
+static HTML_SRCSET_ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)(\ssrcset\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
 #[must_use]
 pub fn normalize_and_truncate<S: AsRef<str>>(input: S, max_len: usize) -> String {
     let s = input.as_ref();
@@ -29,6 +48,138 @@ pub fn normalize_and_truncate<S: AsRef<str>>(input: S, max_len: usize) -> String
     }
 }
 
+/// Resolves `url` against `base` when it's relative. Absolute URLs are returned as they are, and
+/// `None` is returned when it can't be made absolute.
+pub fn resolve_url(url: &str, base: Option<&Url>) -> Option<Url> {
+    match Url::parse(url.trim()) {
+        Ok(url) => Some(url),
+        Err(url::ParseError::RelativeUrlWithoutBase) => base?.join(url.trim()).ok(),
+        Err(_) => None,
+    }
+}
+
+/// Makes every relative `href`, `src`, `poster` and `srcset` URL in `html` absolute, so links and
+/// images keep working once the content is converted to Markdown and read outside of its page.
+#[must_use]
+pub fn absolutize_links(html: &str, base: &Url) -> String {
+    let html = HTML_SRCSET_ATTRIBUTE.replace_all(html, |caps: &Captures| {
+        let (value, quote) = match caps.get(2) {
+            Some(value) => (value.as_str(), '"'),
+            None => (caps.get(3).map_or("", |v| v.as_str()), '\''),
+        };
+
+        // Candidates are a URL, optionally followed by a width or density descriptor
+        let candidates: Vec<String> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|candidate| !candidate.is_empty())
+            .map(|candidate| {
+                let (url, descriptor) = candidate
+                    .split_once(char::is_whitespace)
+                    .unwrap_or((candidate, ""));
+                let url = resolve_url(url, Some(base)).map_or(url.to_string(), String::from);
+
+                match descriptor.trim() {
+                    "" => url,
+                    descriptor => format!("{url} {descriptor}"),
+                }
+            })
+            .collect();
+
+        format!("{}{quote}{}{quote}", &caps[1], candidates.join(", "))
+    });
+
+    HTML_URL_ATTRIBUTE
+        .replace_all(&html, |caps: &Captures| {
+            let (value, quote) = match caps.get(2) {
+                Some(value) => (value.as_str(), '"'),
+                None => (caps.get(3).map_or("", |v| v.as_str()), '\''),
+            };
+
+            match resolve_url(value, Some(base)) {
+                Some(url) if !value.is_empty() => {
+                    format!("{}{quote}{}{quote}", &caps[1], url)
+                }
+                _ => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Runs `command` through the system shell and returns its standard output
+pub fn run_command(command: &str) -> color_eyre::Result<Vec<u8>> {
+    let output = shell(command)?
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| eyre!("Couldn't run \"{}\": {}", command, e))?;
+
+    check_output(command, output)
+}
+
+/// Runs `command` through the system shell from `dir`, writing `input` to its standard input
+pub fn run_command_with_input(
+    command: &str,
+    input: &[u8],
+    dir: &Path,
+) -> color_eyre::Result<Vec<u8>> {
+    let mut child = shell(command)?
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| eyre!("Couldn't run \"{}\": {}", command, e))?;
+
+    // Written from another thread, so a command producing output before reading all of its input
+    // doesn't block
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = thread::spawn(move || {
+        // Commands are free to ignore their input
+        let _ = stdin.write_all(&input);
+    });
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| eyre!("Couldn't run \"{}\": {}", command, e))?;
+    let _ = writer.join();
+
+    check_output(command, output)
+}
+
+fn shell(command: &str) -> color_eyre::Result<Command> {
+    if command.is_empty() {
+        bail!("Empty command");
+    }
+
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+
+    shell.arg(command);
+    Ok(shell)
+}
+
+fn check_output(command: &str, output: Output) -> color_eyre::Result<Vec<u8>> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "Command \"{}\" failed with {}: {}",
+            command,
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    Ok(output.stdout)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +283,45 @@ mod tests {
         assert_eq!(normalize_and_truncate("1234567890", 10), "1234567890");
         assert_eq!(normalize_and_truncate("12345678901", 10), "1234567...");
     }
+
+    #[test]
+    fn resolves_relative_urls() {
+        let base = Url::parse("https://example.com/blog/post.html").unwrap();
+
+        assert_eq!(
+            resolve_url("/images/foo.png", Some(&base))
+                .unwrap()
+                .as_str(),
+            "https://example.com/images/foo.png"
+        );
+        assert_eq!(
+            resolve_url("other.html", Some(&base)).unwrap().as_str(),
+            "https://example.com/blog/other.html"
+        );
+        assert_eq!(
+            resolve_url("https://other.org/a", Some(&base))
+                .unwrap()
+                .as_str(),
+            "https://other.org/a"
+        );
+        assert_eq!(resolve_url("/images/foo.png", None), None);
+    }
+
+    #[test]
+    fn absolutizes_html_links() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let html = r#"<p><a href="/about">About</a> <img src='img/a.png' alt="a"> <a href="mailto:me@example.com">Mail</a></p>"#;
+
+        assert_eq!(
+            absolutize_links(html, &base),
+            r#"<p><a href="https://example.com/about">About</a> <img src='https://example.com/blog/img/a.png' alt="a"> <a href="mailto:me@example.com">Mail</a></p>"#
+        );
+
+        let html =
+            r#"<img srcset="a.png 1x,/b.png 2x, https://cdn.example.com/c.png" src="a.png">"#;
+        assert_eq!(
+            absolutize_links(html, &base),
+            r#"<img srcset="https://example.com/blog/a.png 1x, https://example.com/b.png 2x, https://cdn.example.com/c.png" src="https://example.com/blog/a.png">"#
+        );
+    }
 }
@@ -0,0 +1,364 @@
+use std::{collections::HashMap, io::Read};
+
+use color_eyre::eyre::{bail, eyre};
+use url::Url;
+
+use crate::core::{feed::charset, library::feeditem::HttpOptions};
+
+/// Largest feed or page accepted by `get_text` unless the fetcher says otherwise
+pub const DEFAULT_MAX_BODY: u64 = 20 * 1024 * 1024;
+
+const MAX_REDIRECTS: usize = 10;
+
+/// The answer to a request. Header names are lowercase, and the body is read as it arrives.
+pub struct FetchResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: Box<dyn Read + Send>,
+}
+
+impl FetchResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(&name.to_lowercase()).map(String::as_str)
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status, 301 | 302 | 303 | 307 | 308)
+    }
+
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("content-length")?.trim().parse().ok()
+    }
+}
+
+/// Gets documents from the web. Everything bulletty downloads goes through one of these, so
+/// connections are reused and tests can answer requests without the network. Implementations
+/// return redirects as they are; they are followed by `fetch`.
+pub trait Fetcher: Send + Sync {
+    fn fetch(&self, url: &str, http: Option<&HttpOptions>) -> color_eyre::Result<FetchResponse>;
+
+    /// Feeds and pages bigger than this are rejected
+    fn max_body(&self) -> u64 {
+        DEFAULT_MAX_BODY
+    }
+}
+
+/// A response after following redirects
+pub struct Fetched {
+    pub response: FetchResponse,
+    /// Where the response came from
+    pub url: String,
+    /// Whether every redirect on the way was permanent, so `url` should be used from now on
+    pub moved: bool,
+}
+
+/// A downloaded text document
+pub struct Document {
+    pub text: String,
+    pub url: String,
+    pub moved: bool,
+}
+
+/// Whether both URLs have the same scheme, host and port
+pub fn same_origin(a: &str, b: &str) -> bool {
+    match (Url::parse(a), Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Requests `url` with `fetcher`, following redirects. The feed's request options are only sent
+/// to the host they were configured for.
+pub fn fetch(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<Fetched> {
+    let origin = Url::parse(url).ok().map(|u| u.origin());
+    let mut current = url.to_string();
+    let mut moved = true;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let same_origin = Url::parse(&current).ok().map(|u| u.origin()) == origin;
+        let response = fetcher.fetch(&current, http.filter(|_| same_origin))?;
+
+        if !response.is_redirect() {
+            return Ok(Fetched {
+                moved: moved && current != url,
+                response,
+                url: current,
+            });
+        }
+
+        let location = response
+            .header("location")
+            .ok_or_else(|| eyre!("Redirect from \"{}\" has no location", current))?;
+
+        moved &= matches!(response.status, 301 | 308);
+        current = Url::parse(&current)
+            .and_then(|base| base.join(location))
+            .map(String::from)
+            .map_err(|e| eyre!("Invalid redirect from \"{}\": {}", current, e))?;
+    }
+
+    bail!("Too many redirects from \"{}\"", url)
+}
+
+/// Downloads `url` as UTF-8, transcoding it from the charset given by the server or declared in
+/// the document. Unsuccessful responses are errors.
+pub fn get_document(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<Document> {
+    let Fetched {
+        response,
+        url: final_url,
+        moved,
+    } = fetch(fetcher, url, http)?;
+
+    if !response.is_success() {
+        return Err(eyre!(
+            "Request to \"{}\" returned status code {}",
+            final_url,
+            response.status
+        ));
+    }
+
+    let max_body = fetcher.max_body();
+    if response.content_length().is_some_and(|len| len > max_body) {
+        bail!("Response from \"{}\" is too large", final_url);
+    }
+
+    let content_type = response.header("content-type").map(String::from);
+
+    let mut bytes = vec![];
+    response
+        .body
+        .take(max_body.saturating_add(1))
+        .read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 > max_body {
+        bail!("Response from \"{}\" is too large", final_url);
+    }
+
+    Ok(Document {
+        text: charset::decode(&bytes, content_type.as_deref()),
+        url: final_url,
+        moved,
+    })
+}
+
+pub fn get_text(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    http: Option<&HttpOptions>,
+) -> color_eyre::Result<String> {
+    Ok(get_document(fetcher, url, http)?.text)
+}
+
+#[cfg(test)]
+pub mod fixture {
+    use std::{collections::HashMap, io::Cursor, sync::Mutex};
+
+    use color_eyre::eyre::eyre;
+
+    use super::*;
+
+    enum Canned {
+        Response {
+            status: u16,
+            headers: HashMap<String, String>,
+            body: Vec<u8>,
+        },
+        Error(String),
+    }
+
+    /// Answers requests with canned responses, read from the test fixtures or given inline.
+    /// Unknown URLs fail like an unreachable host.
+    #[derive(Default)]
+    pub struct FixtureFetcher {
+        responses: Mutex<HashMap<String, Canned>>,
+        requests: Mutex<Vec<(String, bool)>>,
+        max_body: Option<u64>,
+    }
+
+    impl FixtureFetcher {
+        pub fn body(&self, url: &str, body: &str) -> &Self {
+            self.response(url, 200, &[], body.as_bytes())
+        }
+
+        /// Serves `tests/fixtures/feeds/<name>`
+        pub fn file(&self, url: &str, name: &str) -> &Self {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("tests/fixtures/feeds")
+                .join(name);
+            let body = std::fs::read(&path).unwrap_or_else(|e| panic!("{path:?}: {e}"));
+            self.response(url, 200, &[], &body)
+        }
+
+        pub fn status(&self, url: &str, status: u16) -> &Self {
+            self.response(url, status, &[], b"")
+        }
+
+        pub fn redirect(&self, url: &str, status: u16, location: &str) -> &Self {
+            self.response(url, status, &[("Location", location)], b"")
+        }
+
+        pub fn error(&self, url: &str, message: &str) -> &Self {
+            self.responses
+                .lock()
+                .unwrap()
+                .insert(url.to_string(), Canned::Error(message.to_string()));
+            self
+        }
+
+        pub fn response(
+            &self,
+            url: &str,
+            status: u16,
+            headers: &[(&str, &str)],
+            body: &[u8],
+        ) -> &Self {
+            let headers = headers
+                .iter()
+                .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+                .collect();
+
+            self.responses.lock().unwrap().insert(
+                url.to_string(),
+                Canned::Response {
+                    status,
+                    headers,
+                    body: body.to_vec(),
+                },
+            );
+            self
+        }
+
+        pub fn with_max_body(mut self, max_body: u64) -> Self {
+            self.max_body = Some(max_body);
+            self
+        }
+
+        /// URLs requested so far, in order
+        pub fn requests(&self) -> Vec<String> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(url, _)| url.to_string())
+                .collect()
+        }
+
+        /// Whether the request options were sent along with each request so far
+        pub fn requests_with_options(&self) -> Vec<bool> {
+            self.requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(_, with_options)| *with_options)
+                .collect()
+        }
+    }
+
+    impl Fetcher for FixtureFetcher {
+        fn fetch(
+            &self,
+            url: &str,
+            http: Option<&HttpOptions>,
+        ) -> color_eyre::Result<FetchResponse> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((url.to_string(), http.is_some()));
+
+            match self.responses.lock().unwrap().get(url) {
+                Some(Canned::Response {
+                    status,
+                    headers,
+                    body,
+                }) => Ok(FetchResponse {
+                    status: *status,
+                    headers: headers.clone(),
+                    body: Box::new(Cursor::new(body.clone())),
+                }),
+                Some(Canned::Error(message)) => Err(eyre!("{}", message)),
+                None => Err(eyre!("Couldn't connect to \"{}\"", url)),
+            }
+        }
+
+        fn max_body(&self) -> u64 {
+            self.max_body.unwrap_or(DEFAULT_MAX_BODY)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixture::FixtureFetcher, *};
+
+    #[test]
+    fn get_text_checks_status_and_size() {
+        let fetcher = FixtureFetcher::default().with_max_body(6);
+        fetcher
+            .body("https://example.org/feed", "<rss/>")
+            .body("https://example.org/big", "<rss></rss>")
+            .status("https://example.org/missing", 404);
+
+        assert_eq!(
+            get_text(&fetcher, "https://example.org/feed", None).unwrap(),
+            "<rss/>"
+        );
+
+        let err = get_text(&fetcher, "https://example.org/missing", None).unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+
+        let err = get_text(&fetcher, "https://example.org/big", None).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+
+        let err = get_text(&fetcher, "https://example.org/unknown", None).unwrap_err();
+        assert!(err.to_string().contains("connect"), "{err}");
+    }
+
+    #[test]
+    fn follows_redirects() {
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .redirect("https://example.org/rss", 301, "/feed.xml")
+            .redirect(
+                "https://example.org/feed.xml",
+                302,
+                "https://cdn.example.com/feed",
+            )
+            .redirect("https://example.org/old", 308, "https://example.org/new")
+            .redirect("https://example.org/loop", 302, "https://example.org/loop")
+            .body("https://cdn.example.com/feed", "<rss/>")
+            .body("https://example.org/new", "<feed/>");
+
+        let http = HttpOptions {
+            user_agent: Some("Test".to_string()),
+            ..HttpOptions::default()
+        };
+
+        // A temporary redirect on the way means the original URL is still the one to use
+        let doc = get_document(&fetcher, "https://example.org/rss", Some(&http)).unwrap();
+        assert_eq!(doc.text, "<rss/>");
+        assert_eq!(doc.url, "https://cdn.example.com/feed");
+        assert!(!doc.moved);
+
+        // Options aren't sent to other hosts
+        assert_eq!(fetcher.requests_with_options(), vec![true, true, false]);
+
+        let doc = get_document(&fetcher, "https://example.org/old", None).unwrap();
+        assert_eq!(doc.url, "https://example.org/new");
+        assert!(doc.moved);
+
+        let err = get_text(&fetcher, "https://example.org/loop", None).unwrap_err();
+        assert!(err.to_string().contains("Too many redirects"), "{err}");
+    }
+}
@@ -5,10 +5,15 @@ use tl::{Bytes, Node, VDom};
 use url::Url;
 
 pub fn is_html(content: &str) -> bool {
-    let trimmed = content.trim_start();
-    trimmed.starts_with("<!DOCTYPE html")
-        || trimmed.starts_with("<html")
-        || trimmed.starts_with("<HTML")
+    let start: String = content
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .chars()
+        .take(14)
+        .collect::<String>()
+        .to_lowercase();
+
+    start.starts_with("<!doctype html") || start.starts_with("<html")
 }
 
 pub struct LinkParser<'input, 'url> {
@@ -206,6 +211,12 @@ mod tests {
         assert!(is_html("  \n  <!DOCTYPE html><html></html>"));
     }
 
+    #[test]
+    fn lowercase_doctype_is_html() {
+        assert!(is_html("<!doctype html><html></html>"));
+        assert!(is_html("\u{feff}<HTML lang=\"en\"></HTML>"));
+    }
+
     #[test]
     fn html_tag_is_html() {
         assert!(is_html("<html><head></head></html>"));
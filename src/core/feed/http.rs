@@ -0,0 +1,302 @@
+use std::{thread, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Proxy, StatusCode,
+    blocking::{Client, RequestBuilder, Response},
+    header, redirect,
+};
+use tracing::warn;
+
+use crate::core::{
+    feed::fetcher::{FetchResponse, Fetcher},
+    library::{
+        feeditem::{Auth, HttpOptions},
+        settings::network::Network,
+    },
+};
+
+/// Longest wait accepted from a `Retry-After` header
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// The fetcher used outside of tests, configured from the network settings. It holds a single
+/// connection pool shared by every request.
+pub struct HttpClient {
+    client: Client,
+    max_body: u64,
+    retries: u32,
+    retry_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new(network: &Network) -> color_eyre::Result<Self> {
+        let mut builder = Client::builder()
+            .user_agent(format!("bulletty/{}", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(network.connect_timeout())
+            .timeout(network.timeout())
+            .redirect(redirect::Policy::none());
+
+        if let Some(proxy) = network.proxy.as_deref().filter(|p| !p.trim().is_empty()) {
+            builder = builder.proxy(Proxy::all(proxy.trim())?);
+        }
+
+        Ok(Self {
+            client: builder.build()?,
+            max_body: network.max_body_bytes(),
+            retries: network.retries,
+            retry_backoff: network.retry_backoff(),
+        })
+    }
+
+    /// Builds a GET request for `url` with the feed's user agent, headers and credentials
+    pub fn request(
+        &self,
+        url: &str,
+        http: Option<&HttpOptions>,
+    ) -> color_eyre::Result<RequestBuilder> {
+        let mut request = self.client.get(url);
+
+        let Some(http) = http else {
+            return Ok(request);
+        };
+
+        if let Some(user_agent) = http.user_agent.as_deref() {
+            request = request.header(header::USER_AGENT, user_agent);
+        }
+
+        for (name, value) in http.headers.iter() {
+            request = request.header(name, value.resolve()?);
+        }
+
+        if let Some(cookie) = http.cookie.as_ref() {
+            request = request.header(header::COOKIE, cookie.resolve()?);
+        }
+
+        request = match http.auth.as_ref() {
+            Some(Auth::Basic { username, password }) => {
+                let password = password.as_ref().map(|p| p.resolve()).transpose()?;
+                request.basic_auth(username, password)
+            }
+            Some(Auth::Bearer { token }) => request.bearer_auth(token.resolve()?),
+            None => request,
+        };
+
+        Ok(request)
+    }
+
+    /// Sends `request`, trying again with exponential backoff on timeouts, connection errors,
+    /// 429 and 5xx responses. `Retry-After` is honored when the server sends it.
+    pub fn send(&self, mut request: RequestBuilder) -> color_eyre::Result<Response> {
+        let mut attempt = 0;
+
+        loop {
+            // Requests with streaming bodies can't be cloned, and are only sent once
+            let Some(retry) = request.try_clone().filter(|_| attempt < self.retries) else {
+                return Ok(request.send()?);
+            };
+
+            let backoff = self
+                .retry_backoff
+                .saturating_mul(2u32.saturating_pow(attempt));
+
+            let delay = match request.send() {
+                Ok(response) if is_transient(response.status()) => {
+                    warn!(
+                        "Request to \"{}\" returned {}, retrying",
+                        response.url(),
+                        response.status()
+                    );
+                    retry_after(&response).unwrap_or(backoff)
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if e.is_timeout() || e.is_connect() => {
+                    warn!("Request failed, retrying: {}", e);
+                    backoff
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            thread::sleep(delay.min(MAX_RETRY_DELAY));
+            request = retry;
+            attempt += 1;
+        }
+    }
+}
+
+impl Fetcher for HttpClient {
+    fn fetch(&self, url: &str, http: Option<&HttpOptions>) -> color_eyre::Result<FetchResponse> {
+        let response = self.send(self.request(url, http)?)?;
+
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        Ok(FetchResponse {
+            status: response.status().as_u16(),
+            headers,
+            body: Box::new(response),
+        })
+    }
+
+    fn max_body(&self) -> u64 {
+        self.max_body
+    }
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Delay asked by a `Retry-After` header, given in seconds or as an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - now)
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::feed::fetcher::get_text;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    /// Serves `responses` in order, one per connection, and returns the server's URL
+    fn serve(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        url
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {status}\r\nConnection: close\r\nContent-Length: {}\r\n{headers}\r\n{body}",
+            body.len()
+        )
+    }
+
+    fn test_client(retries: u32) -> HttpClient {
+        let mut network = Network::default();
+        network.retries = retries;
+        network.retry_backoff_ms = 1;
+        HttpClient::new(&network).unwrap()
+    }
+
+    #[test]
+    fn request_applies_http_options() {
+        use crate::core::library::feeditem::Secret;
+
+        let http = HttpOptions {
+            user_agent: Some("Mozilla/5.0".to_string()),
+            cookie: Some(Secret("session=1".to_string())),
+            headers: [("X-Api-Key".to_string(), Secret("key".to_string()))].into(),
+            auth: Some(Auth::Bearer {
+                token: Secret("token".to_string()),
+            }),
+        };
+
+        let client = HttpClient::new(&Network::default()).unwrap();
+
+        let built = client
+            .request("https://example.org/feed", Some(&http))
+            .unwrap()
+            .build()
+            .unwrap();
+        let headers = built.headers();
+
+        assert_eq!(headers[header::USER_AGENT], "Mozilla/5.0");
+        assert_eq!(headers[header::COOKIE], "session=1");
+        assert_eq!(headers["X-Api-Key"], "key");
+        assert_eq!(headers[header::AUTHORIZATION], "Bearer token");
+
+        let built = client
+            .request("https://example.org/feed", None)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(built.headers().get(header::AUTHORIZATION).is_none());
+    }
+
+    #[test]
+    fn retries_transient_responses() {
+        let url = serve(vec![
+            response("503 Service Unavailable", "Retry-After: 0\r\n", ""),
+            response("429 Too Many Requests", "", ""),
+            response("200 OK", "", "<rss/>"),
+        ]);
+
+        assert_eq!(get_text(&test_client(2), &url, None).unwrap(), "<rss/>");
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let url = serve(vec![
+            response("500 Internal Server Error", "", ""),
+            response("502 Bad Gateway", "", ""),
+        ]);
+
+        let err = get_text(&test_client(1), &url, None).unwrap_err();
+        assert!(err.to_string().contains("502"), "{err}");
+    }
+
+    #[test]
+    fn rejects_large_bodies() {
+        let url = serve(vec![response("200 OK", "", "0123456789")]);
+
+        let mut client = test_client(0);
+        client.max_body = 4;
+        let err = get_text(&client, &url, None).unwrap_err();
+        assert!(err.to_string().contains("too large"), "{err}");
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
@@ -1,4 +1,10 @@
+mod charset;
 pub mod feedentry;
+mod feedformat;
 pub mod feedparser;
 pub mod feedutils;
+pub mod fetcher;
 mod html;
+pub mod http;
+mod readability;
+pub mod source;
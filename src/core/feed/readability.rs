@@ -0,0 +1,316 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+use tl::{HTMLTag, Node, Parser, VDom};
+
+use crate::core::feed::html::ParseError;
+
+const CANDIDATE_TAGS: [&str; 6] = ["article", "main", "section", "div", "td", "body"];
+const PARAGRAPH_TAGS: [&str; 3] = ["p", "pre", "blockquote"];
+
+static POSITIVE_HINTS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)article|body|content|entry|main|page|post|text|blog|story").unwrap()
+});
+
+static NEGATIVE_HINTS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)comment|meta|footer|footnote|sidebar|sponsor|banner|\bads?\b|nav|menu|share|social|related|promo|widget|popup|cookie").unwrap()
+});
+
+const UNWANTED_TAGS: [&str; 8] = [
+    "script", "style", "noscript", "nav", "aside", "form", "iframe", "button",
+];
+
+const VOID_TAGS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Finds the element that most likely holds the main content of an HTML page and returns its
+/// inner HTML. It follows the same idea as Readability: paragraphs add to the score of their
+/// parent and grandparent, class and id names hint at content or clutter, and containers that
+/// are mostly links are penalized.
+pub fn extract_main_content(input: &str) -> Result<Option<String>, ParseError> {
+    let dom = tl::parse(input, tl::ParserOptions::default())?;
+    let parser = dom.parser();
+
+    let best = candidates(&dom)
+        .map(|tag| (score(tag, parser), tag))
+        .filter(|(score, _)| *score > 0.0)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, tag)| tag);
+
+    Ok(best.map(|tag| {
+        let mut content = String::new();
+        write_clean_children(tag, parser, &mut content);
+        content.trim().to_string()
+    }))
+}
+
+/// Writes the inner HTML of `tag`, leaving out unwanted elements along with everything inside
+/// them
+fn write_clean_children(tag: &HTMLTag, parser: &Parser, out: &mut String) {
+    for node in tag
+        .children()
+        .top()
+        .iter()
+        .filter_map(|handle| handle.get(parser))
+    {
+        let Node::Tag(child) = node else {
+            out.push_str(&node.outer_html(parser));
+            continue;
+        };
+
+        if is_unwanted(child) {
+            continue;
+        }
+
+        // Tags are written again rather than copied, since tl puts attribute values between
+        // double quotes as they are
+        let name = child.name().as_utf8_str();
+        out.push('<');
+        out.push_str(&name);
+        for (key, value) in child.attributes().iter() {
+            out.push(' ');
+            out.push_str(&key);
+            if let Some(value) = value {
+                // Values are as written in the page, possibly with entities or quotes of their own
+                let value = html_escape::decode_html_entities(&value);
+                out.push_str("=\"");
+                out.push_str(&html_escape::encode_double_quoted_attribute(&value));
+                out.push('"');
+            }
+        }
+        out.push('>');
+
+        if VOID_TAGS.contains(&tag_name(child).as_str()) {
+            continue;
+        }
+
+        write_clean_children(child, parser, out);
+
+        out.push_str("</");
+        out.push_str(&name);
+        out.push('>');
+    }
+}
+
+fn is_unwanted(tag: &HTMLTag) -> bool {
+    UNWANTED_TAGS.contains(&tag_name(tag).as_str())
+}
+
+fn candidates<'a>(dom: &'a VDom<'a>) -> impl Iterator<Item = &'a HTMLTag<'a>> {
+    dom.nodes()
+        .iter()
+        .filter_map(Node::as_tag)
+        .filter(|tag| CANDIDATE_TAGS.contains(&tag_name(tag).as_str()))
+}
+
+fn score(tag: &HTMLTag, parser: &Parser) -> f32 {
+    let mut score = 0.0;
+
+    for child in children(tag, parser) {
+        if is_paragraph(child) {
+            score += paragraph_score(child, parser);
+        } else {
+            score += children(child, parser)
+                .filter(|grandchild| is_paragraph(grandchild))
+                .map(|grandchild| paragraph_score(grandchild, parser) / 2.0)
+                .sum::<f32>();
+        }
+    }
+
+    if score == 0.0 {
+        return 0.0;
+    }
+
+    score += class_weight(tag);
+
+    if matches!(tag_name(tag).as_str(), "article" | "main") {
+        score += 10.0;
+    }
+
+    score * (1.0 - link_density(tag, parser))
+}
+
+fn paragraph_score(tag: &HTMLTag, parser: &Parser) -> f32 {
+    let text = tag.inner_text(parser);
+    let len = text.trim().chars().count();
+
+    if len < 25 {
+        return 0.0;
+    }
+
+    1.0 + text.matches(',').count() as f32 + (len as f32 / 100.0).min(3.0)
+}
+
+fn class_weight(tag: &HTMLTag) -> f32 {
+    let mut weight = 0.0;
+
+    for attribute in [tag.attributes().class(), tag.attributes().id()]
+        .into_iter()
+        .flatten()
+    {
+        let value = attribute.as_utf8_str();
+
+        if NEGATIVE_HINTS.is_match(&value) {
+            weight -= 25.0;
+        }
+
+        if POSITIVE_HINTS.is_match(&value) {
+            weight += 25.0;
+        }
+    }
+
+    weight
+}
+
+fn link_density(tag: &HTMLTag, parser: &Parser) -> f32 {
+    let text_len = tag.inner_text(parser).chars().count();
+    if text_len == 0 {
+        return 0.0;
+    }
+
+    let link_len: usize = tag
+        .children()
+        .all(parser)
+        .iter()
+        .filter_map(Node::as_tag)
+        .filter(|t| tag_name(t) == "a")
+        .map(|t| t.inner_text(parser).chars().count())
+        .sum();
+
+    (link_len as f32 / text_len as f32).min(1.0)
+}
+
+fn children<'p, 'a>(
+    tag: &'p HTMLTag<'a>,
+    parser: &'p Parser<'a>,
+) -> impl Iterator<Item = &'p HTMLTag<'a>> {
+    let handles: Vec<_> = tag.children().top().iter().copied().collect();
+
+    handles
+        .into_iter()
+        .filter_map(move |handle| handle.get(parser))
+        .filter_map(Node::as_tag)
+}
+
+fn is_paragraph(tag: &HTMLTag) -> bool {
+    PARAGRAPH_TAGS.contains(&tag_name(tag).as_str())
+}
+
+fn tag_name(tag: &HTMLTag) -> String {
+    tag.name().as_utf8_str().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE_TEXT: &str = "This is a long paragraph about something interesting, \
+        with enough words, commas, and substance to look like actual content.";
+
+    #[test]
+    fn picks_article_over_navigation() {
+        let html = format!(
+            r#"<!DOCTYPE html>
+<html>
+<body>
+<nav class="menu"><a href="/">Home</a> <a href="/about">About</a></nav>
+<div id="sidebar"><p>Subscribe to our newsletter to get more of the same, every week.</p></div>
+<div class="post-content">
+<h1>The Title</h1>
+<p>{ARTICLE_TEXT}</p>
+<p>{ARTICLE_TEXT}</p>
+<script>console.log("tracking");</script>
+</div>
+<footer><p>Copyright notice and a bunch of other links for the footer.</p></footer>
+</body>
+</html>"#
+        );
+
+        let content = extract_main_content(&html).unwrap().unwrap();
+
+        assert!(content.starts_with("<h1>The Title</h1>"));
+        assert!(content.contains(ARTICLE_TEXT));
+        assert!(!content.contains("newsletter"));
+        assert!(!content.contains("tracking"));
+    }
+
+    #[test]
+    fn scores_nested_paragraphs() {
+        let html = format!(
+            r#"<html><body>
+<div class="comments"><p>Great post, thanks for sharing it with all of us here!</p></div>
+<article><div><p>{ARTICLE_TEXT}</p><p>{ARTICLE_TEXT}</p></div></article>
+</body></html>"#
+        );
+
+        let content = extract_main_content(&html).unwrap().unwrap();
+
+        assert!(content.contains(ARTICLE_TEXT));
+        assert!(!content.contains("Great post"));
+    }
+
+    #[test]
+    fn penalizes_link_lists() {
+        let links = "<p><a href=\"/a\">A very long link text that goes on and on, forever</a></p>"
+            .repeat(4);
+        let html = format!(
+            r#"<html><body>
+<div class="content">{links}</div>
+<div class="text"><p>{ARTICLE_TEXT}</p></div>
+</body></html>"#
+        );
+
+        let content = extract_main_content(&html).unwrap().unwrap();
+
+        assert!(content.contains(ARTICLE_TEXT));
+        assert!(!content.contains("forever"));
+    }
+
+    #[test]
+    fn removes_nested_unwanted_elements() {
+        let html = format!(
+            r#"<html><body><article>
+<p>{ARTICLE_TEXT}</p>
+<nav>Jump to <script>var a = "</nav>";</script> the <a href="/next">next post</a></nav>
+<div><aside>Outer <aside>inner</aside> still aside</aside><p>{ARTICLE_TEXT} Again.</p></div>
+<p>The end of it all, with a <img src="a.png"> picture and<br>a break.</p>
+</article></body></html>"#
+        );
+
+        let content = extract_main_content(&html).unwrap().unwrap();
+
+        assert!(content.contains(ARTICLE_TEXT));
+        assert!(content.contains("Again.</p></div>"));
+        assert!(content.contains("<img src=\"a.png\"> picture and<br>a break."));
+        for clutter in ["<nav", "</nav>", "next post", "aside", "var a"] {
+            assert!(!content.contains(clutter), "{clutter} left in {content}");
+        }
+    }
+
+    #[test]
+    fn escapes_rebuilt_attributes() {
+        let html = format!(
+            r#"<html><body><article>
+<p>{ARTICLE_TEXT}</p>
+<div><a href="/search?a=1&amp;b=2" title='Say "hi"'>Link</a><img src=a.png alt="R&D"></div>
+</article></body></html>"#
+        );
+
+        let content = extract_main_content(&html).unwrap().unwrap();
+
+        assert!(content.contains(r#"<img src="a.png" alt="R&amp;D"></div>"#));
+        assert!(
+            content
+                .contains(r#"<a href="/search?a=1&amp;b=2" title="Say &quot;hi&quot;">Link</a>"#),
+            "{content}"
+        );
+    }
+
+    #[test]
+    fn returns_none_without_content() {
+        let html = "<html><body><div><a href=\"/\">Home</a></div></body></html>";
+        assert_eq!(extract_main_content(html).unwrap(), None);
+    }
+}
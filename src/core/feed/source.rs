@@ -0,0 +1,150 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::eyre;
+use url::Url;
+
+use crate::core::feed::{charset, feedutils};
+
+const EXEC_PREFIX: &str = "exec:";
+
+/// Where a feed document comes from: a web server, a file on this machine, or the output of a
+/// command, given as `exec:<command>`.
+#[derive(Debug, PartialEq)]
+pub enum FeedSource {
+    Remote(String),
+    File(PathBuf),
+    Exec(String),
+}
+
+impl FeedSource {
+    pub fn new(url: &str) -> Self {
+        let url = url.trim();
+
+        if let Some(command) = url.strip_prefix(EXEC_PREFIX) {
+            return FeedSource::Exec(command.trim().to_string());
+        }
+
+        match Url::parse(url) {
+            Ok(parsed) if parsed.scheme() == "file" => match parsed.to_file_path() {
+                Ok(path) => FeedSource::File(path),
+                Err(_) => FeedSource::Remote(url.to_string()),
+            },
+            // Windows drive letters parse as a one letter scheme
+            Ok(parsed) if parsed.scheme().len() > 1 => FeedSource::Remote(url.to_string()),
+            _ if Path::new(url).exists() => {
+                let path = Path::new(url);
+                FeedSource::File(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+            }
+            _ => FeedSource::Remote(url.to_string()),
+        }
+    }
+
+    /// Whether `url` is an http or https address. Anything else may read files or run commands
+    /// on this machine, so it's only accepted when the user adds the feed themselves.
+    pub fn is_web_url(url: &str) -> bool {
+        Url::parse(url.trim()).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+    }
+
+    /// The URL stored in the feed settings, which gives this same source back. Local paths are
+    /// stored as absolute `file://` URLs so updates don't depend on the working directory.
+    pub fn url(&self) -> String {
+        match self {
+            FeedSource::Remote(url) => url.to_string(),
+            FeedSource::File(path) => Url::from_file_path(path)
+                .map(String::from)
+                .unwrap_or_else(|_| path.to_string_lossy().to_string()),
+            FeedSource::Exec(command) => format!("{EXEC_PREFIX}{command}"),
+        }
+    }
+
+    /// Reads the feed document from a local source, or `None` for remote ones
+    pub fn read_local(&self) -> Option<color_eyre::Result<String>> {
+        match self {
+            FeedSource::Remote(_) => None,
+            FeedSource::File(path) => Some(
+                fs::read(path)
+                    .map(|bytes| charset::decode(&bytes, None))
+                    .map_err(|e| eyre!("Couldn't read feed file {:?}: {}", path, e)),
+            ),
+            FeedSource::Exec(command) => Some(
+                feedutils::run_command(command)
+                    .map(|stdout| charset::decode(&stdout, None))
+                    .map_err(|e| eyre!("Feed command failed: {}", e)),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn detects_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("feed.xml");
+        fs::write(&path, "<rss/>").unwrap();
+        let path = fs::canonicalize(path).unwrap();
+
+        assert_eq!(
+            FeedSource::new("https://example.org/feed.xml"),
+            FeedSource::Remote("https://example.org/feed.xml".to_string())
+        );
+        assert_eq!(
+            FeedSource::new(path.to_str().unwrap()),
+            FeedSource::File(path.clone())
+        );
+
+        let url = Url::from_file_path(&path).unwrap().to_string();
+        assert_eq!(FeedSource::new(&url), FeedSource::File(path.clone()));
+        assert_eq!(FeedSource::new(path.to_str().unwrap()).url(), url);
+
+        assert_eq!(
+            FeedSource::new("exec: ./changelog.sh --rss"),
+            FeedSource::Exec("./changelog.sh --rss".to_string())
+        );
+        assert_eq!(
+            FeedSource::new("exec: ./changelog.sh --rss").url(),
+            "exec:./changelog.sh --rss"
+        );
+    }
+
+    #[test]
+    fn tells_web_urls() {
+        assert!(FeedSource::is_web_url("https://example.org/feed.xml"));
+        assert!(FeedSource::is_web_url(" http://example.org/feed.xml"));
+        assert!(!FeedSource::is_web_url("exec:curl https://example.org"));
+        assert!(!FeedSource::is_web_url("file:///etc/passwd"));
+        assert!(!FeedSource::is_web_url("/etc/passwd"));
+        assert!(!FeedSource::is_web_url("feed.xml"));
+    }
+
+    #[test]
+    fn reads_local_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("feed.xml");
+        fs::write(&path, "<rss/>").unwrap();
+
+        let source = FeedSource::new(path.to_str().unwrap());
+        assert_eq!(source.read_local().unwrap().unwrap(), "<rss/>");
+
+        assert!(
+            FeedSource::new("https://example.org/feed.xml")
+                .read_local()
+                .is_none()
+        );
+
+        if cfg!(unix) {
+            let source = FeedSource::new("exec:printf '<rss/>'");
+            assert_eq!(source.read_local().unwrap().unwrap(), "<rss/>");
+
+            let source = FeedSource::new("exec:echo oops >&2; exit 3");
+            let err = source.read_local().unwrap().unwrap_err().to_string();
+            assert!(err.contains("oops"), "{err}");
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions, TryLockError},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering::Relaxed},
+    },
+    thread,
+    time::Duration as StdDuration,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::core::{
+    defs::{DATA_DAEMON_STATUS, DATA_UPDATE_LOCK},
+    feed::{feedentry::EntrySummary, fetcher::Fetcher},
+    library::{
+        data::librarydata::LibraryData,
+        feeditem::{FeedItem, MAX_UPDATE_INTERVAL_MINS},
+        settings::hooks::Hooks,
+    },
+};
+
+/// Longest sleep between checks for due feeds, so new feeds and changed intervals are noticed
+const MAX_SLEEP: Duration = Duration::minutes(1);
+
+/// Held while feeds are being updated, so the daemon, `bulletty update` and the TUI never update
+/// the same library at once. Released when dropped, or when the process ends.
+pub struct UpdateLock {
+    _file: File,
+}
+
+impl UpdateLock {
+    /// Takes the lock, or returns `None` when another update is running
+    pub fn acquire(datapath: &Path) -> color_eyre::Result<Option<Self>> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(datapath.join(DATA_UPDATE_LOCK))?;
+
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e.into()),
+        }
+    }
+}
+
+/// Summary of the daemon's last run, written to the library after each one
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub running: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_run: Option<DateTime<Utc>>,
+    pub feeds_updated: usize,
+    pub new_entries: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FeedError>,
+    /// Why the last run failed as a whole, e.g. the library couldn't be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeedError {
+    pub feed: String,
+    pub error: String,
+}
+
+/// Keeps the library up to date, updating each feed once its interval has passed
+pub struct Daemon {
+    data: LibraryData,
+    fetcher: Arc<dyn Fetcher>,
+    hooks: Hooks,
+    /// Used for feeds without their own `update_interval_mins`
+    interval: Duration,
+    /// Failed attempts, so failing feeds wait for their interval too
+    attempts: HashMap<(String, String), DateTime<Utc>>,
+    pub status: DaemonStatus,
+}
+
+impl Daemon {
+    pub fn new(
+        datapath: &Path,
+        fetcher: Arc<dyn Fetcher>,
+        hooks: Hooks,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            data: LibraryData::new(datapath),
+            fetcher,
+            hooks,
+            interval,
+            attempts: HashMap::new(),
+            status: DaemonStatus {
+                pid: std::process::id(),
+                running: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Updates until `stop` is set, checking it between feeds and while waiting
+    pub fn run(&mut self, stop: &AtomicBool) -> color_eyre::Result<()> {
+        info!("Starting daemon");
+
+        while !stop.load(Relaxed) {
+            let next = self.run_once(Utc::now(), stop);
+
+            let wake = next.min(Utc::now() + MAX_SLEEP);
+            while !stop.load(Relaxed) && Utc::now() < wake {
+                thread::sleep(StdDuration::from_secs(1));
+            }
+        }
+
+        info!("Stopping daemon");
+        self.status.running = false;
+        self.status.next_run = None;
+        self.save_status()
+    }
+
+    /// Runs the feeds due at `now` like `run_pending`, but a failed run is only logged and kept in
+    /// the status, to be tried again shortly, so the daemon keeps going
+    pub fn run_once(&mut self, now: DateTime<Utc>, stop: &AtomicBool) -> DateTime<Utc> {
+        match self.run_pending(now, stop) {
+            Ok(next) => next,
+            Err(e) => {
+                error!("Daemon run failed: {:?}", e);
+                self.status.error = Some(e.to_string());
+                self.status.next_run = Some(now + MAX_SLEEP);
+
+                if let Err(e) = self.save_status() {
+                    error!("Couldn't save the daemon status: {:?}", e);
+                }
+
+                now + MAX_SLEEP
+            }
+        }
+    }
+
+    /// Updates the feeds due at `now`, and returns when the next one is due
+    pub fn run_pending(
+        &mut self,
+        now: DateTime<Utc>,
+        stop: &AtomicBool,
+    ) -> color_eyre::Result<DateTime<Utc>> {
+        let feeds: Vec<FeedItem> = self
+            .data
+            .generate_categories_tree()?
+            .into_iter()
+            .flat_map(|category| category.feeds)
+            .collect();
+
+        let due: Vec<&FeedItem> = feeds.iter().filter(|f| self.due_at(f) <= now).collect();
+
+        if !due.is_empty() {
+            match UpdateLock::acquire(&self.data.path)? {
+                Some(_lock) => self.update(&due, now, stop),
+                None => info!("Another update is running, waiting for the next run"),
+            }
+        }
+
+        let next = feeds
+            .iter()
+            .map(|f| self.due_at(f))
+            .filter(|due| *due > now)
+            .min()
+            .unwrap_or(now + self.interval);
+
+        self.status.next_run = Some(next);
+        self.status.error = None;
+        self.save_status()?;
+
+        Ok(next)
+    }
+
+    fn update(&mut self, feeds: &[&FeedItem], now: DateTime<Utc>, stop: &AtomicBool) {
+        let mut new_entries = vec![];
+
+        self.status.last_run = Some(now);
+        self.status.feeds_updated = 0;
+        self.status.errors.clear();
+
+        for feed in feeds {
+            if stop.load(Relaxed) {
+                break;
+            }
+
+            self.attempts
+                .insert((feed.category.clone(), feed.slug.clone()), now);
+
+            match self
+                .data
+                .update_feed_entries(self.fetcher.as_ref(), &feed.category, feed, None)
+            {
+                Ok(entries) => {
+                    info!("Updated {}", feed.title);
+                    self.status.feeds_updated += 1;
+                    new_entries.extend(
+                        entries
+                            .iter()
+                            .map(|entry| EntrySummary::new(&self.data.path, feed, entry)),
+                    );
+                }
+                Err(e) => {
+                    error!("Something happened when updating {}: {:?}", feed.title, e);
+                    self.status.errors.push(FeedError {
+                        feed: feed.title.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        self.status.new_entries = new_entries.len();
+        self.hooks.run(&new_entries);
+    }
+
+    fn due_at(&self, feed: &FeedItem) -> DateTime<Utc> {
+        let interval = feed
+            .update_interval_mins
+            .and_then(|mins| Duration::try_minutes(mins.min(MAX_UPDATE_INTERVAL_MINS) as i64))
+            .unwrap_or(self.interval);
+
+        let last = self
+            .attempts
+            .get(&(feed.category.clone(), feed.slug.clone()))
+            .map_or(feed.lastupdated, |attempt| (*attempt).max(feed.lastupdated));
+
+        // A date too far to be represented is never reached
+        last.checked_add_signed(interval)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    fn save_status(&self) -> color_eyre::Result<()> {
+        fs::write(
+            self.data.path.join(DATA_DAEMON_STATUS),
+            toml::to_string(&self.status)?,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::feed::fetcher::fixture::FixtureFetcher;
+
+    fn rss(title: &str) -> String {
+        format!(
+            "<rss version=\"2.0\"><channel><title>Feed</title><link>https://example.org/</link><item><title>{title}</title><link>https://example.org/{title}</link></item></channel></rss>"
+        )
+    }
+
+    #[test]
+    fn test_updates_due_feeds() {
+        let (data, temp_dir) = LibraryData::new_for_test();
+        let fetcher = Arc::new(FixtureFetcher::default());
+        fetcher
+            .body("https://example.org/hourly", &rss("first"))
+            .body("https://example.org/daily", &rss("second"));
+
+        let long_ago = Utc::now() - Duration::days(2);
+        for (slug, interval) in [("hourly", None), ("daily", Some(24 * 60)), ("broken", None)] {
+            data.feed_create(&FeedItem {
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                feed_url: format!("https://example.org/{slug}"),
+                category: "General".to_string(),
+                lastupdated: long_ago,
+                update_interval_mins: interval,
+                ..Default::default()
+            })
+            .unwrap();
+        }
+
+        let stop = AtomicBool::new(false);
+        let mut daemon = Daemon::new(
+            temp_dir.path(),
+            fetcher.clone(),
+            Hooks::default(),
+            Duration::minutes(60),
+        );
+
+        let now = Utc::now();
+        let next = daemon.run_pending(now, &stop).unwrap();
+        assert_eq!(daemon.status.feeds_updated, 2);
+        assert_eq!(daemon.status.new_entries, 2);
+        assert_eq!(daemon.status.errors.len(), 1);
+        assert!(next > now && next <= now + Duration::minutes(61));
+
+        let status: DaemonStatus =
+            toml::from_str(&fs::read_to_string(temp_dir.path().join(DATA_DAEMON_STATUS)).unwrap())
+                .unwrap();
+        assert_eq!(status, daemon.status);
+
+        // Each feed waits for its own interval, failing ones included
+        let due: HashMap<String, DateTime<Utc>> = daemon
+            .data
+            .generate_categories_tree()
+            .unwrap()
+            .into_iter()
+            .flat_map(|category| category.feeds)
+            .map(|feed| (feed.slug.clone(), daemon.due_at(&feed)))
+            .collect();
+        assert!(due["hourly"] > now + Duration::minutes(59));
+        assert!(due["hourly"] < now + Duration::minutes(61));
+        assert_eq!(due["broken"], now + Duration::minutes(60));
+        assert!(due["daily"] > now + Duration::hours(23));
+
+        let requests = fetcher.requests().len();
+        daemon
+            .run_pending(now + Duration::minutes(30), &stop)
+            .unwrap();
+        assert_eq!(fetcher.requests().len(), requests);
+    }
+
+    #[test]
+    fn test_huge_intervals() {
+        let (data, temp_dir) = LibraryData::new_for_test();
+        let fetcher = Arc::new(FixtureFetcher::default());
+        fetcher.body("https://example.org/feed", &rss("first"));
+
+        let feed = FeedItem {
+            title: "Feed".to_string(),
+            slug: "feed".to_string(),
+            feed_url: "https://example.org/feed".to_string(),
+            category: "General".to_string(),
+            lastupdated: Utc::now() - Duration::days(2),
+            update_interval_mins: Some(u64::MAX),
+            ..Default::default()
+        };
+        data.feed_create(&feed).unwrap();
+
+        let stop = AtomicBool::new(false);
+        let mut daemon = Daemon::new(
+            temp_dir.path(),
+            fetcher.clone(),
+            Hooks::default(),
+            Duration::minutes(60),
+        );
+
+        // Read back as a year, so it isn't due yet
+        let now = Utc::now();
+        daemon.run_pending(now, &stop).unwrap();
+        assert!(fetcher.requests().is_empty());
+        assert_eq!(
+            daemon.due_at(&feed),
+            feed.lastupdated + Duration::minutes(MAX_UPDATE_INTERVAL_MINS as i64)
+        );
+
+        // And never due when the date would overflow
+        let feed = FeedItem {
+            lastupdated: DateTime::<Utc>::MAX_UTC - Duration::days(1),
+            ..feed
+        };
+        assert_eq!(daemon.due_at(&feed), DateTime::<Utc>::MAX_UTC);
+    }
+
+    #[test]
+    fn test_keeps_running_after_failed_runs() {
+        let (data, temp_dir) = LibraryData::new_for_test();
+        let fetcher = Arc::new(FixtureFetcher::default());
+        let stop = AtomicBool::new(false);
+        let mut daemon = Daemon::new(
+            temp_dir.path(),
+            fetcher,
+            Hooks::default(),
+            Duration::minutes(60),
+        );
+
+        // The library can't be read for a moment, e.g. while it's being synced
+        let categories = data.path.join(crate::core::defs::DATA_CATEGORIES_DIR);
+        fs::remove_dir_all(&categories).unwrap();
+
+        let now = Utc::now();
+        assert_eq!(daemon.run_once(now, &stop), now + MAX_SLEEP);
+        assert!(daemon.status.error.is_some());
+
+        let status: DaemonStatus =
+            toml::from_str(&fs::read_to_string(temp_dir.path().join(DATA_DAEMON_STATUS)).unwrap())
+                .unwrap();
+        assert_eq!(status.error, daemon.status.error);
+
+        fs::create_dir_all(&categories).unwrap();
+        assert_eq!(daemon.run_once(now, &stop), now + Duration::minutes(60));
+        assert_eq!(daemon.status.error, None);
+    }
+
+    #[test]
+    fn test_update_lock() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let lock = UpdateLock::acquire(temp_dir.path()).unwrap();
+        assert!(lock.is_some());
+        assert!(UpdateLock::acquire(temp_dir.path()).unwrap().is_none());
+
+        drop(lock);
+        assert!(UpdateLock::acquire(temp_dir.path()).unwrap().is_some());
+    }
+}
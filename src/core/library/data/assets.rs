@@ -0,0 +1,372 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock,
+        atomic::{AtomicU64, Ordering::Relaxed},
+    },
+};
+
+use color_eyre::eyre::eyre;
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use tracing::{error, info};
+use url::Url;
+
+use crate::core::{
+    defs,
+    feed::{
+        feedentry::Enclosure,
+        fetcher::{self, Fetcher},
+    },
+    library::feeditem::AssetOptions,
+};
+
+static MARKDOWN_IMAGE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"!\[([^\]]*)\]\(\s*<?([^)\s>]+)>?((?:\s+"[^"]*")?)\s*\)"#).unwrap()
+});
+
+/// Downloads the remote images referenced by `text` into `assets_dir` and returns the Markdown
+/// with those links rewritten to paths relative to `entry_dir`. Images that can't be downloaded
+/// keep their original URL.
+pub fn localize_images(
+    fetcher: &dyn Fetcher,
+    text: &str,
+    entry_dir: &Path,
+    options: &AssetOptions,
+) -> color_eyre::Result<String> {
+    let assets_dir = entry_dir.join(defs::DATA_ASSETS_DIR);
+
+    Ok(rewrite_images(text, |url| {
+        match download_asset(fetcher, url, &assets_dir, options.max_size_kb * 1024) {
+            Ok(path) => relative_asset_path(&path, entry_dir),
+            Err(e) => {
+                error!("Couldn't download asset {url}: {e}");
+                None
+            }
+        }
+    }))
+}
+
+/// Downloads every enclosure that isn't stored yet into `assets_dir`, recording the path relative
+/// to `entry_dir` in each of them.
+pub fn localize_enclosures(
+    fetcher: &dyn Fetcher,
+    enclosures: &mut [Enclosure],
+    entry_dir: &Path,
+    options: &AssetOptions,
+) -> color_eyre::Result<()> {
+    let assets_dir = entry_dir.join(defs::DATA_ASSETS_DIR);
+    let max_bytes = options.max_enclosure_size_mb * 1024 * 1024;
+
+    for enclosure in enclosures.iter_mut().filter(|e| e.local.is_none()) {
+        let progress = AtomicU64::new(0);
+        match download_enclosure(fetcher, &enclosure.url, &assets_dir, max_bytes, &progress) {
+            Ok(path) => enclosure.local = relative_asset_path(&path, entry_dir),
+            Err(e) => error!("Couldn't download enclosure {}: {e}", enclosure.url),
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites every remote Markdown image link using `resolve`. Each distinct URL is resolved only
+/// once; when `resolve` returns `None` the link is left untouched.
+fn rewrite_images<F>(text: &str, mut resolve: F) -> String
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut resolved: HashMap<String, Option<String>> = HashMap::new();
+
+    MARKDOWN_IMAGE
+        .replace_all(text, |caps: &Captures| {
+            let url = &caps[2];
+
+            if !is_remote(url) {
+                return caps[0].to_string();
+            }
+
+            let local = resolved
+                .entry(url.to_string())
+                .or_insert_with(|| resolve(url));
+
+            match local {
+                Some(path) => format!("![{}]({}{})", &caps[1], path, &caps[3]),
+                None => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Downloads `url` into `assets_dir`, naming the file after the hash of its contents so the same
+/// image is only stored once, no matter how many entries reference it.
+pub fn download_asset(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    assets_dir: &Path,
+    max_bytes: u64,
+) -> color_eyre::Result<PathBuf> {
+    let (data, extension) = fetch_asset(fetcher, url, max_bytes)?;
+    store_asset(&data, &extension, assets_dir)
+}
+
+/// Downloads `url` into memory, returning its contents and the extension for its file
+pub fn fetch_asset(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    max_bytes: u64,
+) -> color_eyre::Result<(Vec<u8>, String)> {
+    let response = fetcher::fetch(fetcher, url, None)?.response;
+
+    if !response.is_success() {
+        return Err(eyre!(
+            "Request to \"{}\" returned status code {}",
+            url,
+            response.status
+        ));
+    }
+
+    if let Some(length) = response.content_length()
+        && length > max_bytes
+    {
+        return Err(eyre!(
+            "Asset is {length} bytes, over the {max_bytes} bytes limit"
+        ));
+    }
+
+    let content_type = response.header("content-type").map(String::from);
+
+    let mut data = Vec::new();
+    response.body.take(max_bytes + 1).read_to_end(&mut data)?;
+
+    if data.len() as u64 > max_bytes {
+        return Err(eyre!("Asset is over the {max_bytes} bytes limit"));
+    }
+
+    Ok((data, asset_extension(url, content_type.as_deref())))
+}
+
+/// Streams a potentially large media file at `url` into `assets_dir`, reporting the amount of
+/// bytes downloaded so far through `progress`. Like images, it's named after the hash of its
+/// contents.
+pub fn download_enclosure(
+    fetcher: &dyn Fetcher,
+    url: &str,
+    assets_dir: &Path,
+    max_bytes: u64,
+    progress: &AtomicU64,
+) -> color_eyre::Result<PathBuf> {
+    let mut response = fetcher::fetch(fetcher, url, None)?.response;
+
+    if !response.is_success() {
+        return Err(eyre!(
+            "Request to \"{}\" returned status code {}",
+            url,
+            response.status
+        ));
+    }
+
+    if let Some(length) = response.content_length()
+        && length > max_bytes
+    {
+        return Err(eyre!(
+            "Enclosure is {length} bytes, over the {max_bytes} bytes limit"
+        ));
+    }
+
+    let content_type = response.header("content-type").map(String::from);
+
+    fs::create_dir_all(assets_dir)?;
+
+    let url_hash = Sha256::digest(url.as_bytes());
+    let partial = assets_dir.join(format!(".{}.part", hex_prefix(&url_hash)));
+
+    let result = (|| {
+        let mut file = File::create(&partial)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut total = 0u64;
+
+        loop {
+            let read = response.body.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+
+            total += read as u64;
+            if total > max_bytes {
+                return Err(eyre!("Enclosure is over the {max_bytes} bytes limit"));
+            }
+
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read])?;
+            progress.store(total, Relaxed);
+        }
+
+        let extension = asset_extension(url, content_type.as_deref());
+        let path = assets_dir.join(asset_filename(&hasher.finalize(), &extension));
+
+        if path.exists() {
+            fs::remove_file(&partial)?;
+        } else {
+            fs::rename(&partial, &path)?;
+        }
+
+        Ok(path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+
+    result
+}
+
+/// Writes `data` to `assets_dir` unless an asset with the same contents is already there.
+fn store_asset(data: &[u8], extension: &str, assets_dir: &Path) -> color_eyre::Result<PathBuf> {
+    let path = assets_dir.join(asset_name(data, extension));
+    if path.exists() {
+        return Ok(path);
+    }
+
+    fs::create_dir_all(assets_dir)?;
+    fs::write(&path, data).map_err(|e| eyre!("Failed to write asset {}: {}", path.display(), e))?;
+
+    info!("Stored asset {:?}", path);
+    Ok(path)
+}
+
+/// Name of the file of an asset, after the hash of its contents
+pub fn asset_name(data: &[u8], extension: &str) -> String {
+    asset_filename(&Sha256::digest(data), extension)
+}
+
+fn asset_filename(hash: &[u8], extension: &str) -> String {
+    let name = hex_prefix(hash);
+
+    if extension.is_empty() {
+        name
+    } else {
+        format!("{name}.{extension}")
+    }
+}
+
+fn hex_prefix(hash: &[u8]) -> String {
+    hash.iter().take(16).map(|b| format!("{b:02x}")).collect()
+}
+
+fn asset_extension(url: &str, content_type: Option<&str>) -> String {
+    let from_url = Url::parse(url).ok().and_then(|u| {
+        Path::new(u.path())
+            .extension()
+            .and_then(|e| e.to_str())
+            .filter(|e| e.len() <= 5 && e.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(|e| e.to_lowercase())
+    });
+
+    if let Some(extension) = from_url {
+        return extension;
+    }
+
+    let mime = content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_lowercase())
+        .unwrap_or_default();
+
+    match mime.as_str() {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        "audio/mpeg" => "mp3",
+        "audio/mp4" | "audio/x-m4a" => "m4a",
+        "audio/ogg" => "ogg",
+        "video/mp4" => "mp4",
+        "video/webm" => "webm",
+        _ => "",
+    }
+    .to_string()
+}
+
+pub fn relative_asset_path(path: &Path, entry_dir: &Path) -> Option<String> {
+    path.strip_prefix(entry_dir)
+        .ok()
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+}
+
+fn is_remote(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn rewrites_remote_images_only() {
+        let text = "![a](https://example.com/a.png) ![b](assets/b.png) [link](https://example.com)";
+
+        let result = rewrite_images(text, |_| Some("assets/local.png".to_string()));
+
+        assert_eq!(
+            result,
+            "![a](assets/local.png) ![b](assets/b.png) [link](https://example.com)"
+        );
+    }
+
+    #[test]
+    fn keeps_title_and_resolves_each_url_once() {
+        let text =
+            "![a](https://example.com/a.png \"A title\")\n![again](https://example.com/a.png)";
+        let mut calls = 0;
+
+        let result = rewrite_images(text, |_| {
+            calls += 1;
+            Some("assets/a.png".to_string())
+        });
+
+        assert_eq!(calls, 1);
+        assert_eq!(
+            result,
+            "![a](assets/a.png \"A title\")\n![again](assets/a.png)"
+        );
+    }
+
+    #[test]
+    fn keeps_original_link_when_download_fails() {
+        let text = "![a](https://example.com/a.png)";
+        assert_eq!(rewrite_images(text, |_| None), text);
+    }
+
+    #[test]
+    fn stores_identical_assets_once() {
+        let temp = TempDir::new().unwrap();
+
+        let first = store_asset(b"image data", "png", temp.path()).unwrap();
+        let second = store_asset(b"image data", "png", temp.path()).unwrap();
+        let other = store_asset(b"other data", "png", temp.path()).unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn picks_extension_from_url_then_content_type() {
+        assert_eq!(
+            asset_extension("https://example.com/a.JPG?x=1", None),
+            "jpg"
+        );
+        assert_eq!(
+            asset_extension("https://example.com/image", Some("image/webp; q=1")),
+            "webp"
+        );
+        assert_eq!(asset_extension("https://example.com/image", None), "");
+    }
+}
@@ -11,10 +11,13 @@ use tracing::{error, info};
 
 use crate::core::feed::feedentry::FeedEntry;
 use crate::core::feed::feedparser;
+use crate::core::feed::fetcher::Fetcher;
+use crate::core::library::data::assets;
+use crate::core::library::data::rules::Rules;
 use crate::core::library::feedcategory::FeedCategory;
 use crate::{
     core::defs::{self, DATA_CATEGORIES_DIR, DATA_FEED, DATA_READ_LATER},
-    core::library::feeditem::FeedItem,
+    core::library::feeditem::{FeedItem, MAX_UPDATE_INTERVAL_MINS},
 };
 use serde::{Deserialize, Serialize};
 
@@ -85,6 +88,37 @@ impl LibraryData {
         }
     }
 
+    /// Number of entries stored for a feed
+    pub fn count_feed_entries(&self, category: &str, slug: &str) -> color_eyre::Result<usize> {
+        let feed_dir = self
+            .path
+            .join(DATA_CATEGORIES_DIR)
+            .join(category)
+            .join(slug);
+
+        let mut count = 0;
+        for entry in fs::read_dir(feed_dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Read Later references to entries of a feed, relative to the categories directory
+    pub fn feed_read_later(&self, category: &str, slug: &str) -> color_eyre::Result<Vec<String>> {
+        let feed_dir = Path::new(category).join(slug);
+
+        Ok(self
+            .load_read_later()?
+            .read_later
+            .into_iter()
+            .filter(|rel| Path::new(rel).starts_with(&feed_dir))
+            .collect())
+    }
+
     pub fn feed_create(&self, feed: &FeedItem) -> color_eyre::Result<()> {
         let feedir = self
             .path
@@ -150,6 +184,9 @@ impl LibraryData {
                     };
 
                     feed.category = category_name.to_string();
+                    feed.update_interval_mins = feed
+                        .update_interval_mins
+                        .map(|mins| mins.min(MAX_UPDATE_INTERVAL_MINS));
                     feeds.push(feed);
                 }
             }
@@ -158,48 +195,95 @@ impl LibraryData {
         Ok(feeds)
     }
 
+    /// Gets the feed's new entries and stores them, returning those stored
     pub fn update_feed_entries(
         &self,
+        fetcher: &dyn Fetcher,
         category: &str,
         feed: &FeedItem,
         feedxml: Option<String>,
-    ) -> color_eyre::Result<()> {
+    ) -> color_eyre::Result<Vec<FeedEntry>> {
         // TODO: hard coding 5 minutes for now
         if Utc::now().signed_duration_since(feed.lastupdated) < Duration::minutes(5) {
-            return Ok(());
+            return Ok(vec![]);
         }
 
-        let mut feedentries = if let Some(txt) = feedxml {
-            feedparser::get_feed_entries_doc(&txt, &feed.author)
-        } else {
-            feedparser::get_feed_entries(feed)
-        }?;
+        let mut feed = feed.clone();
+
+        let txt = match feedxml {
+            Some(txt) => txt,
+            None => {
+                let doc = feedparser::fetch_feed(fetcher, &feed)?;
+                if doc.moved {
+                    info!("{} moved to {}", feed.feed_url, doc.url);
+                    feed.feed_url = doc.url;
+                }
+                doc.text
+            }
+        };
+
+        // Feeds added before the format was recorded get it on their next update
+        if feed.format.is_none() {
+            feed.format = feedparser::get_feed_format(&txt).map(String::from);
+        }
+
+        let mut feedentries = feedparser::get_feed_entries_doc(&txt, &feed.author, &feed.url)?;
 
         feedentries.iter_mut().for_each(|e| {
-            let entrypath = self
-                .path
-                .join(defs::DATA_CATEGORIES_DIR)
-                .join(category)
-                .join(&feed.slug);
-
-            let item_slug = {
-                let base_path = entrypath.to_string_lossy();
-                let max_slug_len = 250usize.saturating_sub(base_path.len() + 1);
-                let slug = slugify(&e.title);
-                let slug_cut = &slug[..slug.len().min(max_slug_len)];
-                slug_cut.to_string()
-            };
-
-            e.filepath = entrypath.join(format!("{item_slug}.md"));
+            e.filepath = self.entry_path(category, &feed.slug, &e.title);
         });
 
-        self.update_entries(feed, feedentries)
+        self.update_entries(fetcher, &feed, feedentries)
     }
 
-    fn update_entries(&self, feed: &FeedItem, entries: Vec<FeedEntry>) -> color_eyre::Result<()> {
+    /// Where the entry titled `title` of a feed is stored
+    pub fn entry_path(&self, category: &str, feed_slug: &str, title: &str) -> PathBuf {
+        let entrypath = self
+            .path
+            .join(defs::DATA_CATEGORIES_DIR)
+            .join(category)
+            .join(feed_slug);
+
+        let item_slug = {
+            let base_path = entrypath.to_string_lossy();
+            let max_slug_len = 250usize.saturating_sub(base_path.len() + 1);
+            let slug = slugify(title);
+            let slug_cut = &slug[..slug.len().min(max_slug_len)];
+            slug_cut.to_string()
+        };
+
+        entrypath.join(format!("{item_slug}.md"))
+    }
+
+    fn update_entries(
+        &self,
+        fetcher: &dyn Fetcher,
+        feed: &FeedItem,
+        entries: Vec<FeedEntry>,
+    ) -> color_eyre::Result<Vec<FeedEntry>> {
+        let rules = Rules::load(&self.path).unwrap_or_else(|e| {
+            error!("Couldn't load the rules, ignoring them: {:?}", e);
+            Rules::default()
+        });
+        let mut read_later = vec![];
+        let mut stored = vec![];
+
         for entry in entries.iter().as_ref() {
             // if it exists, it means the entry has been setup already
             if !entry.filepath.exists() {
+                let mut entryclone = (*entry).clone();
+                entryclone.text = String::new();
+
+                // Dropped entries aren't stored, so the rules see them again on every update
+                let outcome = rules.apply(feed, &mut entryclone);
+                if outcome.drop {
+                    continue;
+                }
+
+                if outcome.read_later {
+                    read_later.push(entry.filepath.clone());
+                }
+
                 let mut file = match OpenOptions::new()
                     .write(true)
                     .create_new(true)
@@ -217,24 +301,61 @@ impl LibraryData {
                     }
                 };
 
-                let mut entryclone = (*entry).clone();
-                entryclone.text = String::new();
+                let mut text = entry.text.clone();
+
+                if feed.full_content {
+                    match feedparser::get_article_content(
+                        fetcher,
+                        &entry.url,
+                        feed.http_for(&entry.url),
+                    ) {
+                        Ok(content) => text = content,
+                        Err(e) => error!("Couldn't fetch full content for '{}': {}", entry.url, e),
+                    }
+                }
+
+                if let Some(options) = feed.assets.as_ref() {
+                    let entry_dir = entry.filepath.parent().unwrap_or(&self.path);
+
+                    if options.images {
+                        match assets::localize_images(fetcher, &text, entry_dir, options) {
+                            Ok(localized) => text = localized,
+                            Err(e) => error!("Couldn't store assets for '{}': {}", entry.title, e),
+                        }
+                    }
+
+                    if options.enclosures
+                        && let Err(e) = assets::localize_enclosures(
+                            fetcher,
+                            &mut entryclone.enclosures,
+                            entry_dir,
+                            options,
+                        )
+                    {
+                        error!("Couldn't store enclosures for '{}': {}", entry.title, e);
+                    }
+                }
 
                 let entrytext = format!(
                     "+++\n{}+++\n\n{}",
                     toml::to_string(&entryclone).unwrap_or(String::new()),
-                    &entry.text
+                    &text
                 );
 
                 file.write_all(&entrytext.into_bytes())?;
+                stored.push(entryclone);
             }
         }
 
+        if !read_later.is_empty() {
+            self.append_read_later(&read_later)?;
+        }
+
         let mut feed = feed.clone();
         feed.lastupdated = Utc::now();
         self.feed_create(&feed)?;
 
-        Ok(())
+        Ok(stored)
     }
 
     pub fn save_feed_entry(&self, entry: &FeedEntry) -> color_eyre::Result<()> {
@@ -297,6 +418,11 @@ impl LibraryData {
         Ok(entries)
     }
 
+    pub fn load_feed_entry(&self, path: &Path) -> color_eyre::Result<FeedEntry> {
+        let contents = std::fs::read_to_string(path)?;
+        self.parse_feed_entry(&contents, path)
+    }
+
     // TODO: this needs to be cached and only updated every now and then, since it's beeing pretty
     // intensive now
     pub fn get_unread_feed(&self, category: &str, feed_slug: &str) -> color_eyre::Result<u16> {
@@ -342,14 +468,22 @@ impl LibraryData {
         Ok(entry)
     }
 
-    pub fn set_entry_seen(&self, entry: &FeedEntry) {
+    pub fn set_entry_seen(&self, entry: &FeedEntry) -> color_eyre::Result<()> {
         if !entry.seen {
             let mut entry = entry.clone();
             entry.seen = true;
-            if let Err(e) = self.save_feed_entry(&entry) {
-                error!("Couldn't set entry seen: {:?}", e);
-            }
+            self.save_feed_entry(&entry)?;
         }
+        Ok(())
+    }
+
+    pub fn set_entry_unseen(&self, entry: &FeedEntry) -> color_eyre::Result<()> {
+        if entry.seen {
+            let mut entry = entry.clone();
+            entry.seen = false;
+            self.save_feed_entry(&entry)?;
+        }
+        Ok(())
     }
 
     pub fn toggle_entry_seen(&self, entry: &FeedEntry) {
@@ -381,6 +515,21 @@ impl LibraryData {
         Ok(())
     }
 
+    /// Adds entries to Read Later without going through the loaded list, for the updater
+    fn append_read_later(&self, paths: &[PathBuf]) -> color_eyre::Result<()> {
+        let mut read_later_list = self.load_read_later()?;
+
+        for path in paths {
+            let rel_path = self.absolute_path_to_relative_path(path.to_str().unwrap_or_default());
+
+            if !rel_path.is_empty() && !read_later_list.read_later.contains(&rel_path) {
+                read_later_list.read_later.push(rel_path);
+            }
+        }
+
+        self.save_read_later(&read_later_list)
+    }
+
     pub fn remove_from_read_later(&mut self, file_path: &str) -> color_eyre::Result<()> {
         self.ensure_read_later()?;
 
@@ -408,6 +557,18 @@ impl LibraryData {
         Ok(feed_entries.into_iter().rev().collect())
     }
 
+    /// Files of the entries in Read Later
+    pub fn read_later_paths(&self) -> color_eyre::Result<Vec<PathBuf>> {
+        let categories_dir = self.path.join(DATA_CATEGORIES_DIR);
+
+        Ok(self
+            .load_read_later()?
+            .read_later
+            .iter()
+            .map(|rel| categories_dir.join(rel))
+            .collect())
+    }
+
     pub fn is_in_read_later(&mut self, file_path: &str) -> color_eyre::Result<bool> {
         self.ensure_read_later()?;
 
@@ -423,7 +584,7 @@ impl LibraryData {
         Ok(())
     }
 
-    fn load_read_later(&mut self) -> color_eyre::Result<ReadLaterData> {
+    fn load_read_later(&self) -> color_eyre::Result<ReadLaterData> {
         let read_later_path = self.path.join(DATA_READ_LATER);
         if !read_later_path.exists() {
             return Ok(ReadLaterData::default());
@@ -558,4 +719,22 @@ This is the content."#;
         let result = ld.parse_feed_entry(content, path);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_set_entry_seen_reports_failures() {
+        let (ld, temp) = LibraryData::new_for_test();
+        let mut entry = FeedEntry {
+            title: "Entry".to_string(),
+            filepath: temp.path().join("entry.md"),
+            ..Default::default()
+        };
+
+        ld.set_entry_seen(&entry).unwrap();
+        assert!(ld.load_feed_entry(&entry.filepath).unwrap().seen);
+
+        entry.filepath = temp.path().join("missing").join("entry.md");
+        assert!(ld.set_entry_seen(&entry).is_err());
+        entry.seen = true;
+        assert!(ld.set_entry_unseen(&entry).is_err());
+    }
 }
@@ -1,3 +1,6 @@
+pub mod assets;
 pub mod config;
 pub mod librarydata;
+pub mod newsboat;
 pub mod opml;
+pub mod rules;
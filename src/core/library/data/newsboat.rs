@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use html2md_bulletty::parse_html;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::core::{feed::feedentry::FeedEntry, library::data::opml::OpmlFeed};
+
+/// An article from newsboat's `cache.db`
+#[derive(Debug, Clone, Default)]
+pub struct CachedEntry {
+    pub feed_url: String,
+    pub url: String,
+    pub title: String,
+    pub author: String,
+    pub date: DateTime<Utc>,
+    /// HTML content, as downloaded by newsboat
+    pub content: String,
+    pub unread: bool,
+    /// newsboat has several flags, any of them marks the article as starred
+    pub flagged: bool,
+}
+
+impl CachedEntry {
+    /// The entry to store for this article, when the feed doesn't have it anymore
+    pub fn to_entry(&self, filepath: PathBuf) -> FeedEntry {
+        let text = parse_html(&self.content);
+        let description = text.replace('\n', " ").chars().take(280).collect();
+
+        FeedEntry {
+            title: self.title.clone(),
+            description,
+            date: self.date,
+            url: self.url.clone(),
+            author: self.author.clone(),
+            text,
+            lastupdated: Utc::now(),
+            seen: !self.unread,
+            starred: self.flagged,
+            filepath,
+            ..Default::default()
+        }
+    }
+}
+
+/// newsboat's `urls` file, in the legacy location or the XDG one
+pub fn default_urls_path() -> Option<PathBuf> {
+    [
+        dirs::home_dir().map(|home| home.join(".newsboat/urls")),
+        dirs::config_dir().map(|config| config.join("newsboat/urls")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|path| path.exists())
+}
+
+/// Reads the feeds of newsboat's `urls` file. The first tag of a feed is used as its category,
+/// and a `~` tag as its title. Query feeds are skipped, and filtered feeds are read unfiltered.
+pub fn get_urls_feeds(path: &Path) -> Result<Vec<OpmlFeed>> {
+    Ok(parse_urls(&std::fs::read_to_string(path)?))
+}
+
+fn parse_urls(text: &str) -> Vec<OpmlFeed> {
+    let mut feeds = vec![];
+
+    for line in text.lines() {
+        let tokens = split_line(line);
+        let Some((url, tags)) = tokens.split_first() else {
+            continue;
+        };
+
+        if url.starts_with("query:") {
+            continue;
+        }
+
+        let url = url
+            .strip_prefix("filter:")
+            .and_then(|rest| rest.split_once(':'))
+            .map_or(url.as_str(), |(_, url)| url);
+
+        let title = tags
+            .iter()
+            .find_map(|tag| tag.strip_prefix('~'))
+            .map(String::from);
+
+        feeds.push(OpmlFeed {
+            url: url.to_string(),
+            category: tags
+                .iter()
+                .find(|tag| !tag.starts_with('~') && !tag.starts_with('!'))
+                .cloned(),
+            title,
+            description: None,
+            html_url: None,
+            settings: Default::default(),
+        });
+    }
+
+    feeds
+}
+
+/// Splits a line of the `urls` file on whitespace, keeping quoted parts together
+fn split_line(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            '\\' if quoted => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            '#' if !quoted && !in_token => break,
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if in_token {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+/// Reads the articles newsboat keeps, leaving out those deleted in it
+pub fn read_cache(path: &Path) -> Result<Vec<CachedEntry>> {
+    let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let mut statement = connection.prepare(
+        "SELECT feedurl, url, title, author, pubDate, content, unread, flags
+         FROM rss_item WHERE deleted = 0",
+    )?;
+
+    let entries = statement
+        .query_map([], |row| {
+            Ok(CachedEntry {
+                feed_url: row.get(0)?,
+                url: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                title: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                author: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                date: DateTime::from_timestamp(row.get(4)?, 0).unwrap_or_default(),
+                content: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                unread: row.get::<_, i64>(6)? != 0,
+                flagged: row
+                    .get::<_, Option<String>>(7)?
+                    .is_some_and(|flags| !flags.is_empty()),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_urls() {
+        let feeds = parse_urls(
+            r#"
+# My feeds
+https://example.org/feed.xml tech "~Example Blog" news
+https://example.org/other "Long Reads" !hidden
+"query:Unread:unread = \"yes\""
+filter:~/bin/clean.sh:https://example.org/filtered
+exec:~/bin/feed.sh "~A \"quoted\" title"
+https://example.org/plain # a comment
+"#,
+        );
+
+        let summary: Vec<(&str, Option<&str>, Option<&str>)> = feeds
+            .iter()
+            .map(|f| (f.url.as_str(), f.category.as_deref(), f.title.as_deref()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "https://example.org/feed.xml",
+                    Some("tech"),
+                    Some("Example Blog")
+                ),
+                ("https://example.org/other", Some("Long Reads"), None),
+                ("https://example.org/filtered", None, None),
+                ("exec:~/bin/feed.sh", None, Some("A \"quoted\" title")),
+                ("https://example.org/plain", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("cache.db");
+
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                r#"
+CREATE TABLE rss_item (id INTEGER PRIMARY KEY AUTOINCREMENT, guid VARCHAR(64) NOT NULL,
+    title VARCHAR(1024) NOT NULL, author VARCHAR(1024) NOT NULL, url VARCHAR(1024) NOT NULL,
+    feedurl VARCHAR(1024) NOT NULL, pubDate INTEGER NOT NULL, content VARCHAR(65535) NOT NULL,
+    unread INTEGER(1) NOT NULL, enclosure_url VARCHAR(1024), enclosure_type VARCHAR(1024),
+    enqueued INTEGER(1) NOT NULL DEFAULT 0, flags VARCHAR(52), deleted INTEGER(1) NOT NULL DEFAULT 0,
+    base VARCHAR(128) NOT NULL DEFAULT "");
+INSERT INTO rss_item (guid, title, author, url, feedurl, pubDate, content, unread, flags, deleted)
+VALUES
+    ('1', 'Read one', 'Ann', 'https://example.org/1', 'https://example.org/feed', 1700000000, '<p>Hi</p>', 0, NULL, 0),
+    ('2', 'Flagged', '', 'https://example.org/2', 'https://example.org/feed', 1700000100, '<p>Keep <b>me</b></p>', 1, 's', 0),
+    ('3', 'Deleted', '', 'https://example.org/3', 'https://example.org/feed', 1700000200, '', 0, NULL, 1);
+"#,
+            )
+            .unwrap();
+        drop(connection);
+
+        let entries = read_cache(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let read = entries.iter().find(|e| e.title == "Read one").unwrap();
+        assert!(!read.unread && !read.flagged);
+        assert_eq!(read.author, "Ann");
+        assert_eq!(read.date.timestamp(), 1700000000);
+
+        let flagged = entries.iter().find(|e| e.title == "Flagged").unwrap();
+        assert!(flagged.unread && flagged.flagged);
+
+        let entry = flagged.to_entry(temp_dir.path().join("flagged.md"));
+        assert!(entry.starred && !entry.seen);
+        assert_eq!(entry.text, "Keep **me**");
+    }
+}
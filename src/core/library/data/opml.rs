@@ -1,11 +1,54 @@
+use std::{borrow::Cow, io};
+
+use chrono::Utc;
 use color_eyre::{Result, eyre};
+use quick_xml::{
+    Writer,
+    escape::escape,
+    events::{BytesDecl, BytesText, Event, attributes::Attribute},
+    name::QName,
+};
 use roxmltree::Node;
+use slug::slugify;
+
+use crate::core::library::{
+    feedcategory::FeedCategory,
+    feeditem::{AssetOptions, FeedItem},
+};
 
-use crate::core::library::feedcategory::FeedCategory;
+/// Namespace of the attributes holding bulletty's own feed settings
+pub const BULLETTY_NS: &str = "https://github.com/CrociDB/bulletty";
 
 pub struct OpmlFeed {
     pub url: String,
     pub category: Option<String>,
+    /// Used instead of the feed's own title, as it may have been renamed by the user
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub html_url: Option<String>,
+    pub settings: OpmlSettings,
+}
+
+/// Feed settings carried by the bulletty namespaced attributes
+#[derive(Default, Debug, PartialEq)]
+pub struct OpmlSettings {
+    pub full_content: bool,
+    pub update_interval_mins: Option<u64>,
+    pub assets: Option<AssetOptions>,
+    /// Name of the feed's directory, which stays the same when the feed is renamed
+    pub slug: Option<String>,
+}
+
+impl OpmlSettings {
+    pub fn apply(&self, feed: &mut FeedItem) {
+        feed.full_content = self.full_content;
+        feed.update_interval_mins = self.update_interval_mins;
+        feed.assets = self.assets.clone();
+        // Slugified again, since the file may come from anyone and it becomes a path
+        if let Some(slug) = self.slug.as_deref().map(slugify).filter(|s| !s.is_empty()) {
+            feed.slug = slug;
+        }
+    }
 }
 
 pub fn get_opml_feeds(filename: &str) -> Result<Vec<OpmlFeed>> {
@@ -51,48 +94,265 @@ pub fn get_opml_feeds(filename: &str) -> Result<Vec<OpmlFeed>> {
 
 fn get_opml_feed(node: &Node, category: Option<String>) -> Result<OpmlFeed> {
     if let Some(xml_url) = node.attribute("xmlUrl") {
+        let non_empty = |value: Option<&str>| {
+            value
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| value.trim().to_string())
+        };
+
         Ok(OpmlFeed {
             url: xml_url.to_string(),
             category,
+            title: non_empty(node.attribute("title").or_else(|| node.attribute("text"))),
+            description: non_empty(node.attribute("description")),
+            html_url: non_empty(node.attribute("htmlUrl")),
+            settings: get_opml_settings(node),
         })
     } else {
         Err(eyre::eyre!("No xml attribute found in element"))
     }
 }
 
-pub fn save_opml(categories: &[FeedCategory], filename: &str) -> Result<()> {
-    let mut text_categories = String::new();
-    for category in categories.iter() {
-        let mut text_feeds = String::new();
-        for feed in category.feeds.iter() {
-            let title = html_escape::encode_text(&feed.title);
-            let description = html_escape::encode_text(&feed.description);
+fn get_opml_settings(node: &Node) -> OpmlSettings {
+    let attribute = |name: &str| node.attribute((BULLETTY_NS, name));
+    let flag = |name: &str| attribute(name).is_some_and(|v| v == "true");
+    let number = |name: &str| attribute(name).and_then(|v| v.parse::<u64>().ok());
+
+    let assets = (attribute("assetImages").is_some() || attribute("assetEnclosures").is_some())
+        .then(|| {
+            let defaults = AssetOptions::default();
+            AssetOptions {
+                images: flag("assetImages"),
+                max_size_kb: number("assetMaxSizeKb").unwrap_or(defaults.max_size_kb),
+                enclosures: flag("assetEnclosures"),
+                max_enclosure_size_mb: number("assetMaxEnclosureSizeMb")
+                    .unwrap_or(defaults.max_enclosure_size_mb),
+            }
+        });
+
+    OpmlSettings {
+        full_content: flag("fullContent"),
+        update_interval_mins: number("updateIntervalMins"),
+        assets,
+        slug: attribute("slug").map(String::from),
+    }
+}
+
+/// Writes an OPML 2.0 file with every feed. With `with_settings`, bulletty's own feed settings
+/// are written as attributes in its namespace, which other readers ignore. Request settings
+/// aren't, since they may hold credentials.
+pub fn save_opml(categories: &[FeedCategory], filename: &str, with_settings: bool) -> Result<()> {
+    std::fs::write(filename, opml_string(categories, with_settings)?)?;
+
+    Ok(())
+}
+
+fn opml_string(categories: &[FeedCategory], with_settings: bool) -> Result<String> {
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 4);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let mut opml = writer
+        .create_element("opml")
+        .with_attribute(("version", "2.0"));
+    if with_settings {
+        opml = opml.with_attribute(("xmlns:bulletty", BULLETTY_NS));
+    }
+
+    opml.write_inner_content(|writer| {
+        writer
+            .create_element("head")
+            .write_inner_content(|writer| {
+                writer
+                    .create_element("title")
+                    .write_text_content(BytesText::new("Generated from bulletty"))?;
+                writer
+                    .create_element("dateCreated")
+                    .write_text_content(BytesText::new(&Utc::now().to_rfc2822()))?;
+                writer
+                    .create_element("docs")
+                    .write_text_content(BytesText::new("https://opml.org/spec2.opml"))?;
+                Ok(())
+            })?;
+
+        writer
+            .create_element("body")
+            .write_inner_content(|writer| {
+                for category in categories.iter() {
+                    writer
+                        .create_element("outline")
+                        .with_attributes([
+                            ("text", category.title.as_str()),
+                            ("title", category.title.as_str()),
+                        ])
+                        .write_inner_content(|writer| {
+                            for feed in category.feeds.iter() {
+                                write_feed(writer, feed, with_settings)?;
+                            }
+                            Ok(())
+                        })?;
+                }
+                Ok(())
+            })?;
+
+        Ok(())
+    })?;
+
+    let mut opml = String::from_utf8(writer.into_inner())?;
+    opml.push('\n');
+    Ok(opml)
+}
+
+fn write_feed(
+    writer: &mut Writer<Vec<u8>>,
+    feed: &FeedItem,
+    with_settings: bool,
+) -> io::Result<()> {
+    let mut attributes = vec![
+        (
+            "type",
+            feed.format.clone().unwrap_or_else(|| "rss".to_string()),
+        ),
+        ("text", feed.title.clone()),
+        ("title", feed.title.clone()),
+        ("xmlUrl", feed.feed_url.clone()),
+    ];
 
-            text_feeds.push_str(&format!("\n            <outline text={:?} title={:?} description={:?} xmlUrl={:?} type=\"rss\" />", title, title, description, feed.feed_url));
+    if !feed.url.is_empty() {
+        attributes.push(("htmlUrl", feed.url.clone()));
+    }
+
+    if !feed.description.is_empty() {
+        attributes.push(("description", feed.description.clone()));
+    }
+
+    if with_settings {
+        if !feed.slug.is_empty() {
+            attributes.push(("bulletty:slug", feed.slug.clone()));
         }
 
-        let title = html_escape::encode_text(&category.title);
+        if feed.full_content {
+            attributes.push(("bulletty:fullContent", "true".to_string()));
+        }
 
-        text_categories.push_str(&format!(
-            "\n        <outline text={:?} title={:?}>{}\n        </outline>",
-            title, title, text_feeds
-        ));
+        if let Some(interval) = feed.update_interval_mins {
+            attributes.push(("bulletty:updateIntervalMins", interval.to_string()));
+        }
+
+        if let Some(assets) = &feed.assets {
+            attributes.extend([
+                ("bulletty:assetImages", assets.images.to_string()),
+                ("bulletty:assetMaxSizeKb", assets.max_size_kb.to_string()),
+                ("bulletty:assetEnclosures", assets.enclosures.to_string()),
+                (
+                    "bulletty:assetMaxEnclosureSizeMb",
+                    assets.max_enclosure_size_mb.to_string(),
+                ),
+            ]);
+        }
     }
 
-    let opml = format!(
-        r#"<?xml version='1.0' encoding='UTF-8' ?>
-<opml version="1.0">
-    <head>
-        <title>Generated from bulletty</title>
-        <url>https://github.com/CrociDB/bulletty</url>
-    </head>
-    <body>{text_categories}
-    </body>
-</opml>
-"#
-    );
-
-    std::fs::write(filename, opml)?;
+    writer
+        .create_element("outline")
+        .with_attributes(attributes.iter().map(|(key, value)| attribute(key, value)))
+        .write_empty()?;
 
     Ok(())
 }
+
+/// An attribute with whitespace kept as character references, since XML parsers turn line breaks
+/// and tabs in attribute values into spaces
+fn attribute<'a>(key: &'a str, value: &str) -> Attribute<'a> {
+    let value = escape(value)
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+        .replace('\t', "&#9;");
+
+    Attribute {
+        key: QName(key.as_bytes()),
+        value: Cow::Owned(value.into_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn category(title: &str, feeds: Vec<FeedItem>) -> FeedCategory {
+        FeedCategory {
+            title: title.to_string(),
+            feeds,
+        }
+    }
+
+    #[test]
+    fn test_opml_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("feeds.opml");
+        let path = path.to_str().unwrap();
+
+        let tricky = FeedItem {
+            title: "Tom & Jerry's \"<Feed>\" ✨".to_string(),
+            description: "Cats\nand mice".to_string(),
+            url: "https://example.org/?a=1&b=2".to_string(),
+            feed_url: "https://example.org/atom?a=1&b=2".to_string(),
+            format: Some("atom".to_string()),
+            full_content: true,
+            update_interval_mins: Some(30),
+            ..Default::default()
+        };
+        let plain = FeedItem {
+            title: "Plain".to_string(),
+            slug: "renamed-later".to_string(),
+            feed_url: "https://example.org/rss".to_string(),
+            assets: Some(toml::from_str("enclosures = true").unwrap()),
+            ..Default::default()
+        };
+
+        let categories = vec![
+            category("News & Views", vec![tricky.clone()]),
+            category("General", vec![plain.clone()]),
+        ];
+        save_opml(&categories, path, true).unwrap();
+
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(text.contains("<opml version=\"2.0\""));
+        assert!(text.contains("<dateCreated>"));
+        assert!(text.contains("type=\"atom\""));
+        assert!(text.contains("type=\"rss\""));
+        assert!(!text.contains("\\\""));
+
+        let feeds = get_opml_feeds(path).unwrap();
+        assert_eq!(feeds.len(), 2);
+
+        assert_eq!(feeds[0].category.as_deref(), Some("News & Views"));
+        assert_eq!(feeds[0].title.as_deref(), Some(tricky.title.as_str()));
+        assert_eq!(feeds[0].url, tricky.feed_url);
+        assert_eq!(feeds[0].html_url.as_deref(), Some(tricky.url.as_str()));
+        assert_eq!(feeds[0].description.as_deref(), Some("Cats\nand mice"));
+        assert!(feeds[0].settings.full_content);
+        assert_eq!(feeds[0].settings.update_interval_mins, Some(30));
+
+        assert_eq!(feeds[1].html_url, None);
+        assert_eq!(feeds[1].settings.assets, plain.assets);
+        assert_eq!(feeds[1].settings.slug.as_deref(), Some("renamed-later"));
+
+        let mut feed = FeedItem::default();
+        OpmlSettings {
+            slug: Some("../../escape".to_string()),
+            ..Default::default()
+        }
+        .apply(&mut feed);
+        assert_eq!(feed.slug, "escape");
+        assert!(!feeds[1].settings.full_content);
+
+        // Without settings, nothing of bulletty's own is written
+        save_opml(&categories, path, false).unwrap();
+        let text = std::fs::read_to_string(path).unwrap();
+        assert!(!text.contains("bulletty:"));
+        assert_eq!(
+            get_opml_feeds(path).unwrap()[0].settings,
+            OpmlSettings::default()
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{bail, eyre};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::core::{defs::DATA_RULES, feed::feedentry::FeedEntry, library::feeditem::FeedItem};
+
+/// What to do with an entry matching a rule
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Don't store the entry at all
+    Drop,
+    /// Store it as already read
+    Read,
+    ReadLater,
+    Star,
+    /// Add the rule's `tag` to the entry's categories
+    Tag,
+}
+
+/// A rule as written in the rules file. Every condition given must match.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RuleData {
+    title: Option<String>,
+    author: Option<String>,
+    url: Option<String>,
+    /// Category or tag given by the feed, ignoring case
+    category: Option<String>,
+    /// Feed slug or title, to apply the rule to one feed only
+    feed: Option<String>,
+    action: RuleAction,
+    tag: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct RulesData {
+    #[serde(default)]
+    rule: Vec<RuleData>,
+}
+
+struct Rule {
+    title: Option<Regex>,
+    author: Option<Regex>,
+    url: Option<Regex>,
+    category: Option<String>,
+    feed: Option<String>,
+    action: RuleAction,
+    tag: Option<String>,
+}
+
+/// What the rules decided for an entry, besides the changes made to it
+#[derive(Default, Debug, PartialEq)]
+pub struct RuleOutcome {
+    pub drop: bool,
+    pub read_later: bool,
+}
+
+/// Kill-file rules, applied to entries as they are first stored
+#[derive(Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Loads the rules file from the library, if there's one
+    pub fn load(datapath: &Path) -> color_eyre::Result<Self> {
+        let path = datapath.join(DATA_RULES);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        Self::parse(&fs::read_to_string(&path)?).map_err(|e| eyre!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> color_eyre::Result<Self> {
+        let data: RulesData = toml::from_str(text)?;
+        let mut rules = vec![];
+
+        for (i, rule) in data.rule.into_iter().enumerate() {
+            if rule.action == RuleAction::Tag && rule.tag.is_none() {
+                bail!("Rule {} has the tag action but no tag", i + 1);
+            }
+
+            rules.push(Rule {
+                title: rule.title.as_deref().map(pattern).transpose()?,
+                author: rule.author.as_deref().map(pattern).transpose()?,
+                url: rule.url.as_deref().map(pattern).transpose()?,
+                category: rule.category,
+                feed: rule.feed,
+                action: rule.action,
+                tag: rule.tag,
+            });
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Runs every matching rule on `entry`, in order, stopping at the first one dropping it. Tags
+    /// added by a rule can be matched by the ones after it.
+    pub fn apply(&self, feed: &FeedItem, entry: &mut FeedEntry) -> RuleOutcome {
+        let mut outcome = RuleOutcome::default();
+
+        for rule in self.rules.iter() {
+            if !rule.matches(feed, entry) {
+                continue;
+            }
+
+            match rule.action {
+                RuleAction::Drop => {
+                    outcome.drop = true;
+                    break;
+                }
+                RuleAction::Read => entry.seen = true,
+                RuleAction::ReadLater => outcome.read_later = true,
+                RuleAction::Star => entry.starred = true,
+                RuleAction::Tag => {
+                    if let Some(tag) = rule.tag.as_ref()
+                        && !entry.has_category(tag)
+                    {
+                        entry.categories.push(tag.clone());
+                    }
+                }
+            }
+        }
+
+        outcome
+    }
+}
+
+impl Rule {
+    fn matches(&self, feed: &FeedItem, entry: &FeedEntry) -> bool {
+        let is_match =
+            |regex: &Option<Regex>, text: &str| regex.as_ref().is_none_or(|r| r.is_match(text));
+
+        is_match(&self.title, &entry.title)
+            && is_match(&self.author, &entry.author)
+            && is_match(&self.url, &entry.url)
+            && self.category.as_ref().is_none_or(|c| entry.has_category(c))
+            && self.feed.as_ref().is_none_or(|f| {
+                f.eq_ignore_ascii_case(&feed.slug) || f.eq_ignore_ascii_case(&feed.title)
+            })
+    }
+}
+
+/// Compiles a regular expression, also accepting the `/pattern/flags` form
+fn pattern(text: &str) -> color_eyre::Result<Regex> {
+    let source = match text.strip_prefix('/').and_then(|t| t.rsplit_once('/')) {
+        Some((body, flags)) if flags.chars().all(|c| "imsux".contains(c)) => {
+            if flags.is_empty() {
+                body.to_string()
+            } else {
+                format!("(?{flags}){body}")
+            }
+        }
+        _ => text.to_string(),
+    };
+
+    Regex::new(&source).map_err(|e| eyre!("Invalid pattern {:?}: {}", text, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, author: &str, categories: &[&str]) -> FeedEntry {
+        FeedEntry {
+            title: title.to_string(),
+            author: author.to_string(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_rules() {
+        let rules = Rules::parse(
+            r#"
+[[rule]]
+title = "/sponsored/i"
+action = "drop"
+
+[[rule]]
+author = "^Bot$"
+action = "read"
+
+[[rule]]
+category = "rust"
+action = "tag"
+tag = "Programming"
+
+[[rule]]
+category = "rust"
+feed = "this-week"
+action = "star"
+
+[[rule]]
+title = "(?i)release"
+action = "read_later"
+"#,
+        )
+        .unwrap();
+
+        let feed = FeedItem {
+            title: "This Week".to_string(),
+            slug: "this-week".to_string(),
+            ..Default::default()
+        };
+        let other = FeedItem {
+            slug: "other".to_string(),
+            ..Default::default()
+        };
+
+        let mut e = entry("A SPONSORED post", "Bot", &[]);
+        assert!(rules.apply(&feed, &mut e).drop);
+        assert!(!e.seen);
+
+        let mut e = entry("Weekly news", "Bot", &["Rust"]);
+        assert_eq!(rules.apply(&feed, &mut e), RuleOutcome::default());
+        assert!(e.seen);
+        assert!(e.starred);
+        assert_eq!(e.categories, vec!["Rust", "Programming"]);
+
+        let mut e = entry("New release", "Robot", &["rust"]);
+        assert!(rules.apply(&other, &mut e).read_later);
+        assert!(!e.seen);
+        assert!(!e.starred);
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(Rules::parse("[[rule]]\naction = \"tag\"").is_err());
+        assert!(Rules::parse("[[rule]]\ntitle = \"(\"\naction = \"drop\"").is_err());
+        assert!(Rules::parse("[[rule]]\naction = \"explode\"").is_err());
+        assert!(Rules::parse("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use html_escape::encode_text as text;
+use pulldown_cmark::{Parser, html};
+
+use crate::core::library::export::{ExportEntry, STYLE};
+
+/// Longest excerpt of an entry's description, in characters
+const EXCERPT_CHARS: usize = 300;
+
+/// A Markdown digest of the entries, by category and feed, with a link and an excerpt of each
+pub fn digest_markdown(title: &str, since: DateTime<Utc>, entries: &[ExportEntry]) -> String {
+    let mut groups: BTreeMap<&str, BTreeMap<&str, Vec<&ExportEntry>>> = BTreeMap::new();
+    for entry in entries {
+        groups
+            .entry(&entry.summary.category)
+            .or_default()
+            .entry(&entry.summary.feed)
+            .or_default()
+            .push(entry);
+    }
+
+    let mut out = format!(
+        "# {}\n\n{} new {} since {}.\n",
+        escape(title),
+        entries.len(),
+        if entries.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        },
+        since.format("%Y-%m-%d %H:%M UTC")
+    );
+
+    for (category, feeds) in groups {
+        out.push_str(&format!("\n## {}\n", escape(category)));
+
+        for (feed, entries) in feeds {
+            out.push_str(&format!("\n### {}\n\n", escape(feed)));
+
+            for export in entries {
+                let summary = &export.summary;
+                let title = escape(&summary.title);
+
+                let mut line = if summary.url.is_empty() {
+                    format!("- **{title}**")
+                } else {
+                    format!("- [{title}](<{}>)", summary.url.replace('>', "%3E"))
+                };
+                if !summary.author.is_empty() {
+                    line.push_str(&format!(" · {}", escape(&summary.author)));
+                }
+                line.push_str(&format!(" · {}", summary.date.format("%Y-%m-%d %H:%M")));
+
+                // The excerpt goes on its own line, after a hard line break
+                let excerpt = excerpt(&export.entry.description);
+                if excerpt.is_empty() {
+                    line.push('\n');
+                } else {
+                    line.push_str(&format!("\\\n  {}\n", escape(&excerpt)));
+                }
+
+                out.push_str(&line);
+            }
+        }
+    }
+
+    out
+}
+
+/// The Markdown digest as a single HTML document, with its style inline so it can be mailed
+pub fn digest_html(title: &str, markdown: &str) -> String {
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new(markdown));
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        text(title),
+        STYLE,
+        body
+    )
+}
+
+/// The description on a single line, cut at a word near `EXCERPT_CHARS`
+fn excerpt(description: &str) -> String {
+    let line = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= EXCERPT_CHARS {
+        return line;
+    }
+
+    let cut: String = line.chars().take(EXCERPT_CHARS).collect();
+    let cut = cut
+        .rsplit_once(' ')
+        .map_or(cut.as_str(), |(words, _)| words);
+    format!(
+        "{}…",
+        cut.trim_end_matches(|c: char| c.is_ascii_punctuation())
+    )
+}
+
+/// Escapes the characters that Markdown would read as formatting
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '!' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::feed::feedentry::{EntrySummary, FeedEntry};
+
+    fn entry(category: &str, feed: &str, title: &str, description: &str) -> ExportEntry {
+        ExportEntry {
+            summary: EntrySummary {
+                category: category.to_string(),
+                feed: feed.to_string(),
+                title: title.to_string(),
+                url: format!("https://example.org/{}", title.len()),
+                date: DateTime::parse_from_rfc3339("2025-03-10T07:00:00Z")
+                    .unwrap()
+                    .with_timezone(&Utc),
+                ..Default::default()
+            },
+            entry: FeedEntry {
+                description: description.to_string(),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_digest() {
+        let since = DateTime::parse_from_rfc3339("2025-03-09T07:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let entries = vec![
+            entry("Tech", "Blog", "A *new* [release]", "Line one\nline   two"),
+            entry("Art", "Gallery", "Pictures", &"word ".repeat(100)),
+            entry("Tech", "Blog", "Another", ""),
+        ];
+
+        let markdown = digest_markdown("Morning", since, &entries);
+
+        assert!(markdown.starts_with("# Morning\n\n3 new entries since 2025-03-09 07:00 UTC.\n"));
+        assert!(markdown.find("## Art").unwrap() < markdown.find("## Tech").unwrap());
+        assert!(markdown.contains(
+            "- [A \\*new\\* \\[release\\]](<https://example.org/17>) · 2025-03-10 07:00\\\n  Line one line two\n"
+        ));
+        assert!(markdown.contains("- [Another](<https://example.org/7>) · 2025-03-10 07:00\n"));
+        assert!(markdown.contains("word word…\n"));
+
+        let html = digest_html("Morning", &markdown);
+        assert!(html.contains("<h2>Art</h2>"));
+        assert!(html.contains("<a href=\"https://example.org/17\">A *new* [release]</a>"));
+        assert!(html.contains("<br />\nLine one line two"));
+        assert!(!html.contains("<em>"));
+    }
+}
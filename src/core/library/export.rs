@@ -0,0 +1,706 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use color_eyre::Result;
+use html_escape::{encode_double_quoted_attribute as attr, encode_text as text};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd, html};
+use sha2::{Digest, Sha256};
+use slug::slugify;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use tracing::error;
+
+use crate::core::{
+    feed::{
+        feedentry::{EntrySummary, FeedEntry},
+        fetcher::Fetcher,
+    },
+    library::{data::assets, feeditem::AssetOptions},
+};
+
+pub const STYLE: &str = "body { max-width: 42em; margin: 2em auto; padding: 0 1em; \
+font-family: Georgia, serif; line-height: 1.6; color: #222; }
+h1, h2, h3 { font-family: sans-serif; line-height: 1.25; }
+img { max-width: 100%; height: auto; }
+pre { overflow-x: auto; background: #f4f4f4; padding: 0.5em; }
+.meta, time { color: #666; font-size: 0.9em; }
+nav ol, ul.entries { list-style: none; padding-left: 1em; }
+";
+
+/// An entry to export, with the feed and category it belongs to
+pub struct ExportEntry {
+    pub summary: EntrySummary,
+    pub entry: FeedEntry,
+}
+
+/// An exported document, and the entries it holds in reading order, grouped by category and
+/// feed
+struct Book<'a> {
+    title: &'a str,
+    chapters: Vec<Chapter<'a>>,
+    /// Images, by their name in the export
+    assets: BTreeMap<String, Asset>,
+}
+
+/// An image of the export: a file stored with an entry, or one downloaded for it
+enum Asset {
+    File(PathBuf),
+    Data(Vec<u8>),
+}
+
+impl Asset {
+    fn read(&self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Asset::File(path) => std::fs::read(path)?,
+            Asset::Data(data) => data.clone(),
+        })
+    }
+}
+
+/// Chapters of a feed, under its title
+type FeedGroup<'b, 'a> = (&'b str, Vec<&'b Chapter<'a>>);
+
+struct Chapter<'a> {
+    entry: &'a ExportEntry,
+    /// Name of the chapter's file, without extension
+    name: String,
+    body: String,
+}
+
+impl<'a> Book<'a> {
+    fn new(title: &'a str, entries: &'a [ExportEntry], fetcher: &dyn Fetcher) -> Self {
+        let mut groups: BTreeMap<(&str, &str), Vec<&ExportEntry>> = BTreeMap::new();
+        for entry in entries {
+            groups
+                .entry((&entry.summary.category, &entry.summary.feed))
+                .or_default()
+                .push(entry);
+        }
+
+        let mut book = Self {
+            title,
+            chapters: vec![],
+            assets: BTreeMap::new(),
+        };
+        let mut sources = HashMap::new();
+
+        for entry in groups.into_values().flatten() {
+            let slug: String = slugify(&entry.summary.title).chars().take(60).collect();
+            let name = format!("{:04}-{}", book.chapters.len() + 1, slug);
+            let entry_dir = entry.entry.filepath.parent().unwrap_or(Path::new(""));
+            let body = render_entry(entry, &mut |url| {
+                book_asset(&mut book.assets, &mut sources, fetcher, entry_dir, url)
+            });
+
+            book.chapters.push(Chapter { entry, name, body });
+        }
+
+        book
+    }
+
+    /// The chapters of each category and feed, in order
+    fn groups(&self) -> Vec<(&str, Vec<FeedGroup<'_, 'a>>)> {
+        let mut groups: Vec<(&str, Vec<FeedGroup>)> = vec![];
+
+        for chapter in self.chapters.iter() {
+            let summary = &chapter.entry.summary;
+
+            if groups.last().is_none_or(|(c, _)| *c != summary.category) {
+                groups.push((&summary.category, vec![]));
+            }
+            let feeds = &mut groups.last_mut().unwrap().1;
+
+            if feeds.last().is_none_or(|(f, _)| *f != summary.feed) {
+                feeds.push((&summary.feed, vec![]));
+            }
+            feeds.last_mut().unwrap().1.push(chapter);
+        }
+
+        groups
+    }
+}
+
+/// Names the image at `url` for the export, giving the same name to the same file. Remote
+/// images are downloaded, so the export doesn't depend on the web; `None` when that fails.
+fn book_asset(
+    assets: &mut BTreeMap<String, Asset>,
+    sources: &mut HashMap<String, Option<String>>,
+    fetcher: &dyn Fetcher,
+    entry_dir: &Path,
+    url: &str,
+) -> Option<String> {
+    if let Some(path) = local_image(entry_dir, url) {
+        let key = path.to_string_lossy().to_string();
+        if let Some(name) = sources.get(&key) {
+            return name.clone();
+        }
+
+        let name = local_asset_name(assets, &path);
+        assets.insert(name.clone(), Asset::File(path));
+        sources.insert(key, Some(name.clone()));
+        return Some(name);
+    }
+
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    if let Some(name) = sources.get(url) {
+        return name.clone();
+    }
+
+    let max_bytes = AssetOptions::default().max_size_kb * 1024;
+    let name = match assets::fetch_asset(fetcher, url, max_bytes) {
+        Ok((data, extension)) => {
+            let name = assets::asset_name(&data, &extension);
+            if media_type(Path::new(&name)).is_some() {
+                assets.entry(name.clone()).or_insert(Asset::Data(data));
+                Some(name)
+            } else {
+                error!("Not exporting {url}, as it isn't a supported image");
+                None
+            }
+        }
+        Err(e) => {
+            error!("Couldn't download image {url}: {e}");
+            None
+        }
+    };
+
+    sources.insert(url.to_string(), name.clone());
+    name
+}
+
+/// A name for a local image that no other image of the export has
+fn local_asset_name(assets: &BTreeMap<String, Asset>, path: &Path) -> String {
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut name = filename.clone();
+    let mut count = 1;
+    while assets.contains_key(&name) {
+        count += 1;
+        name = format!("{count}-{filename}");
+    }
+
+    name
+}
+
+/// Renders the entry's header and text as HTML, which is also valid XHTML. Images are renamed
+/// through `asset`, and those it has no name for become links. Raw HTML is written as text, since
+/// it may run scripts or not be well formed.
+fn render_entry(export: &ExportEntry, asset: &mut dyn FnMut(&str) -> Option<String>) -> String {
+    let summary = &export.summary;
+    let entry = &export.entry;
+
+    let mut meta = vec![text(&summary.feed).to_string()];
+    if !summary.author.is_empty() {
+        meta.push(text(&summary.author).to_string());
+    }
+    meta.push(format!(
+        "<time datetime=\"{}\">{}</time>",
+        summary.date.to_rfc3339(),
+        summary.date.format("%Y-%m-%d %H:%M")
+    ));
+
+    let mut out = format!(
+        "<article>\n<header>\n<h1>{}</h1>\n<p class=\"meta\">{}</p>\n",
+        text(&summary.title),
+        meta.join(" · ")
+    );
+    if !summary.url.is_empty() {
+        out.push_str(&format!(
+            "<p class=\"meta\"><a href=\"{}\">{}</a></p>\n",
+            attr(&summary.url),
+            text(&summary.url)
+        ));
+    }
+    out.push_str("</header>\n");
+
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    // The URL of the image being turned into a link, and whether it had any text
+    let mut linked: Option<(CowStr, bool)> = None;
+    let events = Parser::new_ext(&entry.text, options).flat_map(|event| match event {
+        Event::Start(Tag::Image { ref dest_url, .. }) if dest_url.starts_with("data:") => {
+            vec![event]
+        }
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => match asset(&dest_url) {
+            Some(name) => vec![Event::Start(Tag::Image {
+                link_type,
+                dest_url: CowStr::from(format!("../assets/{name}")),
+                title,
+                id,
+            })],
+            None => {
+                linked = Some((dest_url.clone(), false));
+                vec![Event::Start(Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    id,
+                })]
+            }
+        },
+        Event::End(TagEnd::Image) => match linked.take() {
+            Some((url, false)) => vec![Event::Text(url), Event::End(TagEnd::Link)],
+            Some((_, true)) => vec![Event::End(TagEnd::Link)],
+            None => vec![Event::End(TagEnd::Image)],
+        },
+        Event::Html(html) | Event::InlineHtml(html) => vec![Event::Text(html)],
+        event => {
+            if let Some((_, had_text)) = linked.as_mut() {
+                *had_text = true;
+            }
+            vec![event]
+        }
+    });
+    html::push_html(&mut out, events);
+
+    out.push_str("</article>\n");
+    out
+}
+
+/// The file of an image stored next to the entry, if it is one
+fn local_image(entry_dir: &Path, url: &str) -> Option<PathBuf> {
+    if url.contains("://") || url.starts_with("data:") || url.starts_with('/') {
+        return None;
+    }
+
+    let path = entry_dir.join(url);
+    (path.is_file() && media_type(&path).is_some()).then_some(path)
+}
+
+fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    Some(match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+fn html_page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n\
+<link rel=\"stylesheet\" href=\"{}\">\n</head>\n<body>\n{}</body>\n</html>\n",
+        text(title),
+        stylesheet,
+        body
+    )
+}
+
+fn xhtml_page(title: &str, stylesheet: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" \
+lang=\"en\" xml:lang=\"en\">\n<head>\n<meta charset=\"UTF-8\" />\n<title>{}</title>\n\
+<link rel=\"stylesheet\" type=\"text/css\" href=\"{}\" />\n</head>\n<body>\n{}</body>\n</html>\n",
+        text(title),
+        stylesheet,
+        body
+    )
+}
+
+/// Writes the entries as a static site in `dir`: an `index.html` listing them by category and
+/// feed, a page for each one and the images they use
+pub fn write_site(
+    dir: &Path,
+    title: &str,
+    entries: &[ExportEntry],
+    fetcher: &dyn Fetcher,
+) -> Result<()> {
+    let book = Book::new(title, entries, fetcher);
+
+    std::fs::create_dir_all(dir.join("entries"))?;
+    std::fs::write(dir.join("style.css"), STYLE)?;
+
+    let mut index = format!(
+        "<h1>{}</h1>\n<p class=\"meta\">{} entries, exported {}</p>\n",
+        text(title),
+        book.chapters.len(),
+        Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+
+    for (category, feeds) in book.groups() {
+        index.push_str(&format!("<h2>{}</h2>\n", text(category)));
+
+        for (feed, chapters) in feeds {
+            index.push_str(&format!(
+                "<h3>{}</h3>\n<ul class=\"entries\">\n",
+                text(feed)
+            ));
+            for chapter in chapters {
+                index.push_str(&format!(
+                    "<li><a href=\"entries/{}.html\">{}</a> <time>{}</time></li>\n",
+                    chapter.name,
+                    text(&chapter.entry.summary.title),
+                    chapter.entry.summary.date.format("%Y-%m-%d")
+                ));
+            }
+            index.push_str("</ul>\n");
+        }
+    }
+
+    std::fs::write(
+        dir.join("index.html"),
+        html_page(title, "style.css", &index),
+    )?;
+
+    for chapter in book.chapters.iter() {
+        let body = format!(
+            "<nav><a href=\"../index.html\">← {}</a></nav>\n{}",
+            text(title),
+            chapter.body
+        );
+        std::fs::write(
+            dir.join("entries").join(format!("{}.html", chapter.name)),
+            html_page(&chapter.entry.summary.title, "../style.css", &body),
+        )?;
+    }
+
+    copy_assets(&book, &dir.join("assets"))
+}
+
+fn copy_assets(book: &Book, dir: &Path) -> Result<()> {
+    if book.assets.is_empty() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(dir)?;
+    for (name, asset) in book.assets.iter() {
+        std::fs::write(dir.join(name), asset.read()?)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the entries as an EPUB 3 book, with a chapter for each one, in a table of contents by
+/// category and feed
+pub fn write_epub(
+    file: &Path,
+    title: &str,
+    entries: &[ExportEntry],
+    fetcher: &dyn Fetcher,
+) -> Result<()> {
+    let book = Book::new(title, entries, fetcher);
+
+    let mut zip = ZipWriter::new(File::create(file)?);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype must come first and uncompressed, so it can be read at a fixed offset
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(b"application/epub+zip")?;
+
+    zip.start_file("META-INF/container.xml", deflated)?;
+    zip.write_all(
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">\n\
+<rootfiles>\n<rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/>\n\
+</rootfiles>\n</container>\n",
+    )?;
+
+    zip.start_file("OEBPS/content.opf", deflated)?;
+    zip.write_all(epub_package(&book).as_bytes())?;
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)?;
+    zip.write_all(epub_nav(&book).as_bytes())?;
+
+    zip.start_file("OEBPS/style.css", deflated)?;
+    zip.write_all(STYLE.as_bytes())?;
+
+    for chapter in book.chapters.iter() {
+        zip.start_file(format!("OEBPS/entries/{}.xhtml", chapter.name), deflated)?;
+        zip.write_all(
+            xhtml_page(&chapter.entry.summary.title, "../style.css", &chapter.body).as_bytes(),
+        )?;
+    }
+
+    for (name, asset) in book.assets.iter() {
+        zip.start_file(format!("OEBPS/assets/{name}"), deflated)?;
+        zip.write_all(&asset.read()?)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn epub_package(book: &Book) -> String {
+    // A stable identifier for the same selection of entries
+    let mut hasher = Sha256::new();
+    hasher.update(book.title.as_bytes());
+    for chapter in book.chapters.iter() {
+        hasher.update(chapter.entry.summary.path.as_bytes());
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    let uuid = format!(
+        "{}-{}-{}-{}-{}",
+        &hash[0..8],
+        &hash[8..12],
+        &hash[12..16],
+        &hash[16..20],
+        &hash[20..32]
+    );
+
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+<item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+
+    for (i, chapter) in book.chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"entry{i}\" href=\"entries/{}.xhtml\" media-type=\"application/xhtml+xml\"/>\n",
+            chapter.name
+        ));
+        spine.push_str(&format!("<itemref idref=\"entry{i}\"/>\n"));
+    }
+
+    for (i, name) in book.assets.keys().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"asset{i}\" href=\"assets/{}\" media-type=\"{}\"/>\n",
+            attr(name),
+            media_type(Path::new(name)).unwrap_or("application/octet-stream")
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\">\n\
+<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+<dc:identifier id=\"id\">urn:uuid:{uuid}</dc:identifier>\n\
+<dc:title>{}</dc:title>\n<dc:language>en</dc:language>\n<dc:creator>bulletty</dc:creator>\n\
+<meta property=\"dcterms:modified\">{}</meta>\n</metadata>\n\
+<manifest>\n{manifest}</manifest>\n<spine>\n{spine}</spine>\n</package>\n",
+        text(book.title),
+        Utc::now().format("%Y-%m-%dT%H:%M:%SZ")
+    )
+}
+
+fn epub_nav(book: &Book) -> String {
+    let mut nav = format!(
+        "<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n",
+        text(book.title)
+    );
+
+    for (category, feeds) in book.groups() {
+        let first = &feeds[0].1[0].name;
+        nav.push_str(&format!(
+            "<li><a href=\"entries/{first}.xhtml\">{}</a>\n<ol>\n",
+            text(category)
+        ));
+
+        for (feed, chapters) in feeds {
+            nav.push_str(&format!(
+                "<li><a href=\"entries/{}.xhtml\">{}</a>\n<ol>\n",
+                chapters[0].name,
+                text(feed)
+            ));
+            for chapter in chapters {
+                nav.push_str(&format!(
+                    "<li><a href=\"entries/{}.xhtml\">{}</a></li>\n",
+                    chapter.name,
+                    text(&chapter.entry.summary.title)
+                ));
+            }
+            nav.push_str("</ol>\n</li>\n");
+        }
+
+        nav.push_str("</ol>\n</li>\n");
+    }
+    nav.push_str("</ol>\n</nav>\n");
+
+    xhtml_page(book.title, "style.css", &nav)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::feed::fetcher::fixture::FixtureFetcher;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    fn export_entry(
+        dir: &Path,
+        category: &str,
+        feed: &str,
+        title: &str,
+        text: &str,
+    ) -> ExportEntry {
+        let filepath = dir.join(format!("{}.md", slugify(title)));
+        ExportEntry {
+            summary: EntrySummary {
+                feed: feed.to_string(),
+                category: category.to_string(),
+                title: title.to_string(),
+                url: "https://example.org/?a=1&b=2".to_string(),
+                path: filepath.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+            entry: FeedEntry {
+                title: title.to_string(),
+                text: text.to_string(),
+                filepath,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn entries(dir: &Path) -> Vec<ExportEntry> {
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("assets/abc.png"), b"png").unwrap();
+
+        vec![
+            export_entry(
+                dir,
+                "Tech",
+                "Blog",
+                "Tom & Jerry",
+                "Some **bold** text<br>\n\n![local](assets/abc.png) ![remote](https://example.org/a.png) \
+![gone](https://example.org/gone.png) ![](https://example.org/gone.png)\n\n\
+<script>alert(1)</script>\n\n<iframe src=\"https://example.org/\"></iframe>",
+            ),
+            export_entry(
+                dir,
+                "Art",
+                "Gallery",
+                "Pictures",
+                "![again](assets/abc.png) ![remote again](https://example.org/a.png)",
+            ),
+        ]
+    }
+
+    fn fetcher() -> FixtureFetcher {
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.org/a.png", "remote png")
+            .status("https://example.org/gone.png", 404);
+        fetcher
+    }
+
+    fn remote_asset() -> String {
+        assets::asset_name(b"remote png", "png")
+    }
+
+    #[test]
+    fn test_write_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = entries(&temp_dir.path().join("library"));
+        let out = temp_dir.path().join("site");
+
+        let fetcher = fetcher();
+        write_site(&out, "My <Export>", &entries, &fetcher).unwrap();
+        // Each image is only downloaded once
+        assert_eq!(fetcher.requests().len(), 2);
+
+        let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains("<title>My &lt;Export&gt;</title>"));
+        // Categories are sorted, so Art comes first
+        assert!(index.find("<h2>Art</h2>").unwrap() < index.find("<h2>Tech</h2>").unwrap());
+        assert!(index.contains("<a href=\"entries/0002-tom-jerry.html\">Tom &amp; Jerry</a>"));
+
+        let page = std::fs::read_to_string(out.join("entries/0002-tom-jerry.html")).unwrap();
+        assert!(page.contains("<strong>bold</strong>"));
+        assert!(page.contains("src=\"../assets/abc.png\""));
+        assert!(page.contains(&format!("src=\"../assets/{}\"", remote_asset())));
+        // Images that can't be downloaded are linked instead
+        assert!(!page.contains("<img src=\"https://"));
+        assert!(page.contains("<a href=\"https://example.org/gone.png\">gone</a>"));
+        assert!(
+            page.contains(
+                "<a href=\"https://example.org/gone.png\">https://example.org/gone.png</a>"
+            )
+        );
+        assert!(page.contains("href=\"https://example.org/?a=1&amp;b=2\""));
+        assert!(page.contains("<a href=\"../index.html\">"));
+        // Raw HTML is shown as text, so it can't run anything
+        assert!(!page.contains("<script>"));
+        assert!(!page.contains("<iframe"));
+        assert!(page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+
+        assert_eq!(std::fs::read(out.join("assets/abc.png")).unwrap(), b"png");
+        assert_eq!(
+            std::fs::read(out.join("assets").join(remote_asset())).unwrap(),
+            b"remote png"
+        );
+        assert!(out.join("style.css").exists());
+    }
+
+    #[test]
+    fn test_write_epub() {
+        let temp_dir = TempDir::new().unwrap();
+        let entries = entries(&temp_dir.path().join("library"));
+        let path = temp_dir.path().join("export.epub");
+
+        write_epub(&path, "My Export", &entries, &fetcher()).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        {
+            let mimetype = archive.by_index(0).unwrap();
+            assert_eq!(mimetype.name(), "mimetype");
+            assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        }
+
+        let read = |archive: &mut zip::ZipArchive<File>, name: &str| {
+            let mut content = String::new();
+            archive
+                .by_name(name)
+                .unwrap()
+                .read_to_string(&mut content)
+                .unwrap();
+            content
+        };
+
+        let package = read(&mut archive, "OEBPS/content.opf");
+        assert!(package.contains("href=\"entries/0001-pictures.xhtml\""));
+        assert!(package.contains("href=\"assets/abc.png\" media-type=\"image/png\""));
+        assert!(archive.by_name("OEBPS/assets/abc.png").is_ok());
+        assert!(
+            archive
+                .by_name(&format!("OEBPS/assets/{}", remote_asset()))
+                .is_ok()
+        );
+        // Everything is in the book, so no remote resources have to be declared
+        assert!(!package.contains("remote-resources"));
+
+        // Every XML document must be well formed, even with raw HTML in the entry
+        let names: Vec<String> = archive.file_names().map(String::from).collect();
+        for name in names
+            .iter()
+            .filter(|n| n.ends_with(".xhtml") || n.ends_with(".opf") || n.ends_with(".xml"))
+        {
+            let content = read(&mut archive, name);
+            assert!(
+                roxmltree::Document::parse_with_options(
+                    &content,
+                    roxmltree::ParsingOptions {
+                        allow_dtd: true,
+                        ..Default::default()
+                    }
+                )
+                .is_ok(),
+                "{name} isn't well formed"
+            );
+        }
+
+        let chapter = read(&mut archive, "OEBPS/entries/0002-tom-jerry.xhtml");
+        assert!(chapter.contains("&lt;br&gt;"));
+        assert!(chapter.contains("src=\"../assets/abc.png\""));
+        assert!(!chapter.contains("<img src=\"https://"));
+    }
+}
@@ -1,6 +1,14 @@
+use std::collections::BTreeMap;
+
 use chrono::{DateTime, Utc};
+use color_eyre::eyre::eyre;
 use serde::{Deserialize, Serialize};
 
+use crate::core::feed::{feedutils, fetcher};
+
+/// Longest `update_interval_mins`, a year, so the next update is always a valid date
+pub const MAX_UPDATE_INTERVAL_MINS: u64 = 365 * 24 * 60;
+
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct FeedItem {
     pub title: String,
@@ -12,6 +20,213 @@ pub struct FeedItem {
 
     pub lastupdated: DateTime<Utc>,
 
+    /// Download each new entry's page and store its main content instead of the feed's summary
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub full_content: bool,
+
+    /// Minutes between updates by `bulletty daemon`, instead of its `--interval`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_interval_mins: Option<u64>,
+
+    /// When set, referenced images and enclosures are downloaded next to the entries
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets: Option<AssetOptions>,
+
+    /// Format of the feed document, `rss` or `atom`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+
+    /// Extra request settings for feeds that need authentication or special headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpOptions>,
+
     #[serde(skip_serializing, skip_deserializing)]
     pub category: String,
 }
+
+impl FeedItem {
+    /// The feed's request options to send to `url`, only when it's on the feed's own host.
+    /// Entry links can point anywhere, and the options may hold credentials.
+    pub fn http_for(&self, url: &str) -> Option<&HttpOptions> {
+        self.http
+            .as_ref()
+            .filter(|_| fetcher::same_origin(&self.feed_url, url))
+    }
+}
+
+/// Per-feed settings for storing entry assets locally, read from the `[assets]` table of the
+/// feed's `.feed.toml`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AssetOptions {
+    #[serde(default = "default_images")]
+    pub images: bool,
+    #[serde(default = "default_max_size_kb")]
+    pub max_size_kb: u64,
+    #[serde(default)]
+    pub enclosures: bool,
+    #[serde(default = "default_max_enclosure_size_mb")]
+    pub max_enclosure_size_mb: u64,
+}
+
+impl Default for AssetOptions {
+    fn default() -> Self {
+        Self {
+            images: default_images(),
+            max_size_kb: default_max_size_kb(),
+            enclosures: false,
+            max_enclosure_size_mb: default_max_enclosure_size_mb(),
+        }
+    }
+}
+
+/// Per-feed request settings, read from the `[http]` table of the feed's `.feed.toml`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cookie: Option<Secret>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, Secret>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<Auth>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    Basic {
+        username: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password: Option<Secret>,
+    },
+    Bearer {
+        token: Secret,
+    },
+}
+
+/// A value that shouldn't be stored in the synced data directory. `env:NAME` reads it from an
+/// environment variable and `cmd:COMMAND` from the output of a command, e.g. a password manager.
+/// Anything else is used as is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(pub String);
+
+impl Secret {
+    pub fn resolve(&self) -> color_eyre::Result<String> {
+        if let Some(name) = self.0.strip_prefix("env:") {
+            std::env::var(name.trim())
+                .map_err(|e| eyre!("Couldn't read environment variable {}: {}", name.trim(), e))
+        } else if let Some(command) = self.0.strip_prefix("cmd:") {
+            let output = feedutils::run_command(command.trim())?;
+            Ok(String::from_utf8_lossy(&output).trim_end().to_string())
+        } else {
+            Ok(self.0.clone())
+        }
+    }
+}
+
+// Defaults
+fn default_images() -> bool {
+    true
+}
+
+fn default_max_size_kb() -> u64 {
+    5 * 1024
+}
+
+fn default_max_enclosure_size_mb() -> u64 {
+    512
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_options_toml() {
+        let toml = r#"
+title = "Private"
+description = ""
+url = "https://example.org"
+feed_url = "https://example.org/feed"
+author = ""
+slug = "private"
+lastupdated = "2025-01-01T00:00:00Z"
+
+[http]
+user_agent = "Mozilla/5.0"
+cookie = "env:PRIVATE_COOKIE"
+
+[http.headers]
+X-Api-Key = "cmd:pass show example"
+
+[http.auth]
+type = "basic"
+username = "me"
+password = "secret"
+"#;
+        let feed: FeedItem = toml::from_str(toml).unwrap();
+        let http = feed.http.as_ref().unwrap();
+
+        assert_eq!(http.user_agent.as_deref(), Some("Mozilla/5.0"));
+        assert_eq!(http.headers["X-Api-Key"].0, "cmd:pass show example");
+        assert_eq!(
+            http.auth,
+            Some(Auth::Basic {
+                username: "me".to_string(),
+                password: Some(Secret("secret".to_string())),
+            })
+        );
+
+        let saved: FeedItem = toml::from_str(&toml::to_string(&feed).unwrap()).unwrap();
+        assert_eq!(saved.http, feed.http);
+    }
+
+    #[test]
+    fn test_http_for_other_hosts() {
+        let feed = FeedItem {
+            feed_url: "https://private.example.org/feed".to_string(),
+            http: Some(HttpOptions {
+                auth: Some(Auth::Bearer {
+                    token: Secret("token".to_string()),
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(
+            feed.http_for("https://private.example.org/posts/1")
+                .is_some()
+        );
+        assert!(
+            feed.http_for("https://elsewhere.example.com/posts/1")
+                .is_none()
+        );
+        assert!(
+            feed.http_for("http://private.example.org/posts/1")
+                .is_none()
+        );
+        assert!(feed.http_for("https://private.example.org:8443/").is_none());
+        assert!(feed.http_for("not a url").is_none());
+    }
+
+    #[test]
+    fn test_secret_resolve() {
+        assert_eq!(Secret("plain".to_string()).resolve().unwrap(), "plain");
+
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(Secret("env:PATH".to_string()).resolve().unwrap(), path);
+        assert!(
+            Secret("env:BULLETTY_TEST_UNSET_VARIABLE".to_string())
+                .resolve()
+                .is_err()
+        );
+
+        if cfg!(unix) {
+            let secret = Secret("cmd:echo token".to_string());
+            assert_eq!(secret.resolve().unwrap(), "token");
+        }
+    }
+}
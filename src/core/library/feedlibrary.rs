@@ -1,30 +1,162 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering::Relaxed},
+    },
+    thread,
+};
+
+use chrono::{DateTime, Utc};
 use color_eyre::eyre::eyre;
 use fuzzt::algorithms::normalized_levenshtein;
-use tracing::error;
+use slug::slugify;
+use tracing::{error, info};
+use url::Url;
 
 use crate::{
     app::AppWorkStatus,
     core::{
         defs,
-        feed::{self, feedentry::FeedEntry},
+        feed::{
+            self, feedentry::FeedEntry, feedparser::FeedCandidate, fetcher::Fetcher,
+            http::HttpClient, source::FeedSource,
+        },
         library::{
-            data::{config::Config, librarydata::LibraryData},
+            data::{
+                config::Config, librarydata::LibraryData, newsboat::CachedEntry, opml::OpmlFeed,
+            },
             feedcategory::FeedCategory,
-            feeditem::FeedItem,
-            settings::usersettings::UserSettings,
+            feeditem::{FeedItem, HttpOptions},
+            settings::{network::Network, usersettings::UserSettings},
             updater::Updater,
         },
     },
 };
 
+#[cfg(test)]
+use crate::core::feed::fetcher::fixture::FixtureFetcher;
 #[cfg(test)]
 use tempfile::TempDir;
 
+/// Which feeds to update
+pub enum UpdateScope {
+    All,
+    Category(String),
+    /// Category title and feed slug
+    Feed(String, String),
+}
+
+impl UpdateScope {
+    fn category(&self) -> Option<&str> {
+        match self {
+            UpdateScope::All => None,
+            UpdateScope::Category(category) | UpdateScope::Feed(category, _) => Some(category),
+        }
+    }
+
+    fn slug(&self) -> Option<&str> {
+        match self {
+            UpdateScope::Feed(_, slug) => Some(slug),
+            _ => None,
+        }
+    }
+}
+
+/// Which entries to list. Empty fields match everything.
+#[derive(Default)]
+pub struct EntryFilter {
+    /// Feed slug or title
+    pub feed: Option<String>,
+    pub category: Option<String>,
+    pub unread: bool,
+    pub starred: bool,
+    /// Only entries in Read Later
+    pub read_later: bool,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only entries stored in the library since then, whatever their publication date
+    pub stored_since: Option<DateTime<Utc>>,
+    /// Category given by the feed
+    pub tag: Option<String>,
+}
+
+impl EntryFilter {
+    fn matches_feed(&self, feed: &FeedItem) -> bool {
+        let feed_matches = self.feed.as_ref().is_none_or(|ident| {
+            feed.slug == *ident || feed.title.to_lowercase() == ident.to_lowercase()
+        });
+        let category_matches = self
+            .category
+            .as_ref()
+            .is_none_or(|category| feed.category.to_lowercase() == category.to_lowercase());
+
+        feed_matches && category_matches
+    }
+
+    fn matches_entry(&self, entry: &FeedEntry) -> bool {
+        (!self.unread || !entry.seen)
+            && (!self.starred || entry.starred)
+            && self.since.is_none_or(|since| entry.date >= since)
+            && self.until.is_none_or(|until| entry.date < until)
+            && self
+                .stored_since
+                .is_none_or(|since| entry.lastupdated >= since)
+            && self.tag.as_ref().is_none_or(|tag| entry.has_category(tag))
+    }
+}
+
+/// Feeds fetched at once by `import_feeds`
+const IMPORT_WORKERS: usize = 8;
+
+/// What happened to a feed from an OPML file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportStatus {
+    Added,
+    /// Couldn't be fetched, so it was registered with its OPML title. The next update tries again
+    Pending,
+    /// Already in the library, or earlier in the same file
+    Exists,
+    /// Would be added, in a dry run
+    New,
+    Failed,
+}
+
+impl ImportStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportStatus::Added => "added",
+            ImportStatus::Pending => "pending",
+            ImportStatus::Exists => "exists",
+            ImportStatus::New => "new",
+            ImportStatus::Failed => "failed",
+        }
+    }
+}
+
+pub struct ImportResult {
+    pub feed: FeedItem,
+    pub status: ImportStatus,
+    pub error: Option<String>,
+}
+
+/// Entries changed by `import_entry_states`
+#[derive(Debug, Default, PartialEq)]
+pub struct StateImport {
+    pub read: usize,
+    pub starred: usize,
+    /// Starred entries the feed doesn't have anymore, stored from the cache
+    pub restored: usize,
+}
+
 pub struct FeedLibrary {
     pub feedcategories: Vec<FeedCategory>,
     pub data: LibraryData,
     pub updater: Option<Updater>,
     pub settings: UserSettings,
+    /// Used for every download, shared with the updater thread
+    pub fetcher: Arc<dyn Fetcher>,
 }
 
 impl Default for FeedLibrary {
@@ -46,11 +178,19 @@ impl FeedLibrary {
             }
         };
 
+        let settings = UserSettings::new(&config_obj.datapath).unwrap();
+
+        let fetcher = HttpClient::new(&settings.network).unwrap_or_else(|e| {
+            error!("Invalid network settings, using the defaults: {:?}", e);
+            HttpClient::new(&Network::default()).expect("default HTTP client should build")
+        });
+
         Self {
             feedcategories: categories,
             data: data_obj,
             updater: None,
-            settings: UserSettings::new(&config_obj.datapath).unwrap(),
+            settings,
+            fetcher: Arc::new(fetcher),
         }
     }
 
@@ -64,6 +204,7 @@ impl FeedLibrary {
                 data: data_obj,
                 updater: None,
                 settings: UserSettings::new(temp_dir.path()).unwrap(),
+                fetcher: Arc::new(FixtureFetcher::default()),
             },
             temp_dir,
         )
@@ -73,16 +214,45 @@ impl FeedLibrary {
         &mut self,
         url: &str,
         category: &Option<String>,
+        http: Option<HttpOptions>,
     ) -> color_eyre::Result<FeedItem> {
-        let (mut feed, text) = feed::feedparser::get_feed_with_data(url)?;
+        let (mut feed, text) =
+            feed::feedparser::get_feed_with_data(self.fetcher.as_ref(), url, http.as_ref())?;
 
         feed.category = category
             .clone()
             .unwrap_or_else(|| String::from(defs::DATA_CATEGORY_DEFAULT));
+        feed.http = http;
 
         self.add_feed(feed, Some(text))
     }
 
+    /// Finds the feeds at `url`, which may be a web page advertising several of them
+    pub fn discover_feeds(
+        &self,
+        url: &str,
+        http: Option<&HttpOptions>,
+    ) -> color_eyre::Result<Vec<FeedCandidate>> {
+        feed::feedparser::discover_feeds(self.fetcher.as_ref(), url, http)
+    }
+
+    /// Adds a feed returned by `discover_feeds`, without downloading it again
+    pub fn add_feed_candidate(
+        &mut self,
+        candidate: FeedCandidate,
+        category: &Option<String>,
+        http: Option<HttpOptions>,
+    ) -> color_eyre::Result<FeedItem> {
+        let mut feed = candidate.feed;
+
+        feed.category = category
+            .clone()
+            .unwrap_or_else(|| String::from(defs::DATA_CATEGORY_DEFAULT));
+        feed.http = http;
+
+        self.add_feed(feed, Some(candidate.text))
+    }
+
     pub fn add_feed(
         &mut self,
         feed: FeedItem,
@@ -96,16 +266,235 @@ impl FeedLibrary {
         // then create
         self.data.feed_create(&feed)?;
 
-        // then update
-        // but let's only update the text is present. because of tests. maybve not the best
-        // approach, but...
-        if text.is_some() {
-            self.data.update_feed_entries(&feed.category, &feed, text)?;
+        // then get its entries, from `text` when the document was downloaded already. A feed
+        // that can't be fetched right now is kept, the next update will try again
+        if let Err(e) =
+            self.data
+                .update_feed_entries(self.fetcher.as_ref(), &feed.category, &feed, text)
+        {
+            error!("Couldn't update {}: {:?}", feed.title, e);
         }
 
         Ok(feed)
     }
 
+    /// Adds the feeds of an OPML file, skipping those already in the library. Feeds are fetched
+    /// in parallel, and those that can't be are kept as pending. With `dry_run`, nothing is
+    /// fetched or added. Results are in the order of `feeds`.
+    pub fn import_feeds(
+        &mut self,
+        feeds: &[OpmlFeed],
+        dry_run: bool,
+        allow_local: bool,
+    ) -> Vec<ImportResult> {
+        let mut known: HashMap<String, FeedItem> = self
+            .feedcategories
+            .iter()
+            .flat_map(|c| c.feeds.iter())
+            .map(|f| (normalize_feed_url(&f.feed_url), f.clone()))
+            .collect();
+
+        let mut results = vec![];
+        let mut to_fetch = vec![];
+
+        for opml_feed in feeds {
+            let title = opml_feed
+                .title
+                .clone()
+                .unwrap_or_else(|| opml_feed.url.clone());
+
+            let mut feed = FeedItem {
+                slug: slugify(&title),
+                title,
+                description: opml_feed.description.clone().unwrap_or_default(),
+                feed_url: opml_feed.url.clone(),
+                url: opml_feed.html_url.clone().unwrap_or_default(),
+                category: opml_feed
+                    .category
+                    .clone()
+                    .unwrap_or_else(|| String::from(defs::DATA_CATEGORY_DEFAULT)),
+                ..Default::default()
+            };
+            opml_feed.settings.apply(&mut feed);
+
+            // Imported files may come from anyone, so they mustn't run commands or read files
+            if !allow_local && !FeedSource::is_web_url(&feed.feed_url) {
+                results.push(ImportResult {
+                    feed,
+                    status: ImportStatus::Failed,
+                    error: Some(String::from(
+                        "Not a web address; local files and commands need --allow-local",
+                    )),
+                });
+                continue;
+            }
+
+            if let Some(existing) = known.get(&normalize_feed_url(&feed.feed_url)) {
+                results.push(ImportResult {
+                    feed: existing.clone(),
+                    status: ImportStatus::Exists,
+                    error: None,
+                });
+                continue;
+            }
+            known.insert(normalize_feed_url(&feed.feed_url), feed.clone());
+
+            let status = if dry_run {
+                ImportStatus::New
+            } else {
+                to_fetch.push(results.len());
+                ImportStatus::Pending
+            };
+
+            results.push(ImportResult {
+                feed,
+                status,
+                error: None,
+            });
+        }
+
+        let urls: Vec<&str> = to_fetch
+            .iter()
+            .map(|i| results[*i].feed.feed_url.as_str())
+            .collect();
+        let fetched = self.fetch_feeds(&urls);
+
+        for (i, fetched) in to_fetch.into_iter().zip(fetched) {
+            let result = &mut results[i];
+
+            let added = match fetched {
+                Ok((mut feed, text)) => {
+                    if feed.title.trim().is_empty() || feeds[i].title.is_some() {
+                        feed.title = result.feed.title.clone();
+                        feed.slug = result.feed.slug.clone();
+                    }
+                    if feed.url.is_empty() {
+                        feed.url = result.feed.url.clone();
+                    }
+                    feed.category = result.feed.category.clone();
+                    feed.full_content = result.feed.full_content;
+                    feed.update_interval_mins = result.feed.update_interval_mins;
+                    feed.assets = result.feed.assets.clone();
+
+                    self.add_feed(feed, Some(text)).map(|feed| {
+                        result.feed = feed;
+                        ImportStatus::Added
+                    })
+                }
+                Err(e) => {
+                    info!("Couldn't fetch {}: {:?}", result.feed.feed_url, e);
+                    result.error = Some(e.to_string());
+                    self.add_pending_feed(&result.feed)
+                        .map(|_| ImportStatus::Pending)
+                }
+            };
+
+            match added {
+                Ok(status) => result.status = status,
+                Err(e) => {
+                    error!("{e}");
+                    result.status = ImportStatus::Failed;
+                    result.error = Some(e.to_string());
+                }
+            }
+        }
+
+        results
+    }
+
+    /// Brings the read and starred state of articles from another reader over to the entries of
+    /// the same feeds, matched by URL or title. Entries are only ever marked read or starred, and
+    /// starred articles the feeds don't have anymore are stored from the cache.
+    pub fn import_entry_states(&self, cached: &[CachedEntry]) -> color_eyre::Result<StateImport> {
+        let mut summary = StateImport::default();
+
+        for category in self.feedcategories.iter() {
+            for feed in category.feeds.iter() {
+                let feed_url = normalize_feed_url(&feed.feed_url);
+                let items: Vec<&CachedEntry> = cached
+                    .iter()
+                    .filter(|c| normalize_feed_url(&c.feed_url) == feed_url)
+                    .collect();
+
+                if items.is_empty() {
+                    continue;
+                }
+
+                let entries = self.data.load_feed_entries(category, feed)?;
+
+                for item in items {
+                    let existing = entries.iter().find(|e| {
+                        (!item.url.is_empty() && e.url == item.url) || e.title == item.title
+                    });
+
+                    match existing {
+                        Some(entry) => {
+                            let mut changed = entry.clone();
+                            changed.seen |= !item.unread;
+                            changed.starred |= item.flagged;
+
+                            if changed.seen != entry.seen || changed.starred != entry.starred {
+                                self.data.save_feed_entry(&changed)?;
+                                summary.read += usize::from(changed.seen != entry.seen);
+                                summary.starred += usize::from(changed.starred != entry.starred);
+                            }
+                        }
+                        None if item.flagged => {
+                            let path =
+                                self.data
+                                    .entry_path(&category.title, &feed.slug, &item.title);
+                            if !path.exists() {
+                                self.data.save_feed_entry(&item.to_entry(path))?;
+                                summary.restored += 1;
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Registers a feed without its entries, so the next update fetches them
+    fn add_pending_feed(&self, feed: &FeedItem) -> color_eyre::Result<()> {
+        if self.data.feed_exists(&feed.slug, &feed.category) {
+            return Err(eyre!("Feed {:?} already exists", feed.title));
+        }
+
+        self.data.feed_create(feed)
+    }
+
+    fn fetch_feeds(&self, urls: &[&str]) -> Vec<color_eyre::Result<(FeedItem, String)>> {
+        let fetcher = self.fetcher.as_ref();
+        let next = AtomicUsize::new(0);
+        let fetched = Mutex::new(urls.iter().map(|_| None).collect::<Vec<_>>());
+
+        thread::scope(|scope| {
+            for _ in 0..IMPORT_WORKERS.min(urls.len()) {
+                scope.spawn(|| {
+                    loop {
+                        let i = next.fetch_add(1, Relaxed);
+                        let Some(url) = urls.get(i) else {
+                            break;
+                        };
+
+                        let result = feed::feedparser::get_feed_with_data(fetcher, url, None);
+                        fetched.lock().unwrap()[i] = Some(result);
+                    }
+                });
+            }
+        });
+
+        fetched
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(eyre!("Feed wasn't fetched"))))
+            .collect()
+    }
+
     pub fn delete_feed(&self, slug: &str, category: &str) -> color_eyre::Result<()> {
         self.data.delete_feed(slug, category)
     }
@@ -124,7 +513,7 @@ impl FeedLibrary {
             }
         }
 
-        entries.sort_by(|a, b| b.date.cmp(&a.date));
+        entries.sort_by_key(|e| std::cmp::Reverse(e.date));
         Ok(entries)
     }
 
@@ -132,10 +521,7 @@ impl FeedLibrary {
         for category in self.feedcategories.iter() {
             for feed in category.feeds.iter() {
                 if feed.slug == slug {
-                    let mut entries = self.data.load_feed_entries(category, feed)?;
-
-                    entries.sort_by(|a, b| b.date.cmp(&a.date));
-                    return Ok(entries);
+                    return self.get_feed_entries(category, feed);
                 }
             }
         }
@@ -143,25 +529,130 @@ impl FeedLibrary {
         Ok(vec![])
     }
 
+    /// Entries of a feed in a category, newest first
+    pub fn get_feed_entries(
+        &self,
+        category: &FeedCategory,
+        feed: &FeedItem,
+    ) -> color_eyre::Result<Vec<FeedEntry>> {
+        let mut entries = self.data.load_feed_entries(category, feed)?;
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.date));
+        Ok(entries)
+    }
+
+    /// Entries of every feed matching `filter`, newest first
+    pub fn get_entries(&self, filter: &EntryFilter) -> color_eyre::Result<Vec<FeedEntry>> {
+        let mut entries = vec![];
+        let read_later: Option<HashSet<PathBuf>> = match filter.read_later {
+            true => Some(self.data.read_later_paths()?.into_iter().collect()),
+            false => None,
+        };
+
+        for category in self.feedcategories.iter() {
+            for feed in category.feeds.iter().filter(|f| filter.matches_feed(f)) {
+                entries.extend(
+                    self.data
+                        .load_feed_entries(category, feed)?
+                        .into_iter()
+                        .filter(|e| filter.matches_entry(e))
+                        .filter(|e| read_later.as_ref().is_none_or(|l| l.contains(&e.filepath))),
+                );
+            }
+        }
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.date));
+        Ok(entries)
+    }
+
+    /// Entries identified by `ident`: the path of their file, absolute or relative to the library
+    /// or its categories directory, or their URL
+    pub fn find_entries(&self, ident: &str) -> color_eyre::Result<Vec<FeedEntry>> {
+        let path = Path::new(ident);
+        let candidates = [
+            path.to_path_buf(),
+            self.data.path.join(path),
+            self.data.path.join(defs::DATA_CATEGORIES_DIR).join(path),
+        ];
+
+        if let Some(path) = candidates
+            .iter()
+            .find(|p| p.is_file() && p.starts_with(&self.data.path))
+        {
+            return Ok(vec![self.data.load_feed_entry(path)?]);
+        }
+
+        Ok(self
+            .get_entries(&EntryFilter::default())?
+            .into_iter()
+            .filter(|e| e.url == ident)
+            .collect())
+    }
+
     pub fn start_updater(&mut self) {
-        self.updater = Some(Updater::new(self.feedcategories.clone()));
+        self.start_update(&UpdateScope::All);
     }
 
-    pub fn update(&mut self) {
+    /// Updates the feeds in `scope` in the background, unless an update is running already.
+    /// Returns whether it started.
+    pub fn start_update(&mut self, scope: &UpdateScope) -> bool {
+        if self.updater.is_some() {
+            return false;
+        }
+
+        // Feeds added or changed outside the TUI are picked up too
+        match self.data.generate_categories_tree() {
+            Ok(categories) => self.feedcategories = categories,
+            Err(e) => error!("Couldn't reload the library: {:?}", e),
+        }
+
+        let categories = self
+            .feedcategories
+            .iter()
+            .filter(|c| scope.category().is_none_or(|title| c.title == title))
+            .map(|c| FeedCategory {
+                title: c.title.clone(),
+                feeds: c
+                    .feeds
+                    .iter()
+                    .filter(|f| scope.slug().is_none_or(|slug| f.slug == slug))
+                    .cloned()
+                    .collect(),
+            })
+            .collect();
+
+        self.updater = Some(Updater::new(
+            categories,
+            self.data.path.clone(),
+            self.fetcher.clone(),
+            self.settings.hooks.clone(),
+        ));
+
+        true
+    }
+
+    /// Checks on the background update. Once it's done, the library is reloaded so new feeds
+    /// and changed ones show up, and true is returned.
+    pub fn update(&mut self) -> bool {
         if let Some(updater) = self.updater.as_ref()
             && updater.finished.load(std::sync::atomic::Ordering::Relaxed)
         {
             self.updater = None;
+
+            match self.data.generate_categories_tree() {
+                Ok(categories) => self.feedcategories = categories,
+                Err(e) => error!("Couldn't reload the library: {:?}", e),
+            }
+
+            return true;
         }
+
+        false
     }
 
     pub fn get_update_status(&self) -> AppWorkStatus {
         if let Some(updater) = self.updater.as_ref() {
-            let total: f32 = self
-                .feedcategories
-                .iter()
-                .map(|cat| cat.feeds.len() as f32)
-                .sum();
+            let total = updater.total.max(1) as f32;
 
             AppWorkStatus::Working(
                 1.0_f32.min(
@@ -201,6 +692,32 @@ impl FeedLibrary {
         matching_vec
     }
 
+    /// Feeds whose slug, title or feed URL is `ident`, ignoring case for titles
+    pub fn get_exact_feeds(&self, ident: &str) -> Vec<&FeedItem> {
+        self.feedcategories
+            .iter()
+            .flat_map(|c| c.feeds.iter())
+            .filter(|feed| {
+                feed.slug == ident
+                    || feed.feed_url == ident
+                    || feed.title.to_lowercase() == ident.to_lowercase()
+            })
+            .collect()
+    }
+
+    /// The feed `entry` belongs to, found from the directory it is stored in
+    pub fn get_entry_feed(&self, entry: &FeedEntry) -> Option<&FeedItem> {
+        let feed_dir = entry.filepath.parent()?;
+        let slug = feed_dir.file_name()?.to_str()?;
+        let category = feed_dir.parent()?.file_name()?.to_str()?;
+
+        self.feedcategories
+            .iter()
+            .filter(|c| c.title == category)
+            .flat_map(|c| c.feeds.iter())
+            .find(|f| f.slug == slug)
+    }
+
     pub fn add_to_read_later(&mut self, entry: &FeedEntry) -> color_eyre::Result<()> {
         self.data.add_to_read_later(entry)
     }
@@ -231,9 +748,32 @@ impl FeedLibrary {
     }
 }
 
+/// Feed URL compared when looking for duplicates
+/// Only the scheme and host are case insensitive, so paths and queries are left alone
+fn normalize_feed_url(url: &str) -> String {
+    let url = url.trim();
+    Url::parse(url)
+        .map_or_else(|_| url.to_string(), |url| url.to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::core::library::feedlibrary::FeedLibrary;
+    use std::{collections::HashMap, sync::Arc};
+
+    use crate::core::{
+        feed::fetcher::fixture::FixtureFetcher,
+        library::{
+            data::newsboat::CachedEntry,
+            data::opml::OpmlFeed,
+            feeditem::{Auth, FeedItem, HttpOptions, Secret},
+            feedlibrary::{
+                EntryFilter, FeedLibrary, ImportStatus, StateImport, UpdateScope,
+                normalize_feed_url,
+            },
+        },
+    };
 
     #[test]
     fn test_add_and_delete_feed() {
@@ -387,4 +927,768 @@ mod tests {
             "Category should be different for both the feeds."
         );
     }
+
+    fn rss(items: &[&str]) -> String {
+        let items: String = items
+            .iter()
+            .map(|title| {
+                format!(
+                    "<item><title>{title}</title><link>https://example.org/{title}</link></item>"
+                )
+            })
+            .collect();
+
+        format!(
+            "<rss version=\"2.0\"><channel><title>Example</title><link>https://example.org/</link>{items}</channel></rss>"
+        )
+    }
+
+    fn fixture_library() -> (FeedLibrary, Arc<FixtureFetcher>, tempfile::TempDir) {
+        let (mut library, temp_dir) = FeedLibrary::new_for_test();
+        let fetcher = Arc::new(FixtureFetcher::default());
+        library.fetcher = fetcher.clone();
+        (library, fetcher, temp_dir)
+    }
+
+    /// Updates every feed, as if the last update happened long ago
+    fn update_all(library: &mut FeedLibrary) {
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        for category in library.feedcategories.iter() {
+            for feed in category.feeds.iter() {
+                let feed = FeedItem {
+                    lastupdated: chrono::DateTime::UNIX_EPOCH,
+                    ..feed.clone()
+                };
+                library
+                    .data
+                    .update_feed_entries(library.fetcher.as_ref(), &category.title, &feed, None)
+                    .unwrap();
+            }
+        }
+
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+    }
+
+    #[test]
+    fn test_add_and_update_through_fetcher() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        fetcher.body("https://example.org/feed", &rss(&["first"]));
+
+        let feed = library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+        assert_eq!(feed.title, "Example");
+
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+        let entries = library.get_feed_entries_by_item_slug("example").unwrap();
+        assert_eq!(entries.len(), 1);
+
+        fetcher.body("https://example.org/feed", &rss(&["second", "first"]));
+        update_all(&mut library);
+
+        let entries = library.get_feed_entries_by_item_slug("example").unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            fetcher.requests(),
+            vec!["https://example.org/feed", "https://example.org/feed"]
+        );
+    }
+
+    #[test]
+    fn test_feed_entries_with_same_slug_in_two_categories() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        fetcher.body("https://example.org/feed", &rss(&["first"]));
+        fetcher.body("https://example.org/other", &rss(&["second"]));
+
+        library
+            .add_feed_from_url("https://example.org/feed", &Some("A".to_string()), None)
+            .unwrap();
+        library
+            .add_feed_from_url("https://example.org/other", &Some("B".to_string()), None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        for category in library.feedcategories.iter() {
+            let feed = &category.feeds[0];
+            assert_eq!(feed.slug, "example");
+
+            let entries = library.get_feed_entries(category, feed).unwrap();
+            let expected = if category.title == "A" {
+                "first"
+            } else {
+                "second"
+            };
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].title, expected);
+        }
+    }
+
+    #[test]
+    fn test_start_update_with_scope() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        for (category, slug) in [("News", "daily"), ("News", "weekly"), ("Blogs", "mine")] {
+            fetcher.body(&format!("https://example.org/{slug}"), &rss(&[slug]));
+            library
+                .data
+                .feed_create(&FeedItem {
+                    title: slug.to_string(),
+                    slug: slug.to_string(),
+                    feed_url: format!("https://example.org/{slug}"),
+                    category: category.to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let wait = |library: &mut FeedLibrary| {
+            for _ in 0..500 {
+                if library.update() {
+                    return;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            panic!("update didn't finish");
+        };
+
+        // Feeds created outside the library are found when the update starts
+        assert!(library.start_update(&UpdateScope::Feed("News".into(), "weekly".into())));
+        assert!(!library.start_update(&UpdateScope::All));
+        wait(&mut library);
+        assert_eq!(fetcher.requests(), vec!["https://example.org/weekly"]);
+        assert_eq!(
+            library
+                .get_feed_entries_by_item_slug("weekly")
+                .unwrap()
+                .len(),
+            1
+        );
+
+        assert!(library.start_update(&UpdateScope::Category("News".into())));
+        wait(&mut library);
+        assert_eq!(
+            fetcher.requests(),
+            vec!["https://example.org/weekly", "https://example.org/daily"]
+        );
+    }
+
+    #[test]
+    fn test_rules_apply_to_new_entries() {
+        let (mut library, fetcher, temp_dir) = fixture_library();
+        std::fs::write(
+            temp_dir.path().join(crate::core::defs::DATA_RULES),
+            r#"
+[[rule]]
+title = "/^sponsored/i"
+action = "drop"
+
+[[rule]]
+title = "release"
+action = "read"
+
+[[rule]]
+title = "release"
+action = "read_later"
+"#,
+        )
+        .unwrap();
+
+        fetcher.body(
+            "https://example.org/feed",
+            &rss(&["Sponsored-post", "release", "news"]),
+        );
+        library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+        let entries = library.get_feed_entries_by_item_slug("example").unwrap();
+        let titles: Vec<(&str, bool)> =
+            entries.iter().map(|e| (e.title.as_str(), e.seen)).collect();
+        assert_eq!(titles.len(), 2);
+        assert!(titles.contains(&("release", true)));
+        assert!(titles.contains(&("news", false)));
+
+        let later = library.get_read_later_feed_entries().unwrap();
+        assert_eq!(later.len(), 1);
+        assert_eq!(later[0].title, "release");
+    }
+
+    #[test]
+    fn test_filter_and_find_entries() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        fetcher
+            .body("https://example.org/feed", &rss(&["first", "second"]))
+            .body("https://example.org/other", &rss(&["third"]));
+        library
+            .add_feed_from_url("https://example.org/feed", &Some("News".into()), None)
+            .unwrap();
+        library
+            .add_feed_from_url("https://example.org/other", &Some("Blogs".into()), None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        assert_eq!(
+            library.get_entries(&EntryFilter::default()).unwrap().len(),
+            3
+        );
+
+        let news = EntryFilter {
+            category: Some("news".into()),
+            ..Default::default()
+        };
+        assert_eq!(library.get_entries(&news).unwrap().len(), 2);
+
+        // Found by URL, and by path relative to the library
+        let first = library.find_entries("https://example.org/first").unwrap();
+        assert_eq!(first.len(), 1);
+        library.data.set_entry_seen(&first[0]).unwrap();
+
+        let relative = first[0].filepath.strip_prefix(&library.data.path).unwrap();
+        let found = library.find_entries(relative.to_str().unwrap()).unwrap();
+        assert_eq!(found[0].title, "first");
+        assert!(found[0].seen);
+
+        let unread = EntryFilter {
+            unread: true,
+            ..news
+        };
+        let unread: Vec<String> = library
+            .get_entries(&unread)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.title)
+            .collect();
+        assert_eq!(unread, vec!["second"]);
+
+        library.data.set_entry_unseen(&found[0]).unwrap();
+        assert!(!library.find_entries("https://example.org/first").unwrap()[0].seen);
+
+        let mut second = library.find_entries("https://example.org/second").unwrap();
+        library.add_to_read_later(&second[0]).unwrap();
+        second[0].starred = true;
+        library.data.save_feed_entry(&second[0]).unwrap();
+
+        let read_later = EntryFilter {
+            read_later: true,
+            ..Default::default()
+        };
+        let starred = EntryFilter {
+            starred: true,
+            ..Default::default()
+        };
+        for filter in [read_later, starred] {
+            let entries = library.get_entries(&filter).unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].title, "second");
+        }
+
+        assert!(library.find_entries("nothing/here.md").unwrap().is_empty());
+        assert!(library.find_entries("/etc/hostname").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_storage_date() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        fetcher.body("https://example.org/feed", &rss(&["backdated", "old"]));
+        library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        let now = chrono::Utc::now();
+        let long_ago = now - chrono::Duration::days(30);
+
+        // Published long ago, but only just fetched
+        let mut backdated = library
+            .find_entries("https://example.org/backdated")
+            .unwrap();
+        backdated[0].date = long_ago;
+        library.data.save_feed_entry(&backdated[0]).unwrap();
+
+        // Published now, but stored long ago
+        let mut old = library.find_entries("https://example.org/old").unwrap();
+        old[0].date = now;
+        old[0].lastupdated = long_ago;
+        library.data.save_feed_entry(&old[0]).unwrap();
+
+        let titles = |filter: EntryFilter| -> Vec<String> {
+            library
+                .get_entries(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|e| e.title)
+                .collect()
+        };
+        let since = now - chrono::Duration::hours(1);
+
+        assert_eq!(
+            titles(EntryFilter {
+                stored_since: Some(since),
+                ..Default::default()
+            }),
+            vec!["backdated"]
+        );
+        assert_eq!(
+            titles(EntryFilter {
+                since: Some(since),
+                ..Default::default()
+            }),
+            vec!["old"]
+        );
+    }
+
+    #[test]
+    fn test_exact_feeds_and_delete_preview() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        fetcher
+            .body("https://example.org/feed", &rss(&["first", "second"]))
+            .body("https://example.org/other", &rss(&["third"]));
+        library
+            .add_feed_from_url("https://example.org/feed", &Some("News".into()), None)
+            .unwrap();
+        library
+            .add_feed_from_url("https://example.org/other", &Some("Blogs".into()), None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        assert_eq!(library.get_exact_feeds("example").len(), 2);
+        assert_eq!(library.get_exact_feeds("EXAMPLE").len(), 2);
+        assert_eq!(
+            library.get_exact_feeds("https://example.org/other").len(),
+            1
+        );
+        assert!(library.get_exact_feeds("exampl").is_empty());
+
+        let first = library.find_entries("https://example.org/first").unwrap();
+        library.add_to_read_later(&first[0]).unwrap();
+
+        assert_eq!(
+            library.data.count_feed_entries("News", "example").unwrap(),
+            2
+        );
+        assert_eq!(
+            library.data.feed_read_later("News", "example").unwrap(),
+            vec!["News/example/first.md"]
+        );
+        assert!(
+            library
+                .data
+                .feed_read_later("Blogs", "example")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_local_sources() {
+        let (mut library, fetcher, temp_dir) = fixture_library();
+
+        let feed_file = temp_dir.path().join("local.xml");
+        std::fs::write(&feed_file, rss(&["local"])).unwrap();
+        let marker = temp_dir.path().join("ran");
+
+        let opml_feed = |url: String| OpmlFeed {
+            url,
+            category: None,
+            title: None,
+            html_url: None,
+            description: None,
+            settings: Default::default(),
+        };
+        let feeds = vec![
+            opml_feed(format!("exec:touch {}", marker.display())),
+            opml_feed(feed_file.to_string_lossy().to_string()),
+            opml_feed(format!("file://{}", feed_file.display())),
+        ];
+
+        let results = library.import_feeds(&feeds, false, false);
+        assert!(
+            results
+                .iter()
+                .all(|r| r.status == ImportStatus::Failed && r.error.is_some())
+        );
+        assert!(!marker.exists());
+        assert!(fetcher.requests().is_empty());
+        assert!(library.data.generate_categories_tree().unwrap().is_empty());
+
+        // Unless the user allows them
+        let results = library.import_feeds(&feeds[1..2], false, true);
+        assert_eq!(results[0].status, ImportStatus::Added);
+        // Without a title in the file, the feed's own is used
+        assert_eq!(results[0].feed.title, "Example");
+    }
+
+    #[test]
+    fn test_import_feeds() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        fetcher
+            .body("https://example.org/feed", &rss(&["first"]))
+            .body("https://example.org/new", &rss(&["second"]))
+            .status("https://example.org/down", 503);
+        library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        let opml_feed = |url: &str, title: &str| OpmlFeed {
+            url: url.to_string(),
+            category: Some("Imported".to_string()),
+            title: Some(title.to_string()),
+            html_url: Some("https://example.org/".to_string()),
+            description: None,
+            settings: Default::default(),
+        };
+        let feeds = vec![
+            opml_feed("HTTPS://Example.ORG/feed/", "Already here"),
+            opml_feed("https://example.org/new", "New one"),
+            opml_feed("https://example.org/down", "Down for now"),
+            opml_feed("https://example.org/new", "Twice in the file"),
+        ];
+
+        let statuses = |results: Vec<crate::core::library::feedlibrary::ImportResult>| {
+            results.into_iter().map(|r| r.status).collect::<Vec<_>>()
+        };
+
+        // A dry run doesn't fetch anything
+        let requests = fetcher.requests().len();
+        assert_eq!(
+            statuses(library.import_feeds(&feeds, true, false)),
+            vec![
+                ImportStatus::Exists,
+                ImportStatus::New,
+                ImportStatus::New,
+                ImportStatus::Exists
+            ]
+        );
+        assert_eq!(fetcher.requests().len(), requests);
+
+        let results = library.import_feeds(&feeds, false, false);
+        assert_eq!(results[2].feed.title, "Down for now");
+        assert!(results[2].error.is_some());
+        assert_eq!(
+            statuses(results),
+            vec![
+                ImportStatus::Exists,
+                ImportStatus::Added,
+                ImportStatus::Pending,
+                ImportStatus::Exists
+            ]
+        );
+
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+        let imported: Vec<(String, String)> = library
+            .feedcategories
+            .iter()
+            .filter(|c| c.title == "Imported")
+            .flat_map(|c| c.feeds.iter())
+            .map(|f| (f.slug.clone(), f.url.clone()))
+            .collect();
+        assert_eq!(imported.len(), 2);
+        // The title from the file wins over the one the feed has
+        assert!(imported.contains(&("new-one".into(), "https://example.org/".into())));
+        assert!(imported.contains(&("down-for-now".into(), "https://example.org/".into())));
+
+        // The pending feed has no entries until an update reaches it
+        assert!(
+            library
+                .get_feed_entries_by_item_slug("down-for-now")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_normalize_feed_url() {
+        assert_eq!(
+            normalize_feed_url(" HTTPS://Example.ORG/Feed.xml?Key=A/ "),
+            "https://example.org/Feed.xml?Key=A"
+        );
+        assert_ne!(
+            normalize_feed_url("https://example.org/Feed"),
+            normalize_feed_url("https://example.org/feed")
+        );
+        assert_eq!(normalize_feed_url("exec:Get-Feed "), "exec:Get-Feed");
+    }
+
+    #[test]
+    fn test_export_then_import_reproduces_library() {
+        let (mut library, fetcher, temp_dir) = fixture_library();
+
+        fetcher
+            .body("https://example.org/rss", &rss(&["first"]))
+            .body(
+                "https://example.org/atom",
+                r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Atom &amp; Co</title><link href="https://example.org/blog"/><entry><title>second</title><link href="https://example.org/second"/></entry></feed>"#,
+            );
+        // Renamed by the user, so its title isn't the one the feed has
+        let mut rss_feed = library
+            .add_feed_from_url("https://example.org/rss", &Some("News".into()), None)
+            .unwrap();
+        rss_feed.title = String::from("Morning News");
+        library.data.feed_create(&rss_feed).unwrap();
+        let mut atom = library
+            .add_feed_from_url("https://example.org/atom", &Some("Blogs".into()), None)
+            .unwrap();
+        atom.full_content = true;
+        atom.update_interval_mins = Some(15);
+        library.data.feed_create(&atom).unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        let path = temp_dir.path().join("export.opml");
+        let path = path.to_str().unwrap();
+        crate::core::library::data::opml::save_opml(&library.feedcategories, path, true).unwrap();
+
+        let (mut imported, _other_dir) = FeedLibrary::new_for_test();
+        imported.fetcher = fetcher.clone();
+        let feeds = crate::core::library::data::opml::get_opml_feeds(path).unwrap();
+        let results = imported.import_feeds(&feeds, false, false);
+        assert!(results.iter().all(|r| r.status == ImportStatus::Added));
+
+        let summary = |library: &FeedLibrary| {
+            let mut feeds: Vec<_> = library
+                .data
+                .generate_categories_tree()
+                .unwrap()
+                .into_iter()
+                .flat_map(|c| c.feeds)
+                .map(|f| {
+                    (
+                        f.category,
+                        f.slug,
+                        f.title,
+                        f.url,
+                        f.feed_url,
+                        f.format,
+                        f.full_content,
+                        f.update_interval_mins,
+                    )
+                })
+                .collect();
+            feeds.sort();
+            feeds
+        };
+
+        assert_eq!(summary(&imported), summary(&library));
+        assert_eq!(summary(&library)[0].5.as_deref(), Some("atom"));
+        assert_eq!(summary(&imported)[1].2, "Morning News");
+    }
+
+    #[test]
+    fn test_import_entry_states() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+
+        fetcher.body(
+            "https://example.org/feed",
+            &rss(&["first", "second", "third"]),
+        );
+        library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+
+        let cached = |title: &str, unread: bool, flagged: bool| CachedEntry {
+            feed_url: "https://example.org/feed".to_string(),
+            url: format!("https://example.org/{title}"),
+            title: title.to_string(),
+            content: "<p>Old <i>news</i></p>".to_string(),
+            unread,
+            flagged,
+            ..Default::default()
+        };
+
+        let cache = vec![
+            cached("first", false, false),
+            cached("second", true, true),
+            cached("gone", true, false),
+            cached("starred-and-gone", false, true),
+            CachedEntry {
+                feed_url: "https://example.org/elsewhere".to_string(),
+                ..cached("third", false, true)
+            },
+        ];
+
+        assert_eq!(
+            library.import_entry_states(&cache).unwrap(),
+            StateImport {
+                read: 1,
+                starred: 1,
+                restored: 1
+            }
+        );
+
+        let entries = library.get_feed_entries_by_item_slug("example").unwrap();
+        let state = |title: &str| {
+            let entry = entries.iter().find(|e| e.title == title).unwrap();
+            (entry.seen, entry.starred)
+        };
+        assert_eq!(entries.len(), 4);
+        assert_eq!(state("first"), (true, false));
+        assert_eq!(state("second"), (false, true));
+        assert_eq!(state("third"), (false, false));
+        assert_eq!(state("starred-and-gone"), (true, true));
+
+        // Importing again changes nothing
+        assert_eq!(
+            library.import_entry_states(&cache).unwrap(),
+            StateImport::default()
+        );
+    }
+
+    #[test]
+    fn test_full_content_keeps_credentials_on_the_feed_host() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        let channel = |items: &str| {
+            format!("<rss version=\"2.0\"><channel><title>Private</title>{items}</channel></rss>")
+        };
+        let article = "<html><body><article><p>A long enough paragraph, with commas, to be picked as the content.</p></article></body></html>";
+
+        fetcher
+            .body("https://private.example.org/feed", &channel(""))
+            .body("https://private.example.org/own", article)
+            .body("https://elsewhere.example.com/linked", article);
+
+        let http = HttpOptions {
+            auth: Some(Auth::Bearer {
+                token: Secret("token".to_string()),
+            }),
+            ..Default::default()
+        };
+        let feed = library
+            .add_feed_from_url("https://private.example.org/feed", &None, Some(http))
+            .unwrap();
+
+        fetcher.body(
+            "https://private.example.org/feed",
+            &channel(
+                "<item><title>Own</title><link>https://private.example.org/own</link></item>\
+                 <item><title>Linked</title><link>https://elsewhere.example.com/linked</link></item>",
+            ),
+        );
+        let feed = FeedItem {
+            full_content: true,
+            lastupdated: chrono::DateTime::UNIX_EPOCH,
+            ..feed
+        };
+        library
+            .data
+            .update_feed_entries(library.fetcher.as_ref(), &feed.category, &feed, None)
+            .unwrap();
+
+        let sent: HashMap<String, bool> = fetcher
+            .requests()
+            .into_iter()
+            .zip(fetcher.requests_with_options())
+            .collect();
+        assert!(sent["https://private.example.org/feed"]);
+        assert!(sent["https://private.example.org/own"]);
+        assert!(!sent["https://elsewhere.example.com/linked"]);
+    }
+
+    #[test]
+    fn test_feeds_follow_permanent_redirects() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        fetcher
+            .redirect("https://example.org/rss", 301, "/feed.xml")
+            .file("https://example.org/feed.xml", "rss20_wordpress.xml");
+
+        let feed = library
+            .add_feed_from_url("https://example.org/rss", &None, None)
+            .unwrap();
+        assert_eq!(feed.feed_url, "https://example.org/feed.xml");
+
+        // A feed that moves later is updated from, and stored with, its new address
+        fetcher
+            .redirect(
+                "https://example.org/feed.xml",
+                308,
+                "https://example.com/feed.xml",
+            )
+            .file("https://example.com/feed.xml", "rss20_wordpress.xml");
+        update_all(&mut library);
+
+        let feeds = &library.feedcategories[0].feeds;
+        assert_eq!(feeds[0].feed_url, "https://example.com/feed.xml");
+
+        // Temporary redirects don't change it
+        fetcher
+            .redirect(
+                "https://example.com/feed.xml",
+                302,
+                "https://mirror.example.com/feed.xml",
+            )
+            .file("https://mirror.example.com/feed.xml", "rss20_wordpress.xml");
+        update_all(&mut library);
+
+        let feeds = &library.feedcategories[0].feeds;
+        assert_eq!(feeds[0].feed_url, "https://example.com/feed.xml");
+    }
+
+    #[test]
+    fn test_add_feed_errors() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        fetcher
+            .status("https://example.org/gone", 410)
+            .error("https://example.org/down", "Connection refused")
+            .body(
+                "https://example.org/page",
+                "<html><body>No feeds here</body></html>",
+            )
+            .body("https://example.org/broken", "<rss><channel>");
+
+        for url in [
+            "https://example.org/gone",
+            "https://example.org/down",
+            "https://example.org/page",
+            "https://example.org/broken",
+            "https://example.org/unknown",
+        ] {
+            assert!(
+                library.add_feed_from_url(url, &None, None).is_err(),
+                "{url}"
+            );
+        }
+
+        assert!(
+            library
+                .data
+                .generate_categories_tree()
+                .unwrap()
+                .iter()
+                .all(|c| c.feeds.is_empty())
+        );
+    }
+
+    #[test]
+    fn test_update_keeps_feed_when_fetch_fails() {
+        let (mut library, fetcher, _temp_dir) = fixture_library();
+        fetcher.body("https://example.org/feed", &rss(&["first"]));
+        library
+            .add_feed_from_url("https://example.org/feed", &None, None)
+            .unwrap();
+
+        fetcher.status("https://example.org/feed", 503);
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+        let category = &library.feedcategories[0];
+        let feed = FeedItem {
+            lastupdated: chrono::DateTime::UNIX_EPOCH,
+            ..category.feeds[0].clone()
+        };
+
+        let result = library.data.update_feed_entries(
+            library.fetcher.as_ref(),
+            &category.title,
+            &feed,
+            None,
+        );
+        assert!(result.unwrap_err().to_string().contains("503"));
+
+        let entries = library.get_feed_entries_by_item_slug("example").unwrap();
+        assert_eq!(entries.len(), 1);
+    }
 }
@@ -1,4 +1,7 @@
+pub mod daemon;
 pub mod data;
+pub mod digest;
+pub mod export;
 pub mod feedcategory;
 pub mod feeditem;
 pub mod feedlibrary;
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{error, info};
+
+use crate::core::feed::{feedentry::EntrySummary, feedutils};
+
+const HOOKS_PATH: &str = ".hooks.toml";
+
+/// How often a hook runs
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookRun {
+    /// Once after each update, with every new entry
+    #[default]
+    Update,
+    /// Once for each new entry
+    Entry,
+}
+
+/// A command run after updates that brought new entries. It gets them as JSON on its standard
+/// input, and runs from the library directory.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub run: HookRun,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Hooks {
+    #[serde(default, rename = "hook")]
+    pub hooks: Vec<Hook>,
+
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Hooks {
+    pub fn new(datapath: &Path) -> color_eyre::Result<Self> {
+        let path = datapath.join(HOOKS_PATH);
+
+        if !path.exists() {
+            let mut hooks: Self = toml::from_str("")?;
+            hooks.path = path.clone();
+            return Ok(hooks);
+        }
+
+        let data = fs::read_to_string(&path)?;
+        let mut hooks: Hooks = match toml::from_str(&data) {
+            Ok(h) => h,
+            Err(e) => {
+                error!("Error parsing {path:?}: {e:?}");
+                toml::from_str("")?
+            }
+        };
+
+        hooks.path = path.clone();
+        Ok(hooks)
+    }
+
+    /// Runs the hooks for the entries stored by an update. Failing hooks are logged and don't
+    /// stop the others.
+    pub fn run(&self, entries: &[EntrySummary]) {
+        if entries.is_empty() || self.hooks.is_empty() {
+            return;
+        }
+
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+
+        for hook in self.hooks.iter() {
+            let inputs = match hook.run {
+                HookRun::Update => vec![serde_json::to_vec(entries)],
+                HookRun::Entry => entries.iter().map(serde_json::to_vec).collect(),
+            };
+
+            for input in inputs {
+                let result = input.map_err(color_eyre::Report::from).and_then(|input| {
+                    feedutils::run_command_with_input(&hook.command, &input, dir)
+                });
+
+                match result {
+                    Ok(_) => info!("Ran hook \"{}\"", hook.command),
+                    Err(e) => error!("Hook failed: {:?}", e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{feed::feedentry::FeedEntry, library::feeditem::FeedItem};
+    use tempfile::TempDir;
+
+    fn entry(datapath: &Path, title: &str) -> EntrySummary {
+        let feed = FeedItem {
+            title: "Example".to_string(),
+            slug: "example".to_string(),
+            category: "General".to_string(),
+            ..Default::default()
+        };
+        let entry = FeedEntry {
+            title: title.to_string(),
+            filepath: datapath
+                .join("categories/General/example")
+                .join(format!("{title}.md")),
+            ..Default::default()
+        };
+
+        EntrySummary::new(datapath, &feed, &entry)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join(HOOKS_PATH),
+            r#"
+[[hook]]
+command = "cat >> update.json; echo >> update.json"
+
+[[hook]]
+command = "cat >> entries.json; echo >> entries.json"
+run = "entry"
+
+[[hook]]
+command = "exit 1"
+"#,
+        )
+        .unwrap();
+
+        let hooks = Hooks::new(temp_dir.path()).unwrap();
+        assert_eq!(hooks.hooks.len(), 3);
+        assert_eq!(hooks.hooks[1].run, HookRun::Entry);
+
+        // Nothing runs without new entries
+        hooks.run(&[]);
+        assert!(!temp_dir.path().join("update.json").exists());
+
+        let entries = vec![
+            entry(temp_dir.path(), "first"),
+            entry(temp_dir.path(), "second"),
+        ];
+        hooks.run(&entries);
+
+        let update = fs::read_to_string(temp_dir.path().join("update.json")).unwrap();
+        let update: serde_json::Value = serde_json::from_str(&update).unwrap();
+        assert_eq!(update[1]["title"], "second");
+        assert_eq!(update[0]["feed_slug"], "example");
+        assert_eq!(update[0]["path"], "categories/General/example/first.md");
+
+        let per_entry = fs::read_to_string(temp_dir.path().join("entries.json")).unwrap();
+        let titles: Vec<String> = per_entry
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["title"].to_string()
+            })
+            .collect();
+        assert_eq!(titles, vec!["\"first\"", "\"second\""]);
+    }
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use tracing::error;
+
+const MEDIA_PATH: &str = ".media.toml";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Media {
+    /// Command used to play enclosures, e.g. "mpv --no-video". The file or URL is appended to
+    /// it. When unset, the system's default application is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player: Option<String>,
+
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Media {
+    pub fn new(datapath: &Path) -> color_eyre::Result<Self> {
+        let path = datapath.join(MEDIA_PATH);
+
+        if !path.exists() {
+            let mut media: Self = toml::from_str("")?;
+            media.path = path.clone();
+            return Ok(media);
+        }
+
+        let data = fs::read_to_string(&path)?;
+        let mut media: Media = match toml::from_str(&data) {
+            Ok(m) => m,
+            Err(e) => {
+                error!("Error parsing {path:?}: {e:?}");
+                toml::from_str("")?
+            }
+        };
+
+        media.path = path.clone();
+        Ok(media)
+    }
+
+    pub fn save(&mut self) -> color_eyre::Result<()> {
+        let toml_string = toml::to_string_pretty(self)?;
+        fs::write(&self.path, toml_string)?;
+        Ok(())
+    }
+
+    /// Plays `target`, a local file or a URL, with the configured player
+    pub fn play(&self, target: &str) -> color_eyre::Result<()> {
+        let Some(mut args) = self
+            .player
+            .as_deref()
+            .map(|p| p.split_whitespace())
+            .filter(|p| p.clone().next().is_some())
+        else {
+            open::that_detached(target)?;
+            return Ok(());
+        };
+
+        let program = args.next().unwrap_or_default();
+
+        Command::new(program)
+            .args(args)
+            .arg(target)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_media_save_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let mut media = Media::new(temp_dir.path()).unwrap();
+        assert_eq!(media.player, None);
+
+        media.player = Some("mpv --no-video".to_string());
+        media.save().unwrap();
+
+        let media = Media::new(temp_dir.path()).unwrap();
+        assert_eq!(media.player.as_deref(), Some("mpv --no-video"));
+    }
+}
@@ -1,4 +1,8 @@
 pub mod appearance;
+pub mod hooks;
+pub mod media;
+pub mod network;
 pub mod theme;
 pub mod themedata;
+pub mod tui;
 pub mod usersettings;
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::error;
+
+const NETWORK_PATH: &str = ".network.toml";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Network {
+    /// Proxy for all requests, e.g. "http://proxy:3128" or "socks5h://localhost:1080". When
+    /// unset, the usual `HTTP_PROXY`/`HTTPS_PROXY` environment variables are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// How long to wait for the server to answer, or for each read of the response
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Feeds and pages bigger than this are rejected
+    #[serde(default = "default_max_body_mb")]
+    pub max_body_mb: u64,
+    /// How many times a request is tried again after a 429 or 5xx response, or a timeout
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Delay before the first retry, doubled on each one unless the server sends `Retry-After`
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+// Defaults
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_max_body_mb() -> u64 {
+    20
+}
+
+fn default_retries() -> u32 {
+    2
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    1000
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            connect_timeout_secs: default_connect_timeout_secs(),
+            timeout_secs: default_timeout_secs(),
+            max_body_mb: default_max_body_mb(),
+            retries: default_retries(),
+            retry_backoff_ms: default_retry_backoff_ms(),
+            path: PathBuf::new(),
+        }
+    }
+}
+
+impl Network {
+    pub fn new(datapath: &Path) -> color_eyre::Result<Self> {
+        let path = datapath.join(NETWORK_PATH);
+
+        if !path.exists() {
+            let mut network: Self = toml::from_str("")?;
+            network.path = path.clone();
+            return Ok(network);
+        }
+
+        let data = fs::read_to_string(&path)?;
+        let mut network: Network = match toml::from_str(&data) {
+            Ok(n) => n,
+            Err(e) => {
+                error!("Error parsing {path:?}: {e:?}");
+                toml::from_str("")?
+            }
+        };
+
+        network.path = path.clone();
+        Ok(network)
+    }
+
+    pub fn save(&mut self) -> color_eyre::Result<()> {
+        let toml_string = toml::to_string_pretty(self)?;
+        fs::write(&self.path, toml_string)?;
+        Ok(())
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn max_body_bytes(&self) -> u64 {
+        self.max_body_mb.saturating_mul(1024 * 1024)
+    }
+
+    pub fn retry_backoff(&self) -> Duration {
+        Duration::from_millis(self.retry_backoff_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_network_defaults_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+
+        let network = Network::new(temp_dir.path()).unwrap();
+        assert_eq!(network.proxy, None);
+        assert_eq!(network.timeout(), Duration::from_secs(30));
+        assert_eq!(network.retries, 2);
+
+        fs::write(
+            temp_dir.path().join(NETWORK_PATH),
+            "proxy = \"socks5h://localhost:1080\"\nretries = 0\n",
+        )
+        .unwrap();
+
+        let network = Network::new(temp_dir.path()).unwrap();
+        assert_eq!(network.proxy.as_deref(), Some("socks5h://localhost:1080"));
+        assert_eq!(network.retries, 0);
+        assert_eq!(network.max_body_bytes(), 20 * 1024 * 1024);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use tracing::error;
+
+const TUI_PATH: &str = ".tui.toml";
+
+/// How the TUI behaves while it's open
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Tui {
+    /// Minutes between updates while the TUI is open. Feeds are only updated at startup when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_refresh_mins: Option<u64>,
+}
+
+impl Tui {
+    pub fn new(datapath: &Path) -> color_eyre::Result<Self> {
+        let path = datapath.join(TUI_PATH);
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let data = fs::read_to_string(&path)?;
+        Ok(match toml::from_str(&data) {
+            Ok(tui) => tui,
+            Err(e) => {
+                error!("Error parsing {path:?}: {e:?}");
+                Self::default()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_tui_defaults_and_load() {
+        let temp_dir = TempDir::new().unwrap();
+        assert_eq!(Tui::new(temp_dir.path()).unwrap().auto_refresh_mins, None);
+
+        fs::write(temp_dir.path().join(TUI_PATH), "auto_refresh_mins = 30\n").unwrap();
+        assert_eq!(
+            Tui::new(temp_dir.path()).unwrap().auto_refresh_mins,
+            Some(30)
+        );
+
+        // A broken file falls back to the defaults
+        fs::write(
+            temp_dir.path().join(TUI_PATH),
+            "auto_refresh_mins = \"soon\"\n",
+        )
+        .unwrap();
+        assert_eq!(Tui::new(temp_dir.path()).unwrap().auto_refresh_mins, None);
+    }
+}
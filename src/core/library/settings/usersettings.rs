@@ -1,9 +1,16 @@
 use std::{collections::HashMap, path::Path};
 
-use crate::core::library::settings::{appearance::Appearance, theme::Theme, themedata};
+use crate::core::library::settings::{
+    appearance::Appearance, hooks::Hooks, media::Media, network::Network, theme::Theme, themedata,
+    tui::Tui,
+};
 
 pub struct UserSettings {
     pub appearance: Appearance,
+    pub media: Media,
+    pub network: Network,
+    pub hooks: Hooks,
+    pub tui: Tui,
     themes: HashMap<String, Theme>,
 }
 
@@ -11,6 +18,10 @@ impl UserSettings {
     pub fn new(datapath: &Path) -> color_eyre::Result<Self> {
         Ok(Self {
             appearance: Appearance::new(datapath)?,
+            media: Media::new(datapath)?,
+            network: Network::new(datapath)?,
+            hooks: Hooks::new(datapath)?,
+            tui: Tui::new(datapath)?,
             themes: themedata::get_themes(),
         })
     }
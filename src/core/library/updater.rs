@@ -1,4 +1,5 @@
 use std::{
+    path::PathBuf,
     sync::{
         Arc, Mutex,
         atomic::{AtomicBool, AtomicU16, Ordering::Relaxed},
@@ -8,18 +9,32 @@ use std::{
 
 use tracing::{error, info};
 
-use crate::core::library::{feedcategory::FeedCategory, feedlibrary::FeedLibrary};
+use crate::core::{
+    feed::{feedentry::EntrySummary, fetcher::Fetcher},
+    library::{
+        daemon::UpdateLock, data::librarydata::LibraryData, feedcategory::FeedCategory,
+        settings::hooks::Hooks,
+    },
+};
 
 pub struct Updater {
     pub last_completed: Arc<Mutex<String>>,
     pub total_completed: Arc<AtomicU16>,
     pub finished: Arc<AtomicBool>,
+    /// How many feeds are being updated
+    pub total: u16,
 
     _thread: Option<JoinHandle<()>>,
 }
 
 impl Updater {
-    pub fn new(feedcategories: Vec<FeedCategory>) -> Self {
+    pub fn new(
+        feedcategories: Vec<FeedCategory>,
+        datapath: PathBuf,
+        fetcher: Arc<dyn Fetcher>,
+        hooks: Hooks,
+    ) -> Self {
+        let total = feedcategories.iter().map(|c| c.feeds.len() as u16).sum();
         let completed = Arc::new(Mutex::new(String::from("Working...")));
         let finished = Arc::new(AtomicBool::new(false));
         let total_completed = Arc::new(AtomicU16::new(0));
@@ -30,16 +45,36 @@ impl Updater {
 
         let handle = Some(thread::spawn(move || {
             info!("Starting updater");
-            let library = FeedLibrary::new();
+            let data = LibraryData::new(&datapath);
+
+            let _lock = match UpdateLock::acquire(&datapath) {
+                Ok(Some(lock)) => lock,
+                Ok(None) => {
+                    info!("Another update is running, skipping");
+                    finished_clone.store(true, Relaxed);
+                    return;
+                }
+                Err(e) => {
+                    error!("Couldn't lock the library for updating: {:?}", e);
+                    finished_clone.store(true, Relaxed);
+                    return;
+                }
+            };
+
+            let mut new_entries = vec![];
 
             for category in feedcategories.iter() {
                 for feed in category.feeds.iter() {
-                    if let Err(e) = library
-                        .data
-                        .update_feed_entries(&category.title, feed, None)
-                    {
-                        error!("Something happened when updating {}: {:?}", &feed.title, e);
-                        continue;
+                    match data.update_feed_entries(fetcher.as_ref(), &category.title, feed, None) {
+                        Ok(entries) => new_entries.extend(
+                            entries
+                                .iter()
+                                .map(|entry| EntrySummary::new(&datapath, feed, entry)),
+                        ),
+                        Err(e) => {
+                            error!("Something happened when updating {}: {:?}", &feed.title, e);
+                            continue;
+                        }
                     }
 
                     info!("Updated {}", &feed.title);
@@ -49,12 +84,15 @@ impl Updater {
                 }
             }
 
+            hooks.run(&new_entries);
+
             finished_clone.store(true, Relaxed);
         }));
 
         Self {
             last_completed: completed,
             total_completed,
+            total,
             _thread: handle,
             finished,
         }
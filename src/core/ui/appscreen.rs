@@ -34,6 +34,10 @@ pub trait AppScreen {
     fn handle_keypress(&mut self, key: KeyEvent) -> Result<AppScreenEvent>;
 
     fn get_work_status(&self) -> AppWorkStatus;
+    /// A notification from work that finished in the background, shown once
+    fn take_notification(&mut self) -> Option<AppNotification> {
+        None
+    }
     fn get_title(&self) -> String;
     fn get_instructions(&self) -> String;
     fn get_full_instructions(&self) -> String;
@@ -5,21 +5,24 @@ pub mod logging;
 pub mod mainui;
 pub mod ui;
 
+use std::process::ExitCode;
+
 use clap::Parser;
 
-pub fn run() -> color_eyre::Result<()> {
+pub fn run() -> color_eyre::Result<ExitCode> {
     let _guard = logging::init();
     color_eyre::install()?;
 
     let cli = cli::Cli::parse();
 
     if cli.command.is_none() {
-        mainui::run_main_ui()
+        mainui::run_main_ui()?;
+        Ok(ExitCode::SUCCESS)
     } else {
         cli::run_main_cli(cli)
     }
 }
 
-fn main() -> color_eyre::Result<()> {
+fn main() -> color_eyre::Result<ExitCode> {
     run()
 }
@@ -0,0 +1,405 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use color_eyre::eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout, Margin, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Padding, Paragraph, Wrap};
+use tracing::{error, info};
+
+use crate::app::AppWorkStatus;
+
+use crate::core::feed::feedparser::{self, FeedCandidate};
+use crate::core::library::feedlibrary::FeedLibrary;
+use crate::core::ui::appscreen::{AppScreen, AppScreenEvent};
+use crate::core::ui::dialog::Dialog;
+
+type Discovery = Arc<Mutex<Option<Result<Vec<FeedCandidate>, String>>>>;
+
+enum AddState {
+    /// Typing the address
+    Input,
+    /// Looking for feeds in the background
+    Searching(Discovery),
+    /// Choosing among the feeds found
+    Picking(Vec<FeedCandidate>, ListState),
+}
+
+/// Adds a feed from a URL, letting the user pick when the address leads to several feeds
+pub struct AddDialog {
+    library: Rc<RefCell<FeedLibrary>>,
+    category: Option<String>,
+    url: String,
+    message: Option<String>,
+    state: AddState,
+}
+
+impl AddDialog {
+    pub fn new(library: Rc<RefCell<FeedLibrary>>, category: Option<String>) -> Self {
+        Self {
+            library,
+            category,
+            url: String::new(),
+            message: None,
+            state: AddState::Input,
+        }
+    }
+
+    fn search(&mut self) {
+        let url = self.url.trim().to_string();
+        if url.is_empty() {
+            return;
+        }
+
+        let discovery: Discovery = Arc::new(Mutex::new(None));
+        let result = discovery.clone();
+        let fetcher = self.library.borrow().fetcher.clone();
+
+        thread::spawn(move || {
+            let candidates =
+                feedparser::discover_feeds(fetcher.as_ref(), &url, None).map_err(|e| e.to_string());
+            *result.lock().unwrap() = Some(candidates);
+        });
+
+        self.message = None;
+        self.state = AddState::Searching(discovery);
+    }
+
+    /// Shows the feeds found once the background search is done
+    fn check_search(&mut self) {
+        let AddState::Searching(discovery) = &self.state else {
+            return;
+        };
+
+        let Some(result) = discovery.lock().unwrap().take() else {
+            return;
+        };
+
+        match result {
+            Ok(candidates) => {
+                self.state =
+                    AddState::Picking(candidates, ListState::default().with_selected(Some(0)));
+            }
+            Err(e) => {
+                self.message = Some(e);
+                self.state = AddState::Input;
+            }
+        }
+    }
+
+    fn add_selected(&mut self) -> AppScreenEvent {
+        let AddState::Picking(mut candidates, state) =
+            std::mem::replace(&mut self.state, AddState::Input)
+        else {
+            return AppScreenEvent::None;
+        };
+
+        let candidate = candidates.swap_remove(state.selected().unwrap_or(0));
+        let mut library = self.library.borrow_mut();
+
+        match library.add_feed_candidate(candidate, &self.category, None) {
+            Ok(feed) => {
+                info!("Feed added: {}", feed.title);
+
+                match library.data.generate_categories_tree() {
+                    Ok(categories) => library.feedcategories = categories,
+                    Err(e) => error!("Couldn't reload the library: {:?}", e),
+                }
+
+                AppScreenEvent::CloseDialog
+            }
+            Err(e) => {
+                self.message = Some(e.to_string());
+                AppScreenEvent::None
+            }
+        }
+    }
+
+    fn render_list(&mut self, frame: &mut ratatui::Frame, area: Rect, theme_base: &[u32]) {
+        let AddState::Picking(candidates, state) = &mut self.state else {
+            return;
+        };
+
+        let items: Vec<ListItem> = candidates
+            .iter()
+            .map(|c| {
+                ListItem::new(format!(
+                    "{} ({})\n  {}",
+                    c.feed.title, c.format, c.feed.feed_url
+                ))
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .style(
+                        Style::default()
+                            .fg(Color::from_u32(theme_base[0x5]))
+                            .bg(Color::from_u32(theme_base[0x1])),
+                    )
+                    .padding(Padding::new(1, 1, 1, 1)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::from_u32(theme_base[0x2]))
+                    .bg(Color::from_u32(theme_base[0x8])),
+            );
+
+        frame.render_stateful_widget(list, area, state);
+    }
+}
+
+impl Dialog for AddDialog {
+    fn get_size(&self) -> ratatui::prelude::Rect {
+        Rect::new(70, 20, 0, 0)
+    }
+
+    fn as_screen(&self) -> &dyn AppScreen {
+        self
+    }
+
+    fn as_screen_mut(&mut self) -> &mut dyn AppScreen {
+        self
+    }
+}
+
+impl AppScreen for AddDialog {
+    fn start(&mut self) {}
+
+    fn quit(&mut self) {}
+
+    fn pause(&mut self) {}
+
+    fn unpause(&mut self) {}
+
+    fn render(&mut self, frame: &mut ratatui::Frame, area: ratatui::prelude::Rect) {
+        self.check_search();
+
+        let theme = {
+            let library = self.library.borrow();
+            library.settings.get_theme().unwrap().clone()
+        };
+
+        let contentlayout = Layout::vertical([
+            Constraint::Length(2),
+            Constraint::Length(3),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+        ])
+        .split(area.inner(Margin::new(2, 1)));
+
+        let title = Paragraph::new(self.get_title())
+            .style(Style::new().fg(Color::from_u32(theme.base[0x8])))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+        let cursor = if matches!(self.state, AddState::Input) {
+            "_"
+        } else {
+            ""
+        };
+        let input = Paragraph::new(format!("{}{}", self.url, cursor))
+            .style(
+                Style::default()
+                    .fg(Color::from_u32(theme.base[0x5]))
+                    .bg(Color::from_u32(theme.base[0x1])),
+            )
+            .block(Block::default().padding(Padding::new(1, 1, 1, 1)));
+
+        let status = match (&self.state, &self.message) {
+            (AddState::Searching(_), _) => String::from("Looking for feeds..."),
+            (_, Some(message)) => message.clone(),
+            (AddState::Picking(candidates, _), None) if candidates.len() > 1 => {
+                format!("{} feeds found, pick one:", candidates.len())
+            }
+            _ => format!(
+                "Category: {}",
+                self.category
+                    .as_deref()
+                    .unwrap_or(crate::core::defs::DATA_CATEGORY_DEFAULT)
+            ),
+        };
+        let status = Paragraph::new(status)
+            .style(Style::new().fg(Color::from_u32(theme.base[0x4])))
+            .wrap(Wrap { trim: true });
+
+        frame.render_widget(title, contentlayout[0]);
+        frame.render_widget(input, contentlayout[1]);
+        frame.render_widget(status, contentlayout[2]);
+        self.render_list(frame, contentlayout[3], &theme.base);
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<AppScreenEvent> {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_keypress(key),
+            Event::Paste(text) if matches!(self.state, AddState::Input) => {
+                self.url.push_str(text.trim());
+                Ok(AppScreenEvent::None)
+            }
+            Event::Mouse(_) => Ok(AppScreenEvent::None),
+            Event::Resize(_, _) => Ok(AppScreenEvent::None),
+            _ => Ok(AppScreenEvent::None),
+        }
+    }
+
+    fn handle_keypress(&mut self, key: KeyEvent) -> Result<AppScreenEvent> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc)
+            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => {
+                Ok(AppScreenEvent::CloseDialog)
+            }
+            _ => match &mut self.state {
+                AddState::Input => {
+                    match (key.modifiers, key.code) {
+                        (_, KeyCode::Enter) => self.search(),
+                        (_, KeyCode::Backspace) => {
+                            self.url.pop();
+                        }
+                        (KeyModifiers::CONTROL, KeyCode::Char('u')) => self.url.clear(),
+                        (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => {
+                            self.url.push(c)
+                        }
+                        _ => {}
+                    }
+                    Ok(AppScreenEvent::None)
+                }
+                AddState::Searching(_) => Ok(AppScreenEvent::None),
+                AddState::Picking(candidates, state) => match key.code {
+                    KeyCode::Enter => Ok(self.add_selected()),
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        if state.selected().unwrap_or(0) < candidates.len().saturating_sub(1) {
+                            state.select_next();
+                        }
+                        Ok(AppScreenEvent::None)
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        state.select_previous();
+                        Ok(AppScreenEvent::None)
+                    }
+                    KeyCode::Backspace => {
+                        self.message = None;
+                        self.state = AddState::Input;
+                        Ok(AppScreenEvent::None)
+                    }
+                    _ => Ok(AppScreenEvent::None),
+                },
+            },
+        }
+    }
+
+    fn get_work_status(&self) -> AppWorkStatus {
+        AppWorkStatus::None
+    }
+
+    fn get_title(&self) -> String {
+        String::from("Add feed")
+    }
+
+    fn get_instructions(&self) -> String {
+        match self.state {
+            AddState::Picking(_, _) => {
+                String::from("j/k: select feed | Enter: add | Backspace: edit URL | Esc: close")
+            }
+            _ => String::from("Type a site or feed URL | Enter: search | Esc: close"),
+        }
+    }
+
+    fn get_full_instructions(&self) -> String {
+        self.get_instructions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::feed::fetcher::fixture::FixtureFetcher;
+    use crossterm::event::KeyEvent;
+
+    fn type_text(dialog: &mut AddDialog, text: &str) {
+        for c in text.chars() {
+            dialog
+                .handle_keypress(KeyEvent::from(KeyCode::Char(c)))
+                .unwrap();
+        }
+    }
+
+    fn wait_for_search(dialog: &mut AddDialog) {
+        for _ in 0..500 {
+            dialog.check_search();
+            if !matches!(dialog.state, AddState::Searching(_)) {
+                return;
+            }
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("search didn't finish");
+    }
+
+    #[test]
+    fn test_pick_and_add_feed() {
+        let page = r#"<!doctype html><html><head>
+<link rel="alternate" type="application/rss+xml" href="/feed.xml">
+<link rel="alternate" type="application/atom+xml" href="/comments.atom">
+</head><body></body></html>"#;
+        let fetcher = FixtureFetcher::default();
+        fetcher
+            .body("https://example.org/", page)
+            .file("https://example.org/feed.xml", "rss20_wordpress.xml")
+            .file("https://example.org/comments.atom", "atom10_xhtml.xml");
+
+        let (mut library, _temp_dir) = FeedLibrary::new_for_test();
+        library.fetcher = Arc::new(fetcher);
+        let library = Rc::new(RefCell::new(library));
+
+        let mut dialog = AddDialog::new(library.clone(), Some("Blogs".to_string()));
+
+        // Unknown addresses are reported, and the URL can be corrected
+        type_text(&mut dialog, "https://example.org/missing");
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Enter))
+            .unwrap();
+        wait_for_search(&mut dialog);
+        assert!(dialog.message.is_some());
+
+        for _ in 0.."missing".len() {
+            dialog
+                .handle_keypress(KeyEvent::from(KeyCode::Backspace))
+                .unwrap();
+        }
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Enter))
+            .unwrap();
+        wait_for_search(&mut dialog);
+
+        let AddState::Picking(candidates, _) = &dialog.state else {
+            panic!("expected a list of feeds");
+        };
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[1].format, "Atom");
+
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Char('j')))
+            .unwrap();
+        let event = dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Enter))
+            .unwrap();
+        assert!(matches!(event, AppScreenEvent::CloseDialog));
+
+        let library = library.borrow();
+        let category = library
+            .feedcategories
+            .iter()
+            .find(|c| c.title == "Blogs")
+            .unwrap();
+        assert_eq!(category.feeds.len(), 1);
+        assert_eq!(
+            category.feeds[0].feed_url,
+            "https://example.org/comments.atom"
+        );
+    }
+}
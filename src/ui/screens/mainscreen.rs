@@ -1,4 +1,4 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Instant};
 
 use color_eyre::Result;
 use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
@@ -13,14 +13,17 @@ use crate::{
     app::AppWorkStatus,
     core::{
         feed::feedentry::FeedEntry,
-        library::feedlibrary::FeedLibrary,
+        library::feedlibrary::{FeedLibrary, UpdateScope},
         ui::{
             appscreen::{AppScreen, AppScreenEvent},
             notification::{AppNotification, NotificationPriority},
         },
     },
     ui::{
-        screens::{readerscreen::ReaderScreen, themedialog::ThemeDialog, urldialog::UrlDialog},
+        screens::{
+            adddialog::AddDialog, readerscreen::ReaderScreen, tagdialog::TagDialog,
+            themedialog::ThemeDialog, urldialog::UrlDialog,
+        },
         states::{
             feedentrystate::FeedEntryState,
             feedtreestate::{FeedItemInfo, FeedTreeState},
@@ -41,6 +44,8 @@ pub struct MainScreen {
     feedtreestate: FeedTreeState,
     feedentrystate: FeedEntryState,
     inputstate: MainInputState,
+    /// When the last update started, for the auto-refresh
+    last_refresh: Instant,
 }
 
 impl MainScreen {
@@ -50,6 +55,41 @@ impl MainScreen {
             feedtreestate: FeedTreeState::new(),
             feedentrystate: FeedEntryState::new(),
             inputstate: MainInputState::Menu,
+            last_refresh: Instant::now(),
+        }
+    }
+
+    /// Updates the selected feed or category, or every feed with `all`
+    fn refresh(&mut self, all: bool) -> Result<AppScreenEvent> {
+        let scope = match self.feedtreestate.get_selected() {
+            Some(FeedItemInfo::Category(t)) if !all => UpdateScope::Category(t.clone()),
+            Some(FeedItemInfo::Item(_, c, s)) if !all => UpdateScope::Feed(c.clone(), s.clone()),
+            _ => UpdateScope::All,
+        };
+
+        let message = if self.library.borrow_mut().start_update(&scope) {
+            self.last_refresh = Instant::now();
+            "Updating feeds"
+        } else {
+            "An update is already running"
+        };
+
+        Ok(AppScreenEvent::Notify(AppNotification::new(
+            message,
+            NotificationPriority::Low,
+        )))
+    }
+
+    fn auto_refresh(&mut self) {
+        let Some(mins) = self.library.borrow().settings.tui.auto_refresh_mins else {
+            return;
+        };
+
+        if mins > 0
+            && self.last_refresh.elapsed().as_secs() >= mins * 60
+            && self.library.borrow_mut().start_update(&UpdateScope::All)
+        {
+            self.last_refresh = Instant::now();
         }
     }
 
@@ -85,8 +125,15 @@ impl MainScreen {
             _ => vec![],
         };
 
-        for entry in entries.iter() {
-            self.library.borrow_mut().data.set_entry_seen(entry);
+        let tag_filter = self.feedentrystate.tag_filter.borrow();
+        let entries = entries
+            .iter()
+            .filter(|e| tag_filter.as_deref().is_none_or(|tag| e.has_category(tag)));
+
+        for entry in entries {
+            if let Err(e) = self.library.borrow_mut().data.set_entry_seen(entry) {
+                error!("Couldn't set entry seen: {:?}", e);
+            }
         }
     }
 
@@ -108,6 +155,35 @@ impl MainScreen {
         ))))
     }
 
+    fn open_add_feed(&self) -> Result<AppScreenEvent> {
+        // New feeds go to the category being browsed
+        let category = match self.feedtreestate.get_selected() {
+            Some(FeedItemInfo::Category(t)) => Some(t.clone()),
+            Some(FeedItemInfo::Item(_, c, _)) => Some(c.clone()),
+            _ => None,
+        };
+
+        Ok(AppScreenEvent::OpenDialog(Box::new(AddDialog::new(
+            self.library.clone(),
+            category,
+        ))))
+    }
+
+    fn open_tag_filter(&self) -> Result<AppScreenEvent> {
+        if self.feedentrystate.tags.is_empty() {
+            return Ok(AppScreenEvent::Notify(AppNotification::new(
+                "No tagged entries here",
+                NotificationPriority::Low,
+            )));
+        }
+
+        Ok(AppScreenEvent::OpenDialog(Box::new(TagDialog::new(
+            self.library.clone(),
+            self.feedentrystate.tag_filter.clone(),
+            self.feedentrystate.tags.clone(),
+        ))))
+    }
+
     fn toggle_read_later(&mut self, entry: &FeedEntry) -> bool {
         let file_path = entry.filepath.to_str().unwrap_or_default();
 
@@ -160,6 +236,7 @@ impl AppScreen for MainScreen {
 
     fn render(&mut self, frame: &mut ratatui::Frame, area: Rect) {
         self.library.borrow_mut().update();
+        self.auto_refresh();
 
         let theme = {
             let library = self.library.borrow();
@@ -300,6 +377,10 @@ impl AppScreen for MainScreen {
                     Ok(AppScreenEvent::None)
                 }
                 (_, KeyCode::Char('t')) => self.open_theme_selector(),
+                (_, KeyCode::Char('#')) => self.open_tag_filter(),
+                (_, KeyCode::Char('a')) => self.open_add_feed(),
+                (_, KeyCode::Char('u')) => self.refresh(false),
+                (_, KeyCode::Char('U')) => self.refresh(true),
                 (_, KeyCode::Char('?')) => Ok(AppScreenEvent::OpenDialog(Box::new(
                     HelpDialog::new(self.get_full_instructions()),
                 ))),
@@ -336,7 +417,9 @@ impl AppScreen for MainScreen {
                 }
                 (_, KeyCode::Enter) => {
                     if let Some(entry) = self.feedentrystate.get_selected() {
-                        self.library.borrow_mut().data.set_entry_seen(&entry);
+                        if let Err(e) = self.library.borrow_mut().data.set_entry_seen(&entry) {
+                            error!("Couldn't set entry seen: {:?}", e);
+                        }
                         self.feedentrystate.set_current_read();
 
                         Ok(AppScreenEvent::ChangeState(Box::new(ReaderScreen::new(
@@ -382,7 +465,9 @@ impl AppScreen for MainScreen {
                 }
                 (_, KeyCode::Char('o')) => {
                     if let Some(entry) = self.feedentrystate.get_selected() {
-                        self.library.borrow_mut().data.set_entry_seen(&entry);
+                        if let Err(e) = self.library.borrow_mut().data.set_entry_seen(&entry) {
+                            error!("Couldn't set entry seen: {:?}", e);
+                        }
                         self.open_external_url(&entry.url)
                     } else {
                         Ok(AppScreenEvent::None)
@@ -405,6 +490,10 @@ impl AppScreen for MainScreen {
                     }
                 }
                 (_, KeyCode::Char('t')) => self.open_theme_selector(),
+                (_, KeyCode::Char('#')) => self.open_tag_filter(),
+                (_, KeyCode::Char('a')) => self.open_add_feed(),
+                (_, KeyCode::Char('u')) => self.refresh(false),
+                (_, KeyCode::Char('U')) => self.refresh(true),
                 (_, KeyCode::Char('?')) => Ok(AppScreenEvent::OpenDialog(Box::new(
                     HelpDialog::new(self.get_full_instructions()),
                 ))),
@@ -443,7 +532,11 @@ impl AppScreen for MainScreen {
                
                r: toggle item read state
                R: mark all of the items as read
+               #: filter entries by tag
                
+               a: add a feed
+               u: update the selected feed or category
+               U: update all feeds
                t: open theme picker
                Esc/q: back from entries or quit"#,
         )
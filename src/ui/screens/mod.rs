@@ -1,6 +1,8 @@
+pub mod adddialog;
 pub mod helpdialog;
 pub mod mainscreen;
 pub mod readerscreen;
+pub mod tagdialog;
 pub mod themedialog;
 pub mod urldialog;
 pub mod welcomedialog;
@@ -1,4 +1,7 @@
-use std::{cell::RefCell, rc::Rc};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+use std::sync::{Arc, Mutex};
+use std::{cell::RefCell, rc::Rc, thread};
 
 use color_eyre::Result;
 use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
@@ -12,9 +15,16 @@ use unicode_width::UnicodeWidthStr;
 
 use crate::app::AppWorkStatus;
 use crate::core::{
-    feed::feedentry::FeedEntry,
-    library::feedlibrary::FeedLibrary,
-    ui::appscreen::{AppScreen, AppScreenEvent},
+    defs,
+    feed::{feedentry::FeedEntry, feedparser},
+    library::{
+        data::{assets, librarydata::LibraryData},
+        feedlibrary::FeedLibrary,
+    },
+    ui::{
+        appscreen::{AppScreen, AppScreenEvent},
+        notification::{AppNotification, NotificationPriority},
+    },
 };
 use crate::ui::screens::themedialog::ThemeDialog;
 use crate::ui::screens::urldialog::UrlDialog;
@@ -28,6 +38,28 @@ pub struct ReaderScreen {
     current_index: usize,
     scroll: usize,
     scrollmax: usize,
+    /// Index of the media picked for `d` and `m`, when the entry has several
+    enclosure: usize,
+    download: Option<EnclosureDownload>,
+    fetch: Option<ContentFetch>,
+    /// Result of background work to show next
+    notification: Option<AppNotification>,
+}
+
+/// An enclosure being downloaded in the background. The thread stores it in the entry's file
+/// itself, so it isn't lost when the reader is left first.
+struct EnclosureDownload {
+    entry: usize,
+    length: u64,
+    progress: Arc<AtomicU64>,
+    result: Arc<Mutex<Option<Result<FeedEntry, String>>>>,
+}
+
+/// A full article being fetched in the background, stored in the entry's file by the thread like
+/// downloads
+struct ContentFetch {
+    entry: usize,
+    result: Arc<Mutex<Option<Result<FeedEntry, String>>>>,
 }
 
 impl ReaderScreen {
@@ -42,6 +74,10 @@ impl ReaderScreen {
             current_index,
             scroll: 0,
             scrollmax: 1,
+            enclosure: 0,
+            download: None,
+            fetch: None,
+            notification: None,
         }
     }
 
@@ -59,10 +95,8 @@ impl ReaderScreen {
         if self.current_index < self.entries.len().saturating_sub(1) {
             self.current_index += 1;
             self.scroll = 0;
-            self.library
-                .borrow_mut()
-                .data
-                .set_entry_seen(&self.entries[self.current_index]);
+            self.enclosure = 0;
+            self.set_current_seen();
         }
     }
 
@@ -70,10 +104,15 @@ impl ReaderScreen {
         if self.current_index > 0 {
             self.current_index -= 1;
             self.scroll = 0;
-            self.library
-                .borrow_mut()
-                .data
-                .set_entry_seen(&self.entries[self.current_index]);
+            self.enclosure = 0;
+            self.set_current_seen();
+        }
+    }
+
+    fn set_current_seen(&self) {
+        let entry = &self.entries[self.current_index];
+        if let Err(e) = self.library.borrow().data.set_entry_seen(entry) {
+            error!("Couldn't set entry seen: {:?}", e);
         }
     }
 
@@ -89,6 +128,229 @@ impl ReaderScreen {
         }
     }
 
+    fn fetch_full_content(&mut self) -> AppScreenEvent {
+        if self.fetch.is_some() {
+            return AppScreenEvent::Notify(AppNotification::new(
+                "The full article is already being fetched",
+                NotificationPriority::Low,
+            ));
+        }
+
+        let entry = &self.entries[self.current_index];
+        let (fetcher, http, datapath) = {
+            let library = self.library.borrow();
+            let http = library
+                .get_entry_feed(entry)
+                .and_then(|f| f.http_for(&entry.url).cloned());
+            (library.fetcher.clone(), http, library.data.path.clone())
+        };
+
+        let fetch = ContentFetch {
+            entry: self.current_index,
+            result: Arc::new(Mutex::new(None)),
+        };
+
+        let url = entry.url.clone();
+        let filepath = entry.filepath.clone();
+        let result = fetch.result.clone();
+
+        thread::spawn(move || {
+            let saved = feedparser::get_article_content(fetcher.as_ref(), &url, http.as_ref())
+                .and_then(|content| {
+                    update_entry(&datapath, &filepath, |entry| {
+                        entry.text = content;
+                        entry.seen = true;
+                    })
+                })
+                .map_err(|e| {
+                    error!("Couldn't fetch full content for '{}': {:?}", url, e);
+                    e.to_string()
+                });
+            *result.lock().unwrap() = Some(saved);
+        });
+
+        self.fetch = Some(fetch);
+
+        AppScreenEvent::Notify(AppNotification::new(
+            "Fetching the full article",
+            NotificationPriority::Low,
+        ))
+    }
+
+    /// Stores the full article in its entry once the background fetch is done
+    fn check_fetch(&mut self) {
+        let Some(fetch) = self.fetch.as_ref() else {
+            return;
+        };
+
+        let Some(result) = fetch.result.lock().unwrap().take() else {
+            return;
+        };
+
+        let index = fetch.entry;
+        let entry = &mut self.entries[index];
+
+        match result {
+            Ok(saved) => {
+                entry.text = saved.text;
+                entry.seen = true;
+                if index == self.current_index {
+                    self.scroll = 0;
+                }
+            }
+            Err(_) => {
+                self.notification = Some(AppNotification::new(
+                    "Couldn't fetch the full article",
+                    NotificationPriority::High,
+                ));
+            }
+        }
+
+        self.fetch = None;
+    }
+
+    /// Picks the next media of the entry for `d` and `m`
+    pub fn next_enclosure(&mut self) {
+        let count = self.entries[self.current_index].enclosures.len();
+        if count > 0 {
+            self.enclosure = (self.enclosure + 1) % count;
+        }
+    }
+
+    fn download_enclosure(&mut self) -> AppScreenEvent {
+        if self.download.is_some() {
+            return AppScreenEvent::Notify(AppNotification::new(
+                "A download is already in progress",
+                NotificationPriority::Low,
+            ));
+        }
+
+        let entry = &self.entries[self.current_index];
+        let Some(enclosure) = entry.enclosures.get(self.enclosure) else {
+            return AppScreenEvent::Notify(AppNotification::new(
+                "This entry has no media",
+                NotificationPriority::Low,
+            ));
+        };
+
+        if enclosure.local.is_some() {
+            return AppScreenEvent::Notify(AppNotification::new(
+                "Media already downloaded",
+                NotificationPriority::Low,
+            ));
+        }
+
+        let Some(entry_dir) = entry.filepath.parent() else {
+            return AppScreenEvent::None;
+        };
+
+        let max_bytes = self
+            .library
+            .borrow()
+            .get_entry_feed(entry)
+            .and_then(|feed| feed.assets.clone())
+            .unwrap_or_default()
+            .max_enclosure_size_mb
+            * 1024
+            * 1024;
+
+        let download = EnclosureDownload {
+            entry: self.current_index,
+            length: enclosure.length,
+            progress: Arc::new(AtomicU64::new(0)),
+            result: Arc::new(Mutex::new(None)),
+        };
+
+        let url = enclosure.url.clone();
+        let entry_dir = entry_dir.to_path_buf();
+        let filepath = entry.filepath.clone();
+        let progress = download.progress.clone();
+        let result = download.result.clone();
+        let (fetcher, datapath) = {
+            let library = self.library.borrow();
+            (library.fetcher.clone(), library.data.path.clone())
+        };
+
+        thread::spawn(move || {
+            let saved = assets::download_enclosure(
+                fetcher.as_ref(),
+                &url,
+                &entry_dir.join(defs::DATA_ASSETS_DIR),
+                max_bytes,
+                &progress,
+            )
+            .and_then(|path| {
+                update_entry(&datapath, &filepath, |entry| {
+                    for enclosure in entry.enclosures.iter_mut().filter(|e| e.url == url) {
+                        enclosure.local = assets::relative_asset_path(&path, &entry_dir);
+                    }
+                })
+            })
+            .map_err(|e| {
+                error!("Couldn't download media '{}': {:?}", url, e);
+                e.to_string()
+            });
+
+            *result.lock().unwrap() = Some(saved);
+        });
+
+        self.download = Some(download);
+
+        AppScreenEvent::Notify(AppNotification::new(
+            "Downloading media",
+            NotificationPriority::Low,
+        ))
+    }
+
+    /// Stores the downloaded file in its entry once the background download is done
+    fn check_download(&mut self) {
+        let Some(download) = self.download.as_ref() else {
+            return;
+        };
+
+        let Some(result) = download.result.lock().unwrap().take() else {
+            return;
+        };
+
+        match result {
+            Ok(saved) => self.entries[download.entry].enclosures = saved.enclosures,
+            Err(_) => {
+                self.notification = Some(AppNotification::new(
+                    "Couldn't download the media",
+                    NotificationPriority::High,
+                ));
+            }
+        }
+
+        self.download = None;
+    }
+
+    fn play_enclosure(&self) -> AppScreenEvent {
+        let entry = &self.entries[self.current_index];
+        let Some(enclosure) = entry.enclosures.get(self.enclosure) else {
+            return AppScreenEvent::Notify(AppNotification::new(
+                "This entry has no media",
+                NotificationPriority::Low,
+            ));
+        };
+
+        let target = match (&enclosure.local, entry.filepath.parent()) {
+            (Some(local), Some(entry_dir)) => entry_dir.join(local).to_string_lossy().to_string(),
+            _ => enclosure.url.clone(),
+        };
+
+        match self.library.borrow().settings.media.play(&target) {
+            Ok(_) => AppScreenEvent::None,
+            Err(e) => {
+                error!("Couldn't play '{}': {:?}", target, e);
+                AppScreenEvent::Notify(AppNotification::new(
+                    "Couldn't start the media player",
+                    NotificationPriority::High,
+                ))
+            }
+        }
+    }
+
     fn increase_reader_width(&mut self) -> color_eyre::Result<()> {
         let mut l = self.library.borrow_mut();
         l.settings.appearance.reader_width = l
@@ -116,6 +378,9 @@ impl AppScreen for ReaderScreen {
     fn start(&mut self) {}
 
     fn render(&mut self, frame: &mut ratatui::Frame, area: ratatui::prelude::Rect) {
+        self.check_download();
+        self.check_fetch();
+
         let theme = {
             let library = self.library.borrow();
             library.settings.get_theme().unwrap().clone()
@@ -138,16 +403,17 @@ impl AppScreen for ReaderScreen {
         .margin(2)
         .split(area);
 
+        let current_entry = &self.entries[self.current_index];
+
         let contentlayout = Layout::vertical([
-            Constraint::Length(1), // Title
-            Constraint::Length(1), // Date
-            Constraint::Length(2), // URL
-            Constraint::Fill(1),   // Content
+            Constraint::Length(1),                                         // Title
+            Constraint::Length(1),                                         // Date
+            Constraint::Length(2),                                         // URL
+            Constraint::Length(current_entry.enclosures.len() as u16 * 2), // Media
+            Constraint::Fill(1),                                           // Content
         ])
         .split(sizelayout[1]);
 
-        let current_entry = &self.entries[self.current_index];
-
         // Title
         let title = Paragraph::new(current_entry.title.as_str())
             .style(Style::new().fg(Color::from_u32(theme.base[0x8])))
@@ -157,17 +423,26 @@ impl AppScreen for ReaderScreen {
         frame.render_widget(title, contentlayout[0]);
 
         // Date
-        let date = Paragraph::new(format!(
+        let mut header = format!(
             "\u{f0520} {} | \u{f09e} {}",
             current_entry
                 .date
                 .with_timezone(&chrono::Local)
                 .format("%Y-%m-%d"),
             current_entry.author
-        ))
-        .style(Style::new().fg(Color::from_u32(theme.base[3])))
-        .alignment(Alignment::Center)
-        .wrap(Wrap { trim: true });
+        );
+
+        if !current_entry.categories.is_empty() {
+            header.push_str(&format!(
+                " | \u{f04f9} {}",
+                current_entry.categories.join(", ")
+            ));
+        }
+
+        let date = Paragraph::new(header)
+            .style(Style::new().fg(Color::from_u32(theme.base[3])))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
 
         frame.render_widget(date, contentlayout[1]);
 
@@ -181,9 +456,33 @@ impl AppScreen for ReaderScreen {
 
         frame.render_widget(date, contentlayout[2]);
 
+        // Media
+        if !current_entry.enclosures.is_empty() {
+            let media: Vec<String> = current_entry
+                .enclosures
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    let marker = if current_entry.enclosures.len() > 1 && i == self.enclosure {
+                        "\u{f0da}"
+                    } else {
+                        " "
+                    };
+                    format!("{marker} \u{f0d8} {}\n", e.summary())
+                })
+                .collect();
+
+            let media = Paragraph::new(media.concat())
+                .style(Style::new().fg(Color::from_u32(theme.base[3])))
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true });
+
+            frame.render_widget(media, contentlayout[3]);
+        }
+
         // Content
         let text = tuimarkdown::from_str(&current_entry.text, Some(theme.clone()));
-        let textheight = text.height() as usize;
+        let textheight = text.height();
 
         // This is a workaround to get more or less the amount of wrapped lines, to be used on the
         // scrollbar
@@ -195,12 +494,12 @@ impl AppScreen for ReaderScreen {
                 .map(|span| span.content.to_string())
                 .collect();
             let line_width = UnicodeWidthStr::width(content.as_str());
-            let wrapped = line_width.div_ceil(contentlayout[3].width as usize);
+            let wrapped = line_width.div_ceil(contentlayout[4].width as usize);
             wrapped_lines += wrapped - wrapped.min(1);
         }
 
         let scrollheight = textheight + (wrapped_lines as f32 * 1.06) as usize + 4;
-        self.scrollmax = scrollheight - (contentlayout[3].height as usize).min(scrollheight);
+        self.scrollmax = scrollheight - (contentlayout[4].height as usize).min(scrollheight);
 
         // Content Paragraph component
         let paragraph = Paragraph::new(text)
@@ -208,7 +507,7 @@ impl AppScreen for ReaderScreen {
             .alignment(Alignment::Left)
             .wrap(Wrap { trim: true });
 
-        frame.render_widget(paragraph, contentlayout[3]);
+        frame.render_widget(paragraph, contentlayout[4]);
 
         // Scrollbar
         let mut scrollbarstate = ScrollbarState::new(self.scrollmax).position(self.scroll);
@@ -257,6 +556,13 @@ impl AppScreen for ReaderScreen {
             (_, KeyCode::Char('o')) => {
                 self.open_external_url(&self.entries[self.current_index].url)
             }
+            (_, KeyCode::Char('f')) => Ok(self.fetch_full_content()),
+            (_, KeyCode::Char('d')) => Ok(self.download_enclosure()),
+            (_, KeyCode::Char('m')) => Ok(self.play_enclosure()),
+            (_, KeyCode::Char('e')) => {
+                self.next_enclosure();
+                Ok(AppScreenEvent::None)
+            }
             (_, KeyCode::Char('n')) => {
                 self.next_entry();
                 Ok(AppScreenEvent::None)
@@ -298,7 +604,29 @@ impl AppScreen for ReaderScreen {
     }
 
     fn get_work_status(&self) -> AppWorkStatus {
-        AppWorkStatus::None
+        match &self.download {
+            Some(download) => {
+                let progress = download.progress.load(Relaxed);
+                let ratio = if download.length > 0 {
+                    (progress as f32 / download.length as f32).min(1.0)
+                } else {
+                    0.0
+                };
+
+                AppWorkStatus::Working(
+                    ratio,
+                    format!("Downloading media: {:.1} MB", progress as f64 / 1048576.0),
+                )
+            }
+            None if self.fetch.is_some() => {
+                AppWorkStatus::Working(0.0, String::from("Fetching the full article"))
+            }
+            None => AppWorkStatus::None,
+        }
+    }
+
+    fn take_notification(&mut self) -> Option<AppNotification> {
+        self.notification.take()
     }
 
     fn get_full_instructions(&self) -> String {
@@ -309,6 +637,10 @@ impl AppScreen for ReaderScreen {
                
                n/p: next/previous entry
                o: open externally
+               f: fetch the full article from its page
+               e: pick the next media, when the entry has several
+               d: download the picked media
+               m: play the picked media
                
                t: open theme picker
                Esc/q: leave"#,
@@ -316,9 +648,24 @@ impl AppScreen for ReaderScreen {
     }
 }
 
+/// Changes the entry stored at `filepath` as it is on disk now, since the reader may have saved
+/// it since the background work started
+fn update_entry(
+    datapath: &Path,
+    filepath: &Path,
+    change: impl FnOnce(&mut FeedEntry),
+) -> color_eyre::Result<FeedEntry> {
+    let data = LibraryData::new(datapath);
+    let mut entry = data.load_feed_entry(filepath)?;
+    change(&mut entry);
+    data.save_feed_entry(&entry)?;
+    Ok(entry)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::feed::fetcher::fixture::FixtureFetcher;
     use crate::core::library::feedlibrary::FeedLibrary;
 
     fn create_test_entries() -> Vec<FeedEntry> {
@@ -363,4 +710,93 @@ mod tests {
         reader_screen.previous_entry();
         assert_eq!(reader_screen.current_index, 0);
     }
+
+    /// A reader for a stored entry linking to `url`, with `fetcher` answering its requests
+    fn reader_with_stored_entry(
+        fetcher: FixtureFetcher,
+        url: &str,
+    ) -> (ReaderScreen, FeedEntry, tempfile::TempDir) {
+        let (mut library, temp_dir) = FeedLibrary::new_for_test();
+        library.fetcher = Arc::new(fetcher);
+
+        let entry = FeedEntry {
+            title: "Post".to_string(),
+            url: url.to_string(),
+            text: "Just a summary".to_string(),
+            filepath: temp_dir.path().join("post.md"),
+            ..Default::default()
+        };
+        library.data.save_feed_entry(&entry).unwrap();
+
+        let reader = ReaderScreen::new(Rc::new(RefCell::new(library)), vec![entry.clone()], 0);
+        (reader, entry, temp_dir)
+    }
+
+    fn wait_for<T>(result: &Arc<Mutex<Option<T>>>) {
+        while result.lock().unwrap().is_none() {
+            thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_full_content_is_kept_after_leaving() {
+        let text = "A paragraph long enough to count as the content of the article, and more.";
+        let fetcher = FixtureFetcher::default();
+        fetcher.body(
+            "https://example.org/post",
+            &format!("<html><body><article><p>{text}</p><p>{text}</p></article></body></html>"),
+        );
+        let (mut reader, entry, temp_dir) =
+            reader_with_stored_entry(fetcher, "https://example.org/post");
+
+        reader.fetch_full_content();
+        let result = reader.fetch.as_ref().unwrap().result.clone();
+        reader.quit();
+        drop(reader);
+        wait_for(&result);
+
+        let saved = LibraryData::new(temp_dir.path())
+            .load_feed_entry(&entry.filepath)
+            .unwrap();
+        assert!(saved.text.contains(text));
+        assert!(saved.seen);
+    }
+
+    #[test]
+    fn test_failed_fetch_is_notified() {
+        let fetcher = FixtureFetcher::default();
+        fetcher.status("https://example.org/post", 404);
+        let (mut reader, entry, _temp_dir) =
+            reader_with_stored_entry(fetcher, "https://example.org/post");
+
+        reader.fetch_full_content();
+        wait_for(&reader.fetch.as_ref().unwrap().result.clone());
+        reader.check_fetch();
+
+        assert!(reader.fetch.is_none());
+        assert!(reader.take_notification().is_some());
+        assert!(reader.take_notification().is_none());
+        assert_eq!(reader.entries[0].text, entry.text);
+    }
+
+    #[test]
+    fn test_pick_enclosure() {
+        let (library, _temp_dir) = FeedLibrary::new_for_test();
+        let mut entries = create_test_entries();
+        entries[0].enclosures = vec![Default::default(), Default::default()];
+        let mut reader_screen = ReaderScreen::new(Rc::new(RefCell::new(library)), entries, 0);
+
+        assert_eq!(reader_screen.enclosure, 0);
+        reader_screen.next_enclosure();
+        assert_eq!(reader_screen.enclosure, 1);
+        reader_screen.next_enclosure();
+        assert_eq!(reader_screen.enclosure, 0);
+
+        reader_screen.next_enclosure();
+        reader_screen.next_entry();
+        assert_eq!(reader_screen.enclosure, 0);
+        // Entries without media keep nothing picked
+        reader_screen.next_enclosure();
+        assert_eq!(reader_screen.enclosure, 0);
+    }
 }
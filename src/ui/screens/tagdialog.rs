@@ -0,0 +1,222 @@
+use std::{cell::RefCell, rc::Rc};
+
+use color_eyre::eyre::Result;
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout, Margin, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{
+    Block, List, ListItem, ListState, Padding, Paragraph, Scrollbar, ScrollbarOrientation,
+    ScrollbarState, Wrap,
+};
+
+use crate::app::AppWorkStatus;
+
+use crate::core::library::feedlibrary::FeedLibrary;
+use crate::core::ui::appscreen::{AppScreen, AppScreenEvent};
+use crate::core::ui::dialog::Dialog;
+
+const ALL_TAGS: &str = "All entries";
+
+/// Picks the category used to filter the entry list
+pub struct TagDialog {
+    library: Rc<RefCell<FeedLibrary>>,
+    filter: Rc<RefCell<Option<String>>>,
+    tags: Vec<String>,
+    state: ListState,
+}
+
+impl TagDialog {
+    pub fn new(
+        library: Rc<RefCell<FeedLibrary>>,
+        filter: Rc<RefCell<Option<String>>>,
+        tags: Vec<String>,
+    ) -> Self {
+        let selected = filter
+            .borrow()
+            .as_ref()
+            .and_then(|current| tags.iter().position(|t| t == current))
+            .map_or(0, |i| i + 1);
+
+        Self {
+            library,
+            filter,
+            tags,
+            state: ListState::default().with_selected(Some(selected)),
+        }
+    }
+
+    fn apply(&self) {
+        let selected = self.state.selected().unwrap_or(0);
+        let tag = selected
+            .checked_sub(1)
+            .and_then(|i| self.tags.get(i))
+            .cloned();
+
+        self.filter.replace(tag);
+    }
+
+    fn len(&self) -> usize {
+        self.tags.len() + 1
+    }
+}
+
+impl Dialog for TagDialog {
+    fn get_size(&self) -> ratatui::prelude::Rect {
+        Rect::new(50, 20, 0, 0)
+    }
+
+    fn as_screen(&self) -> &dyn AppScreen {
+        self
+    }
+
+    fn as_screen_mut(&mut self) -> &mut dyn AppScreen {
+        self
+    }
+}
+
+impl AppScreen for TagDialog {
+    fn start(&mut self) {}
+
+    fn quit(&mut self) {}
+
+    fn pause(&mut self) {}
+
+    fn unpause(&mut self) {}
+
+    fn render(&mut self, frame: &mut ratatui::Frame, area: ratatui::prelude::Rect) {
+        let theme = {
+            let library = self.library.borrow();
+            library.settings.get_theme().unwrap().clone()
+        };
+
+        let contentlayout = Layout::vertical([Constraint::Length(2), Constraint::Fill(1)])
+            .split(area.inner(Margin::new(2, 1)));
+
+        let title = Paragraph::new(self.get_title())
+            .style(Style::new().fg(Color::from_u32(theme.base[0x8])))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true });
+
+        // List
+        let chunks = Layout::horizontal([Constraint::Fill(1), Constraint::Length(1)])
+            .split(contentlayout[1]);
+
+        let items: Vec<ListItem> = std::iter::once(ALL_TAGS.to_string())
+            .chain(self.tags.iter().map(|t| format!("\u{f04f9} {t}")))
+            .map(ListItem::new)
+            .collect();
+
+        let taglist = List::new(items)
+            .block(
+                Block::default()
+                    .style(
+                        Style::default()
+                            .fg(Color::from_u32(theme.base[0x5]))
+                            .bg(Color::from_u32(theme.base[0x1])),
+                    )
+                    .padding(Padding::new(1, 1, 1, 1)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::from_u32(theme.base[0x2]))
+                    .bg(Color::from_u32(theme.base[0x8])),
+            );
+
+        frame.render_widget(title, contentlayout[0]);
+        frame.render_stateful_widget(taglist, chunks[0], &mut self.state);
+
+        // Scrollbar
+        let mut scrollbarstate = ScrollbarState::new(self.len().saturating_sub(1))
+            .position(self.state.selected().unwrap_or(0));
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight).style(
+            Style::new()
+                .fg(Color::from_u32(theme.base[3]))
+                .bg(Color::from_u32(theme.base[2])),
+        );
+        frame.render_stateful_widget(scrollbar, chunks[1], &mut scrollbarstate);
+    }
+
+    fn handle_event(&mut self, event: Event) -> Result<AppScreenEvent> {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_keypress(key),
+            Event::Mouse(_) => Ok(AppScreenEvent::None),
+            Event::Resize(_, _) => Ok(AppScreenEvent::None),
+            _ => Ok(AppScreenEvent::None),
+        }
+    }
+
+    fn handle_keypress(&mut self, key: KeyEvent) -> Result<AppScreenEvent> {
+        match (key.modifiers, key.code) {
+            (_, KeyCode::Esc | KeyCode::Char('q'))
+            | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => {
+                Ok(AppScreenEvent::CloseDialog)
+            }
+            (_, KeyCode::Enter) => {
+                self.apply();
+                Ok(AppScreenEvent::CloseDialog)
+            }
+            (_, KeyCode::Down | KeyCode::Char('j')) => {
+                if self.state.selected().unwrap_or(0) < self.len().saturating_sub(1) {
+                    self.state.select_next();
+                }
+                Ok(AppScreenEvent::None)
+            }
+            (_, KeyCode::Up | KeyCode::Char('k')) => {
+                self.state.select_previous();
+                Ok(AppScreenEvent::None)
+            }
+            _ => Ok(AppScreenEvent::None),
+        }
+    }
+
+    fn get_work_status(&self) -> AppWorkStatus {
+        AppWorkStatus::None
+    }
+
+    fn get_title(&self) -> String {
+        String::from("Filter by tag")
+    }
+
+    fn get_instructions(&self) -> String {
+        String::from("j/k: select tag | Enter: apply | Esc/q: close")
+    }
+
+    fn get_full_instructions(&self) -> String {
+        self.get_instructions()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    #[test]
+    fn test_select_and_clear_filter() {
+        let (library, _temp_dir) = FeedLibrary::new_for_test();
+        let library = Rc::new(RefCell::new(library));
+        let filter = Rc::new(RefCell::new(None));
+        let tags = vec!["atom".to_string(), "rust".to_string()];
+
+        let mut dialog = TagDialog::new(library.clone(), filter.clone(), tags.clone());
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Char('j')))
+            .unwrap();
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Char('j')))
+            .unwrap();
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Enter))
+            .unwrap();
+        assert_eq!(filter.borrow().as_deref(), Some("rust"));
+
+        // Opens on the current filter, and the first item clears it
+        let mut dialog = TagDialog::new(library, filter.clone(), tags);
+        assert_eq!(dialog.state.selected(), Some(2));
+        dialog.state.select(Some(0));
+        dialog
+            .handle_keypress(KeyEvent::from(KeyCode::Enter))
+            .unwrap();
+        assert_eq!(*filter.borrow(), None);
+    }
+}
@@ -20,6 +20,10 @@ pub struct FeedEntryState {
     pub listatate: ListState,
     pub previous_selected: String,
     pub library: Option<Rc<RefCell<FeedLibrary>>>,
+    /// Only entries with this category are listed when set. It's shared with the tag picker
+    pub tag_filter: Rc<RefCell<Option<String>>>,
+    /// Categories of the entries of the current selection, before filtering
+    pub tags: Vec<String>,
     theme: Theme,
 }
 
@@ -36,12 +40,15 @@ impl FeedEntryState {
             listatate: ListState::default().with_selected(Some(0)),
             previous_selected: String::new(),
             library: None,
+            tag_filter: Rc::new(RefCell::new(None)),
+            tags: vec![],
             theme: Theme::default(),
         }
     }
 
     pub fn update(&mut self, library: &mut FeedLibrary, treestate: &FeedTreeState) {
         let prev = self.previous_selected.to_string();
+        let selected_path = self.get_selected().map(|e| e.filepath);
         self.theme = library.settings.get_theme().unwrap().clone();
 
         self.entries = match treestate.get_selected() {
@@ -78,8 +85,24 @@ impl FeedEntryState {
             _ => vec![],
         };
 
+        self.tags = collect_tags(&self.entries);
+
+        // The tag filter only applies to the feed or category it was picked for
+        if prev != self.previous_selected {
+            self.tag_filter.replace(None);
+        }
+
+        if let Some(tag) = self.tag_filter.borrow().as_deref() {
+            self.entries.retain(|e| e.has_category(tag));
+        }
+
         if prev != self.previous_selected {
             self.listatate.select_first();
+        } else if let Some(path) = selected_path
+            && let Some(i) = self.entries.iter().position(|e| e.filepath == path)
+        {
+            // New entries arrived above the selected one
+            self.listatate.select(Some(i));
         }
     }
 
@@ -98,17 +121,22 @@ impl FeedEntryState {
                         ""
                     };
 
+                let star_icon = if entry.starred { " \u{f005}" } else { "" };
+
                 // Title
                 if !entry.seen {
                     item_content_lines.push(Line::from(Span::styled(
-                        format!(" \u{f1ea} {}{} \u{e3e3}", entry.title, read_later_icon),
+                        format!(
+                            " \u{f1ea} {}{}{} \u{e3e3}",
+                            entry.title, star_icon, read_later_icon
+                        ),
                         Style::default()
                             .bold()
                             .fg(Color::from_u32(self.theme.base[9])),
                     )));
                 } else {
                     item_content_lines.push(Line::from(Span::styled(
-                        format!(" \u{f1ea} {}{}", entry.title, read_later_icon),
+                        format!(" \u{f1ea} {}{}{}", entry.title, star_icon, read_later_icon),
                         Style::default()
                             .bold()
                             .fg(Color::from_u32(self.theme.base[6])),
@@ -116,14 +144,23 @@ impl FeedEntryState {
                 };
 
                 // Date
-                item_content_lines.push(Line::from(Span::styled(
+                let mut date_spans = vec![Span::styled(
                     format!(
                         " \u{f0520} {} | \u{f09e} {}",
                         entry.date.with_timezone(&chrono::Local).format("%Y-%m-%d"),
                         entry.author
                     ),
                     Style::default().fg(Color::from_u32(self.theme.base[5])),
-                )));
+                )];
+
+                if !entry.categories.is_empty() {
+                    date_spans.push(Span::styled(
+                        format!(" | \u{f04f9} {}", entry.categories.join(", ")),
+                        Style::default().fg(Color::from_u32(self.theme.base[4])),
+                    ));
+                }
+
+                item_content_lines.push(Line::from(date_spans));
 
                 // Description
                 item_content_lines.push(Line::from(Span::styled(
@@ -213,3 +250,41 @@ impl FeedEntryState {
         }
     }
 }
+
+/// Sorted list of the distinct categories of `entries`
+pub fn collect_tags(entries: &[FeedEntry]) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+
+    for category in entries.iter().flat_map(|e| e.categories.iter()) {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(category)) {
+            tags.push(category.to_string());
+        }
+    }
+
+    tags.sort_by_key(|t| t.to_lowercase());
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_tags() {
+        let entries = vec![
+            FeedEntry {
+                categories: vec!["rust".to_string(), "Linux".to_string()],
+                ..Default::default()
+            },
+            FeedEntry {
+                categories: vec!["Rust".to_string(), "atom".to_string()],
+                ..Default::default()
+            },
+            FeedEntry::default(),
+        ];
+
+        assert_eq!(collect_tags(&entries), vec!["atom", "Linux", "rust"]);
+        assert!(entries[1].has_category("RUST"));
+        assert!(!entries[2].has_category("rust"));
+    }
+}
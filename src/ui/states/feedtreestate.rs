@@ -3,6 +3,7 @@ use tracing::error;
 
 use crate::core::library::feedlibrary::FeedLibrary;
 
+#[derive(Clone, Debug)]
 pub enum FeedItemInfo {
     /// Represents the category title
     Category(String),
@@ -14,6 +15,21 @@ pub enum FeedItemInfo {
     ReadLater,
 }
 
+impl FeedItemInfo {
+    /// Whether both point to the same category or feed, even if its title changed
+    pub fn is_same(&self, other: &FeedItemInfo) -> bool {
+        match (self, other) {
+            (FeedItemInfo::Category(a), FeedItemInfo::Category(b)) => a == b,
+            (FeedItemInfo::Item(_, a_cat, a_slug), FeedItemInfo::Item(_, b_cat, b_slug)) => {
+                a_cat == b_cat && a_slug == b_slug
+            }
+            (FeedItemInfo::Separator, FeedItemInfo::Separator)
+            | (FeedItemInfo::ReadLater, FeedItemInfo::ReadLater) => true,
+            _ => false,
+        }
+    }
+}
+
 pub struct FeedTreeState {
     pub treeitems: Vec<FeedItemInfo>,
     pub listatate: ListState,
@@ -34,6 +50,10 @@ impl FeedTreeState {
     }
 
     pub fn update(&mut self, library: &mut FeedLibrary) {
+        let selected = self
+            .listatate
+            .selected()
+            .and_then(|i| self.treeitems.get(i).cloned());
         self.treeitems.clear();
 
         for category in library.feedcategories.iter() {
@@ -53,6 +73,16 @@ impl FeedTreeState {
             self.treeitems.push(FeedItemInfo::Separator);
             self.treeitems.push(FeedItemInfo::ReadLater);
         }
+
+        // Keeps the selection on the same item when the library was reloaded
+        if let Some(selected) = selected
+            && let Some(i) = self
+                .treeitems
+                .iter()
+                .position(|item| item.is_same(&selected))
+        {
+            self.listatate.select(Some(i));
+        }
     }
 
     pub fn get_items(&self, library: &mut FeedLibrary) -> Vec<ListItem<'_>> {
@@ -159,3 +189,44 @@ impl FeedTreeState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::library::feeditem::FeedItem;
+
+    fn add_feed(library: &mut FeedLibrary, category: &str, slug: &str) {
+        library
+            .data
+            .feed_create(&FeedItem {
+                title: slug.to_string(),
+                slug: slug.to_string(),
+                category: category.to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        library.feedcategories = library.data.generate_categories_tree().unwrap();
+    }
+
+    #[test]
+    fn test_selection_survives_reload() {
+        let (mut library, _temp_dir) = FeedLibrary::new_for_test();
+        add_feed(&mut library, "News", "b-feed");
+
+        let mut state = FeedTreeState::new();
+        state.update(&mut library);
+        state.select_next();
+        assert!(matches!(state.get_selected(), Some(FeedItemInfo::Item(_, _, s)) if s == "b-feed"));
+
+        // Feeds and categories showing up before the selection don't move it
+        add_feed(&mut library, "News", "a-feed");
+        add_feed(&mut library, "Blogs", "c-feed");
+        library.feedcategories.sort_by(|a, b| a.title.cmp(&b.title));
+        for category in library.feedcategories.iter_mut() {
+            category.feeds.sort_by(|a, b| a.slug.cmp(&b.slug));
+        }
+
+        state.update(&mut library);
+        assert!(matches!(state.get_selected(), Some(FeedItemInfo::Item(_, _, s)) if s == "b-feed"));
+    }
+}
@@ -10,7 +10,9 @@ use itertools::{Itertools, Position};
 use pulldown_cmark::{
     BlockQuoteKind, CodeBlockKind, CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd,
 };
-use ratatui::style::Style;
+use ratatui::backend::IntoCrossterm;
+use ratatui::crossterm::style::{Attribute, ContentStyle};
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span, Text};
 use syntect::{
     easy::HighlightLines,
@@ -37,6 +39,49 @@ pub fn from_str(input: &str, theme: Option<Theme>) -> Text<'_> {
     writer.text
 }
 
+/// Renders `text` with ANSI escape codes, for printing to a terminal outside of the TUI
+pub fn to_ansi(text: &Text) -> String {
+    let mut out = String::new();
+
+    for line in text.lines.iter() {
+        for span in line.spans.iter() {
+            let style = text.style.patch(line.style).patch(span.style);
+            out.push_str(&content_style(style).apply(&span.content).to_string());
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+fn content_style(style: Style) -> ContentStyle {
+    let modifiers = [
+        (Modifier::BOLD, Attribute::Bold),
+        (Modifier::DIM, Attribute::Dim),
+        (Modifier::ITALIC, Attribute::Italic),
+        (Modifier::UNDERLINED, Attribute::Underlined),
+        (Modifier::SLOW_BLINK, Attribute::SlowBlink),
+        (Modifier::RAPID_BLINK, Attribute::RapidBlink),
+        (Modifier::REVERSED, Attribute::Reverse),
+        (Modifier::HIDDEN, Attribute::Hidden),
+        (Modifier::CROSSED_OUT, Attribute::CrossedOut),
+    ];
+
+    let mut content = ContentStyle {
+        foreground_color: style.fg.map(IntoCrossterm::into_crossterm),
+        background_color: style.bg.map(IntoCrossterm::into_crossterm),
+        ..Default::default()
+    };
+
+    for (modifier, attribute) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            content.attributes.set(attribute);
+        }
+    }
+
+    content
+}
+
 // Heading attributes collected from pulldown-cmark to render after the heading text.
 struct HeadingMeta<'a> {
     id: Option<CowStr<'a>>,
@@ -589,6 +634,19 @@ mod tests {
         subscriber::set_default(subscriber)
     }
 
+    #[rstest]
+    fn ansi_output(_with_tracing: DefaultGuard) {
+        let text = Text::from(Line::from(vec![
+            Span::raw("plain "),
+            Span::styled("bold", Style::new().bold().fg(Color::Red)),
+        ]));
+
+        assert_eq!(
+            to_ansi(&text),
+            "plain \u{1b}[38;5;1m\u{1b}[1mbold\u{1b}[0m\n"
+        );
+    }
+
     #[rstest]
     fn empty(_with_tracing: DefaultGuard) {
         assert_eq!(from_str("", None), Text::default());